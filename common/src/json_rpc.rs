@@ -13,6 +13,66 @@ use serde::Serialize;
 pub struct JsonRpcError {
     pub code: i32,
     pub message: String,
+    /// Structured details of the error, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl From<String> for JsonRpcError {
+    /// A server error (`-32000`) with `message`.
+    fn from(message: String) -> Self {
+        Self {
+            code: -32000,
+            message,
+            data: None,
+        }
+    }
+}
+
+impl From<&str> for JsonRpcError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+/// The ways a `json-rpc` request can fail.
+#[derive(Debug)]
+pub enum JsonRpcClientError {
+    /// No connection to the server, the request was not sent
+    Connect(String),
+    /// The response did not arrive in time, the server may still process the request
+    Timeout(String),
+    /// The request or the response failed in transit
+    Transport(String),
+    /// The response is not a json-rpc response with the expected result type
+    Response(String),
+    /// The server answered with an error
+    Rpc(JsonRpcError),
+    /// The server answered without a result
+    NoResult,
+}
+
+impl std::fmt::Display for JsonRpcClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect(err)
+            | Self::Timeout(err)
+            | Self::Transport(err)
+            | Self::Response(err) => {
+                write!(f, "{err}")
+            }
+            Self::Rpc(err) => write!(f, "{}", err.message),
+            Self::NoResult => write!(f, "no result in response"),
+        }
+    }
+}
+
+impl std::error::Error for JsonRpcClientError {}
+
+impl From<JsonRpcClientError> for String {
+    fn from(err: JsonRpcClientError) -> Self {
+        err.to_string()
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -46,6 +106,17 @@ pub async fn jsonrpc_request_client<T: Serialize + Send + Sync, R: DeserializeOw
     method: &str,
     params: T,
 ) -> Result<R, String> {
+    Ok(jsonrpc_call(timeout, client, uri, method, params).await?)
+}
+
+/// Like `jsonrpc_request_client` but tells apart why the request failed.
+pub async fn jsonrpc_call<T: Serialize + Send + Sync, R: DeserializeOwned>(
+    timeout: u64,
+    client: &hyper::Client<HttpConnector>,
+    uri: &Uri,
+    method: &str,
+    params: T,
+) -> Result<R, JsonRpcClientError> {
     #[derive(Debug, Deserialize)]
     struct JsonRpcResponseInternal<T> {
         result: Option<T>,
@@ -67,27 +138,31 @@ pub async fn jsonrpc_request_client<T: Serialize + Send + Sync, R: DeserializeOw
 
     log::trace!("jsonrpc_request_client: {} {}", uri, method);
 
-    let json = tokio::time::timeout(std::time::Duration::from_millis(timeout), async {
-        let err_str = &uri.to_string();
-        let resp = client.request(node_req).await.expect(err_str);
-        let body = hyper::body::aggregate(resp).await.expect(err_str);
-        let json: JsonRpcResponseInternal<R> =
-            serde_json::from_reader(body.reader()).expect(err_str);
-
-        json
+    let transport_err =
+        |err: &dyn std::fmt::Display| format!("jsonrpc: uri={uri} method={method} error={err}");
+    let body = tokio::time::timeout(std::time::Duration::from_millis(timeout), async {
+        let resp = client.request(node_req).await?;
+        hyper::body::aggregate(resp).await
     })
     .await
-    .map_err(|err| format!("jsonrpc: uri={uri} method={method} error={err}"))?;
+    .map_err(|err| JsonRpcClientError::Timeout(transport_err(&err)))?
+    .map_err(|err| match err.is_connect() {
+        true => JsonRpcClientError::Connect(transport_err(&err)),
+        false => JsonRpcClientError::Transport(transport_err(&err)),
+    })?;
 
-    if json.error.is_some() {
-        return Err(json.error.unwrap().message);
-    }
+    let json: JsonRpcResponseInternal<R> =
+        serde_json::from_reader(body.reader()).map_err(|err| {
+            JsonRpcClientError::Response(format!(
+                "invalid response: uri={uri} method={method} error={err}"
+            ))
+        })?;
 
-    if json.result.is_none() {
-        return Err("no result in response".to_string());
+    if let Some(err) = json.error {
+        return Err(JsonRpcClientError::Rpc(err));
     }
 
-    Ok(json.result.unwrap())
+    json.result.ok_or(JsonRpcClientError::NoResult)
 }
 
 pub async fn jsonrpc_request<T: Serialize + Send + Sync, R: DeserializeOwned>(
@@ -1,6 +1,8 @@
-use eth_types::{Bytes, U256};
+use eth_types::{Bytes, H256, U256};
 use serde::{Deserialize, Serialize};
 
+use crate::json_rpc::JsonRpcError;
+
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct ProofResult {
     /// The halo2 transcript
@@ -9,8 +11,12 @@ pub struct ProofResult {
     pub instance: Vec<U256>,
     /// k of circuit parameters
     pub k: u8,
-    /// Randomness used
+    /// Seed of the `StdRng` for the blinding factors of `create_proof`,
+    /// only set for `ProofRandomness::Fixed`
     pub randomness: Bytes,
+    /// Source of the blinding factors used by `create_proof`
+    #[serde(default)]
+    pub rng: Option<ProofRandomness>,
     /// Circuit name / identifier
     pub label: String,
     /// Auxiliary
@@ -24,11 +30,23 @@ impl std::fmt::Debug for ProofResult {
             .field("instance", &self.instance)
             .field("k", &self.k)
             .field("randomness", &format!("{}", &self.randomness))
+            .field("rng", &self.rng)
             .field("aux", &format!("{:#?}", self.aux))
             .finish()
     }
 }
 
+/// Source of the blinding factors used for proof generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofRandomness {
+    /// A rng with a fixed seed. Proofs are reproducible but not zero-knowledge,
+    /// only meant for testing.
+    Fixed,
+    /// Randomness from the operating system
+    Os,
+}
+
 /// Timing information in milliseconds.
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct ProofResultInstrumentation {
@@ -58,6 +76,15 @@ pub struct Proofs {
     pub aggregation: ProofResult,
     /// Gas used. Determines the upper ceiling for circuit parameters
     pub gas: u64,
+    /// The hash of the proven block
+    #[serde(default)]
+    pub block_hash: H256,
+    /// The state root of the parent block
+    #[serde(default)]
+    pub prev_state_root: H256,
+    /// The state root of the proven block
+    #[serde(default)]
+    pub state_root: H256,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -67,6 +94,10 @@ pub struct ProofRequestOptions {
     pub circuit: String,
     /// the block number
     pub block: u64,
+    /// The hash of `block`, the witness is fetched by hash if the source supports it.
+    /// The task fails if the block of `rpc` has a different hash.
+    #[serde(default)]
+    pub block_hash: Option<H256>,
     /// the rpc url
     pub rpc: String,
     /// retry proof computation if error
@@ -86,27 +117,210 @@ pub struct ProofRequestOptions {
     /// Verifies the proof after computation.
     #[serde(default = "default_bool")]
     pub verify_proof: bool,
+    /// Source of the blinding factors.
+    /// Uses the default of the prover if not set.
+    #[serde(default)]
+    pub randomness: Option<ProofRandomness>,
+    /// Where to gather the block traces from.
+    #[serde(default)]
+    pub witness_source: WitnessSource,
+}
+
+/// Sources of the block traces used to build the circuit witness.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WitnessSource {
+    /// `debug_traceBlockByNumber` of an archive node with the debug namespace on `rpc`
+    #[default]
+    GethDebug,
+    /// The `prestateTracer` output of `rpc`. The block is re-executed locally.
+    Prestate,
+    /// The files of `scripts/get_block_fixtures.sh` inside `<trace dir>/<block number>/`
+    /// of the prover. The block is re-executed locally.
+    TraceDir,
 }
 
 impl PartialEq for ProofRequestOptions {
     fn eq(&self, other: &Self) -> bool {
         self.block == other.block
+            && self.block_hash == other.block_hash
             && self.rpc == other.rpc
             && self.param == other.param
             && self.circuit == other.circuit
             && self.mock == other.mock
             && self.aggregate == other.aggregate
+            && self.randomness == other.randomness
+            && self.witness_source == other.witness_source
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofRequest {
     pub options: ProofRequestOptions,
-    pub result: Option<Result<Proofs, String>>,
+    pub result: Option<Result<Proofs, ProverError>>,
     /// A counter to keep track of changes of the `result` field
     pub edition: u64,
 }
 
+/// Failures of the proof pipeline, stored as the task result.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProverError {
+    /// Fetching the block traces or building the circuit witness failed
+    Witness { cause: String },
+    /// No circuit or circuit parameters for the request
+    Circuit { cause: String },
+    /// Reading or generating the circuit parameters failed
+    Params { cause: String },
+    /// keygen_vk or keygen_pk failed
+    Keygen { cause: String },
+    /// create_proof or the MockProver failed
+    Proving {
+        cause: String,
+        /// `MockProver` feedback if `mock_feedback` was requested
+        diagnostics: Option<MockProverDiagnostics>,
+    },
+    /// verify_proof failed
+    Verification {
+        cause: String,
+        /// `MockProver` feedback if `mock_feedback` was requested
+        diagnostics: Option<MockProverDiagnostics>,
+    },
+    /// Compiling the protocol or building the aggregation circuit failed
+    Aggregation { cause: String },
+    /// The task panicked or was aborted
+    Internal { cause: String },
+}
+
+impl ProverError {
+    /// The `kind` tag of the error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Witness { .. } => "witness",
+            Self::Circuit { .. } => "circuit",
+            Self::Params { .. } => "params",
+            Self::Keygen { .. } => "keygen",
+            Self::Proving { .. } => "proving",
+            Self::Verification { .. } => "verification",
+            Self::Aggregation { .. } => "aggregation",
+            Self::Internal { .. } => "internal",
+        }
+    }
+
+    /// The `MockProver` feedback, if any.
+    pub fn diagnostics(&self) -> Option<&MockProverDiagnostics> {
+        match self {
+            Self::Proving { diagnostics, .. } | Self::Verification { diagnostics, .. } => {
+                diagnostics.as_ref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Decodes the `ProverError` of a json-rpc error, if it carries one.
+    pub fn from_rpc_error(err: &JsonRpcError) -> Option<Self> {
+        serde_json::from_value(err.data.clone()?).ok()
+    }
+}
+
+impl std::fmt::Display for ProverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cause = match self {
+            Self::Witness { cause }
+            | Self::Circuit { cause }
+            | Self::Params { cause }
+            | Self::Keygen { cause }
+            | Self::Proving { cause, .. }
+            | Self::Verification { cause, .. }
+            | Self::Aggregation { cause }
+            | Self::Internal { cause } => cause,
+        };
+        write!(f, "{}: {cause}", self.kind())?;
+        if let Some(diagnostics) = self.diagnostics() {
+            write!(f, " {diagnostics}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ProverError {}
+
+impl From<ProverError> for JsonRpcError {
+    /// A server error (`-32000`) with the tagged `ProverError` as `data`.
+    fn from(err: ProverError) -> Self {
+        Self {
+            code: -32000,
+            message: err.to_string(),
+            data: serde_json::to_value(err).ok(),
+        }
+    }
+}
+
+/// Structured summary of `MockProver::verify_par` failures.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct MockProverDiagnostics {
+    /// Circuit name / identifier
+    pub label: String,
+    /// The total number of failures reported by the `MockProver`
+    pub num_failures: usize,
+    /// The first failures in the order reported by the `MockProver`
+    pub failures: Vec<MockProverFailure>,
+    /// Path of the full report, if written to disk
+    pub report: Option<String>,
+}
+
+impl std::fmt::Display for MockProverDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MockProver: {} failure(s)", self.num_failures)?;
+        if let Some(first) = self.failures.first() {
+            write!(f, ", first: {first}")?;
+        }
+        if let Some(report) = &self.report {
+            write!(f, ", report: {report}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct MockProverFailure {
+    /// "constraint", "constraint_poisoned", "lookup", "permutation", "cell_not_assigned"
+    /// or "other"
+    pub kind: String,
+    /// The gate, constraint, lookup or column that failed
+    pub name: String,
+    /// The region the failure occurred in, if any
+    pub region: Option<String>,
+    /// The offset relative to the start of `region`
+    pub offset: Option<i64>,
+    /// The absolute row for failures outside of any region
+    pub row: Option<usize>,
+    /// Best guess of the sub-circuit that failed
+    pub sub_circuit: Option<String>,
+}
+
+impl std::fmt::Display for MockProverFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.kind, self.name)?;
+        if let Some(region) = &self.region {
+            write!(f, " in {region}")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset}")?;
+        }
+        if let Some(row) = self.row {
+            write!(f, " on row {row}")?;
+        }
+        if let Some(sub_circuit) = &self.sub_circuit {
+            write!(f, " ({sub_circuit})")?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NodeInformation {
     pub id: String,
@@ -0,0 +1,54 @@
+use zkevm_common::json_rpc::JsonRpcError;
+use zkevm_common::prover::*;
+
+#[test]
+fn proof_request_options_eq() {
+    let options = ProofRequestOptions {
+        circuit: "super".to_string(),
+        block: 1,
+        rpc: "http://localhost:8545".to_string(),
+        ..Default::default()
+    };
+    // `retry` does not make a different task
+    let retry = ProofRequestOptions {
+        retry: true,
+        ..options.clone()
+    };
+    assert_eq!(options, retry);
+
+    let prestate = ProofRequestOptions {
+        witness_source: WitnessSource::Prestate,
+        ..options.clone()
+    };
+    assert_ne!(options, prestate);
+
+    let fixed = ProofRequestOptions {
+        randomness: Some(ProofRandomness::Fixed),
+        ..options.clone()
+    };
+    assert_ne!(options, fixed);
+}
+
+#[test]
+fn prover_error_rpc_data() {
+    let err = ProverError::Witness {
+        cause: "block not found".to_string(),
+    };
+    let rpc_err: JsonRpcError = err.into();
+    assert_eq!(rpc_err.code, -32000);
+    assert_eq!(rpc_err.message, "witness: block not found");
+
+    let rpc_err: JsonRpcError =
+        serde_json::from_str(&serde_json::to_string(&rpc_err).unwrap()).unwrap();
+    let err = ProverError::from_rpc_error(&rpc_err).expect("ProverError");
+    assert_eq!(err.kind(), "witness");
+    assert_eq!(err.to_string(), "witness: block not found");
+
+    // plain errors have no data
+    let rpc_err: JsonRpcError = "this method is not available".into();
+    assert!(serde_json::to_value(&rpc_err)
+        .unwrap()
+        .get("data")
+        .is_none());
+    assert!(ProverError::from_rpc_error(&rpc_err).is_none());
+}
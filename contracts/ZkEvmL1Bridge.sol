@@ -52,6 +52,18 @@ contract ZkEvmL1Bridge is
 
   function submitBlock (bytes calldata witness) external {
     _onlyEOA();
+    _submitBlock(witness);
+  }
+
+  /// @notice Submits the blocks of `witnesses` in order, like repeated calls to `submitBlock`.
+  function submitBlocks (bytes[] calldata witnesses) external {
+    _onlyEOA();
+    for (uint256 i = 0; i < witnesses.length; i++) {
+      _submitBlock(witnesses[i]);
+    }
+  }
+
+  function _submitBlock (bytes calldata witness) internal {
     emit BlockSubmitted();
 
     (
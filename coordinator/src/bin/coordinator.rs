@@ -1,10 +1,15 @@
 use clap::Parser;
 use coordinator::config::Config;
+use coordinator::error::CoordinatorError;
 use coordinator::faucet::Faucet;
+use coordinator::proxy::{local_response, proxy, ProxyRequest};
 use coordinator::shared_state::SharedState;
+use coordinator::steps::timestamp_ms;
+use coordinator::store::Store;
 use coordinator::utils::*;
+use coordinator::ws;
 use env_logger::Env;
-use ethers_core::types::{Address, U64};
+use ethers_core::types::{Address, H256, U64};
 use hyper::body::Buf;
 use hyper::body::HttpBody;
 use hyper::client::HttpConnector;
@@ -12,6 +17,7 @@ use hyper::header::HeaderValue;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::HeaderMap;
 use hyper::{Body, Method, Request, Response, Server, StatusCode, Uri};
+use std::future::Future;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::time::Duration;
 use tokio::task::spawn;
@@ -21,51 +27,6 @@ use zkevm_common::json_rpc::JsonRpcRequest;
 use zkevm_common::json_rpc::JsonRpcResponse;
 use zkevm_common::json_rpc::JsonRpcResponseError;
 
-const EVENT_LOOP_COOLDOWN: Duration = Duration::from_millis(3000);
-/// allowed jsonrpc methods
-const PROXY_ALLOWED_METHODS: [&str; 40] = [
-    "eth_chainId",
-    "eth_gasPrice",
-    "eth_blockNumber",
-    "eth_estimateGas",
-    "eth_call",
-    "eth_getCode",
-    "eth_createAccessList",
-    "eth_feeHistory",
-    "eth_getLogs",
-    "eth_getBalance",
-    "eth_getStorageAt",
-    "eth_getTransactionCount",
-    "eth_sendRawTransaction",
-    "eth_getTransactionReceipt",
-    "eth_getTransactionByHash",
-    "net_version",
-    "web3_clientVersion",
-    "eth_getHeaderByNumber",
-    "eth_getHeaderByHash",
-    "eth_getBlockByNumber",
-    "eth_getBlockByHash",
-    "eth_getTransactionByBlockHashAndIndex",
-    "eth_getTransactionByBlockNumberAndIndex",
-    "eth_getBlockTransactionCountByHash",
-    "eth_getBlockTransactionCountByNumber",
-    "eth_getRawTransactionByHash",
-    "eth_getProof",
-    "debug_accountRange",
-    "debug_getHeaderRlp",
-    "debug_getBlockRlp",
-    "debug_dumpBlock",
-    "debug_traceBlock",
-    "debug_intermediateRoots",
-    "debug_traceBlockByNumber",
-    "debug_traceBlockByHash",
-    "debug_traceTransaction",
-    "debug_traceCall",
-    "debug_storageRangeAt",
-    "debug_getModifiedAccountsByNumber",
-    "debug_getModifiedAccountsByHash",
-];
-
 fn set_headers(headers: &mut HeaderMap, extended: bool) {
     headers.insert("content-type", HeaderValue::from_static("application/json"));
     headers.insert("access-control-allow-origin", HeaderValue::from_static("*"));
@@ -87,16 +48,11 @@ async fn handle_request(
     shared_state: SharedState,
     faucet: Option<Faucet>,
     client: hyper::Client<HttpConnector>,
+    remote_addr: SocketAddr,
     req: Request<Body>,
 ) -> Result<Response<Body>, hyper::Error> {
     // TODO: support deflate content encoding
 
-    #[derive(serde::Deserialize, serde::Serialize)]
-    struct ProxyRequest {
-        id: serde_json::Value,
-        method: String,
-    }
-
     {
         // limits the request size
         const MAX_BODY_SIZE: u64 = 4 << 20;
@@ -112,7 +68,19 @@ async fn handle_request(
         }
     }
 
+    // the client behind a trusted reverse proxy
+    let client_addr = {
+        let trusted_proxies = &shared_state.config.lock().await.trusted_proxies;
+        client_ip(remote_addr.ip(), req.headers(), trusted_proxies)
+    };
+
     match (req.method(), req.uri().path()) {
+        // websocket json-rpc with eth_subscribe support
+        (&Method::GET, "/") if ws::is_upgrade(&req) => {
+            let remote_addr = SocketAddr::new(client_addr, remote_addr.port());
+            Ok(ws::upgrade(shared_state, client, remote_addr, req))
+        }
+
         // serve some information about the chain
         (&Method::GET, "/") => {
             let mut resp = Response::new(Body::from(
@@ -122,50 +90,52 @@ async fn handle_request(
             Ok(resp)
         }
 
-        // geth upstream json-rpc
+        // geth upstream json-rpc, supports batches.
+        // Requests of the same client go to the same healthy node if possible
+        // and are retried on the next node if that fails.
         (&Method::POST, "/") => {
-            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
-            let obj: ProxyRequest =
-                serde_json::from_slice(body_bytes.as_ref()).expect("ProxyRequest");
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await?;
+            let (status, body) = match local_response(&shared_state, body_bytes.as_ref()).await {
+                // methods of the coordinator itself
+                Some(body) => (StatusCode::OK, body),
+                None => match ProxyRequest::parse(body_bytes.as_ref()) {
+                    Err(err) => (StatusCode::BAD_REQUEST, serde_json::to_value(err).unwrap()),
+                    Ok(proxy_req) if proxy_req.forward.is_empty() => (
+                        StatusCode::OK,
+                        proxy_req.response(Ok(serde_json::Value::Null)),
+                    ),
+                    Ok(proxy_req) => {
+                        let upstream = proxy(&shared_state, &client, client_addr, &proxy_req).await;
+                        let status = match upstream {
+                            Ok(_) => StatusCode::OK,
+                            Err(_) => StatusCode::SERVICE_UNAVAILABLE,
+                        };
+                        (status, proxy_req.response(upstream))
+                    }
+                },
+            };
 
-            // only allow allow the following methods and nothing else
-            if !PROXY_ALLOWED_METHODS.iter().any(|e| **e == obj.method) {
-                let err = JsonRpcResponseError {
-                    jsonrpc: "2.0".to_string(),
-                    id: obj.id,
-                    error: JsonRpcError {
-                        code: -32601,
-                        message: "this method is not available".to_string(),
-                    },
-                };
-                let resp = Response::new(Body::from(serde_json::to_vec(&err).unwrap()));
-                return Ok(resp);
-            }
+            let mut resp = Response::new(Body::from(serde_json::to_vec(&body).unwrap()));
+            *resp.status_mut() = status;
+            set_headers(resp.headers_mut(), false);
+            Ok(resp)
+        }
 
-            let mut resp;
-            {
-                // choose a serving node or none
-                let r = rand::random::<usize>();
-                let ctx = shared_state.rw.lock().await;
-                let len = ctx.nodes.len();
-                if len == 0 {
-                    drop(ctx);
-                    resp = Response::default();
-                    *resp.status_mut() = StatusCode::SERVICE_UNAVAILABLE
-                } else {
-                    let node_req = Request::post(&ctx.nodes[r % len]);
-                    drop(ctx);
-                    // reusing the same request doesn't work correctly.
-                    // Feeding the body via a reader() which was already consumed doesn't work either :/
-                    let node_req = node_req
-                        .header(hyper::header::CONTENT_TYPE, "application/json")
-                        .body(Body::from(body_bytes))
-                        .unwrap();
-                    resp = client.request(node_req).await.unwrap();
+        // prometheus metrics
+        (&Method::GET, "/metrics") => {
+            let mut resp = match shared_state.render_metrics().await {
+                Ok(metrics) => Response::new(Body::from(metrics)),
+                Err(err) => {
+                    log::warn!("metrics: {}", err);
+                    let mut resp = Response::new(Body::from(err));
+                    *resp.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                    resp
                 }
-            }
-
-            set_headers(resp.headers_mut(), false);
+            };
+            resp.headers_mut().insert(
+                hyper::header::CONTENT_TYPE,
+                HeaderValue::from_static("text/plain; version=0.0.4"),
+            );
             Ok(resp)
         }
 
@@ -176,28 +146,44 @@ async fn handle_request(
             Ok(resp)
         }
 
-        // returns 503 if faucet is disabled else 200 and enqueues a faucet requests
-        // that is processed asyncly.
-        // The faucet transfer can still fail if the `l1_wallet` has not enough ETH.
+        // returns 503 if faucet is disabled, 400 for an invalid receiver,
+        // 429 if the receiver or the client is cooling down
+        // else 200 and enqueues a faucet requests that is processed asyncly.
+        // The faucet transfer can still fail if the faucet signer has not enough ETH.
         (&Method::GET, "/faucet") => {
-            let receiver = req
-                .uri()
-                .query()
-                .expect("uri query")
-                .parse::<Address>()
-                .expect("receiver address");
-            let mut resp = Response::default();
-
-            match faucet {
-                None => {
-                    *resp.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
-                }
-                Some(faucet) => {
-                    faucet.queue.lock().await.push_back(receiver);
-                    *resp.status_mut() = StatusCode::OK;
+            let receiver = req.uri().query().map(|query| query.parse::<Address>());
+            let (status, body) = match (faucet, receiver) {
+                (None, _) => (StatusCode::SERVICE_UNAVAILABLE, serde_json::Value::Null),
+                (_, None) | (_, Some(Err(_))) => (
+                    StatusCode::BAD_REQUEST,
+                    serde_json::json!({ "error": "expected a receiver address as query" }),
+                ),
+                (Some(faucet), Some(Ok(receiver))) => {
+                    let cooldown = shared_state.config.lock().await.faucet_cooldown;
+                    match faucet
+                        .request(receiver, Some(client_addr), cooldown, timestamp_ms())
+                        .await
+                    {
+                        Ok(position) => (
+                            StatusCode::OK,
+                            serde_json::json!({ "receiver": receiver, "position": position }),
+                        ),
+                        Err(retry_after) => (
+                            StatusCode::TOO_MANY_REQUESTS,
+                            serde_json::json!({
+                                "error": "too many requests",
+                                "retry_after": retry_after,
+                            }),
+                        ),
+                    }
                 }
-            }
+            };
 
+            let mut resp = match body {
+                serde_json::Value::Null => Response::default(),
+                body => Response::new(Body::from(body.to_string())),
+            };
+            *resp.status_mut() = status;
             set_headers(resp.headers_mut(), false);
             Ok(resp)
         }
@@ -220,6 +206,7 @@ async fn handle_request(
                         // parser error
                         code: -32700,
                         message: err.to_string(),
+                        data: None,
                     },
                 })
                 .unwrap();
@@ -240,6 +227,7 @@ async fn handle_request(
                             // internal server error
                             code: -32000,
                             message: err,
+                            data: None,
                         },
                     })
                 }
@@ -279,7 +267,13 @@ async fn check_nodes(ctx: SharedState, client: hyper::Client<HttpConnector>) {
     addrs.sort_unstable();
     for addr in addrs {
         let uri = Uri::try_from(format!("http://{addr}")).unwrap();
-        let header = get_chain_head(&client, &uri).await;
+        let header = match get_chain_head(&client, &uri).await {
+            Ok(header) => header,
+            Err(err) => {
+                log::debug!("skipping unavailable node: {} {}", uri, err);
+                continue;
+            }
+        };
 
         // use the most advanced node as fallback
         if header.number >= fallback_node_num {
@@ -308,14 +302,44 @@ async fn check_nodes(ctx: SharedState, client: hyper::Client<HttpConnector>) {
     rw.nodes = nodes;
 }
 
-async fn event_loop(ctx: SharedState, _client: hyper::Client<HttpConnector>) {
-    // TODO: split sync,mine into own task
+/// Runs `step` every `interval` milliseconds of the config.
+/// If `wake_on_change` is set, it also runs as soon as `chain_state` changed.
+async fn schedule<F, Fut>(
+    ctx: SharedState,
+    name: &'static str,
+    interval: fn(&Config) -> u64,
+    wake_on_change: bool,
+    step: F,
+) where
+    F: Fn(SharedState) -> Fut,
+    Fut: Future<Output = Result<(), CoordinatorError>> + Send + 'static,
+{
+    let mut chain_state = ctx.chain_state_rx.clone();
+    loop {
+        ctx.run_step(name, &step).await;
+
+        let interval = Duration::from_millis(interval(&*ctx.config.lock().await));
+        if wake_on_change {
+            tokio::select! {
+                _ = sleep(interval) => {}
+                _ = chain_state.changed() => {}
+            }
+        } else {
+            sleep(interval).await;
+        }
+    }
+}
+
+/// Returns the `index`th parameter as `T`.
+fn param<T: serde::de::DeserializeOwned>(
+    params: &[serde_json::Value],
+    index: usize,
+) -> Result<T, String> {
+    let value = params
+        .get(index)
+        .ok_or_else(|| format!("missing parameter {index}"))?;
 
-    ctx.sync().await;
-    ctx.mine().await;
-    ctx.submit_blocks().await;
-    ctx.finalize_blocks().await.expect("finalize_blocks");
-    ctx.relay_to_l1().await;
+    serde_json::from_value(value.to_owned()).map_err(|e| e.to_string())
 }
 
 async fn handle_method(
@@ -323,6 +347,12 @@ async fn handle_method(
     params: &[serde_json::Value],
     shared_state: &SharedState,
 ) -> Result<serde_json::Value, String> {
+    // methods that change the state of the coordinator
+    const MUTATING_METHODS: [&str; 4] = ["pause", "resume", "resync_l1", "drop_message"];
+    if MUTATING_METHODS.contains(&method) && !shared_state.config.lock().await.unsafe_rpc {
+        return Err("this method is disabled".to_string());
+    }
+
     match method {
         "config" => {
             if !shared_state.config.lock().await.unsafe_rpc {
@@ -344,6 +374,116 @@ async fn handle_method(
             Ok(serde_json::to_value(config).unwrap())
         }
 
+        // L1 transactions that are not yet included, `stuck` if they had to be resent,
+        // `cancelling` if they timed out
+        "l1_transactions" => {
+            let pending = shared_state.l1_pending_transactions().await;
+
+            Ok(serde_json::to_value(pending).unwrap())
+        }
+
+        // error counts and retry schedule of the event loop steps
+        "step_status" => {
+            let steps = shared_state.rw.lock().await.steps.clone();
+
+            Ok(serde_json::to_value(steps).unwrap())
+        }
+
+        // health, load and assigned blocks of the provers
+        "provers" => {
+            let provers = shared_state.rw.lock().await.provers.backends().to_vec();
+
+            Ok(serde_json::to_value(provers).unwrap())
+        }
+
+        // the L1 > L2 and L2 > L1 messages waiting for delivery
+        "message_queues" => {
+            let rw = shared_state.rw.lock().await;
+
+            Ok(serde_json::json!({
+                "l1": rw.l1_message_queue,
+                "l2": rw.l2_message_queue,
+            }))
+        }
+
+        // sync cursors and checkpoints
+        "sync_status" => {
+            let rw = shared_state.rw.lock().await;
+            let checkpoints: Vec<_> = rw
+                .l1_checkpoints
+                .iter()
+                .map(|checkpoint| serde_json::json!([checkpoint.number, checkpoint.hash]))
+                .collect();
+
+            Ok(serde_json::json!({
+                "chain_state": rw.chain_state,
+                "l1_last_sync_block": rw.l1_last_sync_block,
+                "l2_last_sync_block": rw.l2_last_sync_block,
+                "l1_log_range": rw.l1_events.range(),
+                "l2_log_range": rw.l2_events.range(),
+                "l1_checkpoints": checkpoints,
+                "l1_resync": rw.l1_resync,
+                "paused": rw.paused,
+            }))
+        }
+
+        // submission, finalization and proof state of the blocks after the finalized block
+        "block_status" => {
+            let limit: u64 = param(params, 0).unwrap_or(64);
+            let blocks = shared_state.block_status(limit).await?;
+
+            Ok(serde_json::to_value(blocks).unwrap())
+        }
+
+        // the lifecycle of a bridge message
+        "message_status" => {
+            let id: H256 = param(params, 0)?;
+            let record = shared_state.message_status(id).await;
+
+            Ok(serde_json::to_value(record).unwrap())
+        }
+
+        // the L2 rpc nodes used by the proxy
+        "nodes" => {
+            let nodes: Vec<String> = shared_state
+                .rw
+                .lock()
+                .await
+                .nodes
+                .iter()
+                .map(|uri| uri.to_string())
+                .collect();
+
+            Ok(serde_json::to_value(nodes).unwrap())
+        }
+
+        // pauses or resumes block production
+        "pause" | "resume" => {
+            let paused = method == "pause";
+            shared_state.rw.lock().await.paused = paused;
+            log::warn!("admin: paused={}", paused);
+
+            Ok(serde_json::to_value(paused).unwrap())
+        }
+
+        // replays the L1 events from the given block, returns the first block synced again
+        "resync_l1" => {
+            let from: U64 = param(params, 0)?;
+            let from = shared_state.request_l1_resync(from).await?;
+            log::warn!("admin: resync L1 from block {}", from);
+
+            Ok(serde_json::to_value(from).unwrap())
+        }
+
+        // removes a message from the queues, returns `false` if it was not queued
+        "drop_message" => {
+            let id: H256 = param(params, 0)?;
+            let dropped = shared_state.drop_queued_message(id).await;
+            log::warn!("admin: drop message {:?} {}", id, dropped);
+
+            Ok(serde_json::to_value(dropped).unwrap())
+        }
+
         _ => Err("this method is not available".to_string()),
     }
 }
@@ -353,7 +493,11 @@ async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
     let config = Config::parse();
-    let shared_state = SharedState::new(&config).await;
+    let store = config
+        .state_path
+        .as_ref()
+        .map(|path| Store::open(path).expect("open state database"));
+    let shared_state = SharedState::new(&config, store).await;
 
     shared_state.init().await;
 
@@ -372,12 +516,19 @@ async fn main() {
         let faucet = faucet.clone();
         // start the http server
         spawn(async move {
-            let service = make_service_fn(move |_| {
+            let service = make_service_fn(move |conn: &hyper::server::conn::AddrStream| {
+                let remote_addr = conn.remote_addr();
                 let shared_state = shared_state.clone();
                 let faucet = faucet.clone();
                 let client = client.clone();
                 let service = service_fn(move |req| {
-                    handle_request(shared_state.clone(), faucet.clone(), client.to_owned(), req)
+                    handle_request(
+                        shared_state.clone(),
+                        faucet.clone(),
+                        client.to_owned(),
+                        remote_addr,
+                        req,
+                    )
                 });
 
                 async move { Ok::<_, hyper::Error>(service) }
@@ -390,42 +541,83 @@ async fn main() {
     }
 
     {
-        let ctx = shared_state.clone();
-        let h1 = spawn(async move {
-            let client = hyper::Client::new();
-            loop {
-                log::debug!("spawning event_loop task");
-
-                {
-                    // the event_loop handles l1/l2 chain events and chain progression
-                    let res = spawn(event_loop(ctx.clone(), client.to_owned())).await;
-
-                    if let Err(err) = res {
-                        log::error!("event_loop: {}", err);
-                    }
-                }
-
-                {
-                    // The faucet shares the same l1 wallet with the event_loop
-                    // above, therefore it should be invoked in serial.
-                    let ctx = ctx.clone();
+        // the steps run independently, a slow or failing step doesn't hold up the others.
+        // L1 transactions are serialized per signer by `shared_state.l1_role_senders`.
+        let mut tasks = vec![
+            spawn(schedule(
+                shared_state.clone(),
+                "sync",
+                |config| config.sync_interval,
+                false,
+                |ctx| async move { ctx.sync().await },
+            )),
+            spawn(schedule(
+                shared_state.clone(),
+                "mine",
+                |config| config.mine_interval,
+                false,
+                |ctx| async move { ctx.mine().await },
+            )),
+            spawn(schedule(
+                shared_state.clone(),
+                "submit_blocks",
+                |config| config.submit_interval,
+                true,
+                |ctx| async move { ctx.submit_blocks().await },
+            )),
+            spawn(schedule(
+                shared_state.clone(),
+                "finalize_blocks",
+                |config| config.finalize_interval,
+                true,
+                |ctx| async move { ctx.finalize_blocks().await },
+            )),
+            spawn(schedule(
+                shared_state.clone(),
+                "request_proofs_ahead",
+                |config| config.proof_interval,
+                true,
+                |ctx| async move { ctx.request_proofs_ahead().await },
+            )),
+            spawn(schedule(
+                shared_state.clone(),
+                "relay_to_l1",
+                |config| config.relay_interval,
+                true,
+                |ctx| async move { ctx.relay_to_l1().await },
+            )),
+            spawn(schedule(
+                shared_state.clone(),
+                "drop_expired_messages",
+                |config| config.drop_interval,
+                false,
+                |ctx| async move { ctx.drop_expired_messages().await },
+            )),
+            spawn(schedule(
+                shared_state.clone(),
+                "check_provers",
+                |config| config.prover_check_interval,
+                false,
+                |ctx| async move { ctx.check_provers().await },
+            )),
+        ];
+
+        if let Some(faucet) = faucet {
+            tasks.push(spawn(schedule(
+                shared_state.clone(),
+                "faucet",
+                |config| config.faucet_interval,
+                false,
+                move |ctx| {
                     let faucet = faucet.clone();
-                    let res = spawn(async move {
-                        if let Some(_faucet) = &faucet {
-                            // only consume up to 3 items each time
-                            _faucet.drain(ctx, 3).await;
-                        }
-                    })
-                    .await;
-
-                    if let Err(err) = res {
-                        log::error!("faucet: {}", err);
+                    async move {
+                        // only consume up to 3 items each time
+                        faucet.drain(ctx, 3).await;
+                        Ok(())
                     }
-                }
-
-                sleep(EVENT_LOOP_COOLDOWN).await;
-            }
-        });
+                },
+            )));
+        }
 
         let ctx = shared_state.clone();
         let h2 = spawn(async move {
@@ -442,9 +634,13 @@ async fn main() {
             }
         });
 
+        tasks.push(h2);
+
         // wait for all tasks
-        if tokio::try_join!(h1, h2).is_err() {
-            panic!("unexpected task error");
+        for task in tasks {
+            if task.await.is_err() {
+                panic!("unexpected task error");
+            }
         }
     }
 }
@@ -0,0 +1,54 @@
+use clap::{Parser, Subcommand};
+use coordinator::store::PersistentState;
+use coordinator::store::Store;
+use std::fs::File;
+
+#[derive(Parser, Debug)]
+#[clap(version, about)]
+/// Exports or imports the on-disk state of the coordinator, e.g. to move it to another host.
+/// The coordinator must not be running.
+struct Args {
+    #[clap(long, env = "COORDINATOR_STATE_PATH")]
+    /// Directory of the state database.
+    state_path: String,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Writes the state as JSON to `file` or stdout.
+    Export { file: Option<String> },
+    /// Replaces the state with the JSON from `file`.
+    Import { file: String },
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let store = Store::open(&args.state_path).expect("open state database");
+
+    match args.command {
+        Command::Export { file } => {
+            let state = store
+                .load()
+                .expect("load state")
+                .expect("state database is empty");
+            match file {
+                Some(path) => serde_json::to_writer_pretty(
+                    File::create(path).expect("create export file"),
+                    &state,
+                ),
+                None => serde_json::to_writer_pretty(std::io::stdout(), &state),
+            }
+            .expect("serialize and write");
+        }
+        Command::Import { file } => {
+            let state: PersistentState =
+                serde_json::from_reader(File::open(file).expect("open import file"))
+                    .expect("parse state");
+            store.save(&state).await.expect("save state");
+        }
+    }
+}
@@ -1,9 +1,10 @@
+use crate::signer::L1Role;
 use clap::Parser;
 use ethers_core::types::Address;
 use hyper::Uri;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 
 #[serde_as]
 #[derive(Parser, Deserialize, Serialize, Clone, Debug)]
@@ -14,10 +15,58 @@ pub struct Config {
     /// Address in the form of host:port of the L2 rpc node(s). Can resolve to multiple addresses.
     pub rpc_server_nodes: String,
 
+    #[clap(long, env = "COORDINATOR_PROXY_TIMEOUT", default_value_t = 30000)]
+    #[serde(default = "default_proxy_timeout")]
+    /// Milliseconds until a proxied json-rpc request is retried on the next rpc node.
+    pub proxy_timeout: u64,
+
     #[clap(long, env = "COORDINATOR_ENABLE_FAUCET")]
     /// Enables faucet to send eth to L1 wallet.
     pub enable_faucet: bool,
 
+    #[clap(
+        long,
+        env = "COORDINATOR_FAUCET_AMOUNT",
+        default_value_t = 1000000000000000000
+    )]
+    #[serde(default = "default_faucet_amount")]
+    /// Wei sent by the faucet on L1 per request.
+    pub faucet_amount: u128,
+
+    #[clap(long, env = "COORDINATOR_FAUCET_L2_AMOUNT", default_value_t = 0)]
+    #[serde(default)]
+    /// Wei sent by the faucet on L2 per request, from `l2_faucet_key`. 0 disables L2 payouts.
+    pub faucet_l2_amount: u128,
+
+    #[clap(
+        long,
+        env = "COORDINATOR_FAUCET_MIN_BALANCE",
+        default_value_t = 1000000000000000000
+    )]
+    #[serde(default = "default_faucet_min_balance")]
+    /// Wei the faucet keeps in reserve on L1.
+    pub faucet_min_balance: u128,
+
+    #[clap(
+        long,
+        env = "COORDINATOR_FAUCET_L2_MIN_BALANCE",
+        default_value_t = 1000000000000000000
+    )]
+    #[serde(default = "default_faucet_min_balance")]
+    /// Wei the faucet keeps in reserve on L2.
+    pub faucet_l2_min_balance: u128,
+
+    #[clap(long, env = "COORDINATOR_FAUCET_COOLDOWN", default_value_t = 86400000)]
+    #[serde(default = "default_faucet_cooldown")]
+    /// Milliseconds before the same receiver or IP address can use the faucet again.
+    pub faucet_cooldown: u64,
+
+    #[clap(long, env = "COORDINATOR_TRUSTED_PROXIES", value_delimiter = ',')]
+    #[serde(default)]
+    /// IP addresses of the reverse proxies in front of the coordinator, comma separated.
+    /// The client address of their requests is taken from `X-Real-IP` or `X-Forwarded-For`.
+    pub trusted_proxies: Vec<IpAddr>,
+
     #[clap(long, env = "COORDINATOR_LISTEN")]
     /// Address for the coordinator to listen to, in the format of ip:port.
     pub listen: SocketAddr,
@@ -38,6 +87,12 @@ pub struct Config {
     /// Enable verification of the proof.
     pub verify_proof: bool,
 
+    #[clap(long, env = "COORDINATOR_FIXED_RNG", default_value_t = false)]
+    #[serde(default)]
+    /// Request proofs with the fixed blinding factors of the prover, only meant for tests.
+    /// Otherwise the prover uses its own default.
+    pub fixed_rng: bool,
+
     #[clap(long, env = "COORDINATOR_L1_RPC_URL")]
     #[serde_as(as = "DisplayFromStr")]
     /// L1 RPC node URL format.
@@ -49,8 +104,42 @@ pub struct Config {
 
     #[clap(long, env = "COORDINATOR_L1_PRIV")]
     /// Private key for Ethereum L1 wallet.
+    /// Also used by the roles below without own key.
     pub l1_priv: String,
 
+    #[clap(long, env = "COORDINATOR_L1_SUBMITTER_KEY")]
+    /// Signer of `submitBlock`: a private key, `keystore:<path>` or a remote signer URL.
+    pub l1_submitter_key: Option<String>,
+
+    #[clap(long, env = "COORDINATOR_L1_FINALIZER_KEY")]
+    /// Signer of `finalizeBlock`, see `l1_submitter_key`.
+    pub l1_finalizer_key: Option<String>,
+
+    #[clap(long, env = "COORDINATOR_L1_RELAYER_KEY")]
+    /// Signer of the L2 > L1 message relays, see `l1_submitter_key`.
+    pub l1_relayer_key: Option<String>,
+
+    #[clap(long, env = "COORDINATOR_L1_FAUCET_KEY")]
+    /// Signer of the faucet transfers, see `l1_submitter_key`.
+    pub l1_faucet_key: Option<String>,
+
+    #[clap(long, env = "COORDINATOR_L2_KEY")]
+    /// Signer of the L2 transactions like faucet payouts and message drops,
+    /// see `l1_submitter_key`.
+    pub l2_key: Option<String>,
+
+    #[clap(long, env = "COORDINATOR_L2_DELIVERER_KEY")]
+    /// Signer of the L1 > L2 message deliveries, `l2_key` if not set.
+    pub l2_deliverer_key: Option<String>,
+
+    #[clap(long, env = "COORDINATOR_L2_FAUCET_KEY")]
+    /// Signer of the faucet L2 payouts, `l2_key` if not set.
+    pub l2_faucet_key: Option<String>,
+
+    #[clap(long, env = "COORDINATOR_KEYSTORE_PASSWORD")]
+    /// Password of the `keystore:<path>` signers.
+    pub keystore_password: Option<String>,
+
     #[clap(long, env = "COORDINATOR_L2_RPC_URL")]
     #[serde_as(as = "DisplayFromStr")]
     /// L2 RPC node in http URL format.
@@ -61,6 +150,19 @@ pub struct Config {
     /// Prover RPC node URL.
     pub prover_rpcd_url: Uri,
 
+    #[clap(long, env = "COORDINATOR_PROVER_RPCD_URLS", value_delimiter = ',')]
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    /// Additional prover RPC node URLs, comma separated.
+    /// Proof requests go to the least busy healthy prover.
+    pub prover_rpcd_urls: Vec<Uri>,
+
+    #[clap(long, env = "COORDINATOR_PROOF_LOOKAHEAD", default_value_t = 8)]
+    #[serde(default = "default_proof_lookahead")]
+    /// Number of blocks after the finalized block to request proofs for as soon as they are mined.
+    /// 0 requests proofs only once the blocks are submitted.
+    pub proof_lookahead: u64,
+
     #[clap(long, env = "COORDINATOR_PARAMS_PATH")]
     /// Parameters file or directory to use for the prover requests.
     /// Otherwise generates them on the fly.
@@ -78,10 +180,229 @@ pub struct Config {
     #[clap(long, env = "COORDINATOR_UNSAFE_RPC", default_value_t = false)]
     /// Allow unsafe rpc methods of the coordinator if true
     pub unsafe_rpc: bool,
+
+    #[clap(long, env = "COORDINATOR_L1_CONFIRMATIONS", default_value_t = 0)]
+    #[serde(default)]
+    /// Number of L1 blocks on top of a block before its events are processed.
+    pub l1_confirmations: u64,
+
+    #[clap(long, env = "COORDINATOR_L1_TX_RESEND_AFTER", default_value_t = 30000)]
+    #[serde(default = "default_l1_tx_resend_after")]
+    /// Milliseconds without a receipt before a L1 transaction is resent with higher fees.
+    pub l1_tx_resend_after: u64,
+
+    #[clap(long, env = "COORDINATOR_L1_TX_FEE_BUMP", default_value_t = 20)]
+    #[serde(default = "default_l1_tx_fee_bump")]
+    /// Percentage by which the fees of a resent L1 transaction are raised.
+    pub l1_tx_fee_bump: u64,
+
+    #[clap(
+        long,
+        env = "COORDINATOR_L1_TX_MAX_FEE_PER_GAS",
+        default_value_t = 500000000000
+    )]
+    #[serde(default = "default_l1_tx_max_fee_per_gas")]
+    /// Upper limit in wei of `max_fee_per_gas` of L1 transactions, also for resent ones.
+    /// Transactions at the limit are resent without raising their fees.
+    pub l1_tx_max_fee_per_gas: u128,
+
+    #[clap(long, env = "COORDINATOR_L1_TX_TIMEOUT", default_value_t = 120000)]
+    #[serde(default = "default_l1_tx_timeout")]
+    /// Milliseconds before a L1 transaction without receipt is replaced by a 0-value self-transfer.
+    /// The next L1 transaction of the signer waits until either is included.
+    pub l1_tx_timeout: u64,
+
+    #[clap(long, env = "COORDINATOR_L2_TX_TIMEOUT", default_value_t = 60000)]
+    #[serde(default = "default_l2_tx_timeout")]
+    /// Milliseconds before waiting for a L2 transaction of the coordinator to be mined is given up.
+    pub l2_tx_timeout: u64,
+
+    #[clap(long, env = "COORDINATOR_SUBMIT_BATCH_MAX_BLOCKS", default_value_t = 1)]
+    #[serde(default = "default_submit_batch_max_blocks")]
+    /// Maximum number of blocks per block submission L1 transaction.
+    /// Values above 1 enable batching via `submitBlocks`,
+    /// bridges deployed before `submitBlocks` need an upgrade.
+    pub submit_batch_max_blocks: u64,
+
+    #[clap(
+        long,
+        env = "COORDINATOR_SUBMIT_BATCH_MAX_CALLDATA",
+        default_value_t = 120000
+    )]
+    #[serde(default = "default_submit_batch_max_calldata")]
+    /// Maximum calldata size in bytes of a batched block submission.
+    pub submit_batch_max_calldata: u64,
+
+    #[clap(
+        long,
+        env = "COORDINATOR_RELAY_BATCH_MAX_GAS",
+        default_value_t = 3000000
+    )]
+    #[serde(default = "default_relay_batch_max_gas")]
+    /// Gas budget of a L1 transaction relaying a batch of L2 > L1 messages.
+    pub relay_batch_max_gas: u64,
+
+    #[clap(long, env = "COORDINATOR_RELAY_MAX_MESSAGES", default_value_t = 32)]
+    #[serde(default = "default_relay_max_messages")]
+    /// Maximum number of L2 > L1 messages relayed per run of `relay_to_l1`.
+    pub relay_max_messages: u64,
+
+    #[clap(long, env = "COORDINATOR_MESSAGE_FEE_MARGIN", default_value_t = 0)]
+    #[serde(default)]
+    /// Percentage of the estimated L2 delivery costs the fee of a L1 > L2 message has to cover,
+    /// e.g. 120 for costs plus 20%. 0 delivers messages regardless of their fee.
+    pub message_fee_margin: u64,
+
+    #[clap(long, env = "COORDINATOR_MAX_MESSAGES_PER_BLOCK", default_value_t = 0)]
+    #[serde(default)]
+    /// Maximum number of L1 > L2 messages delivered per L2 block, 0 for no limit.
+    pub max_messages_per_block: u64,
+
+    #[clap(long, env = "COORDINATOR_DROP_EXPIRED_MIN_VALUE")]
+    /// Enables refunding expired messages via `dropMessage` on behalf of their senders,
+    /// for messages with a value plus fee of at least this many wei.
+    pub drop_expired_min_value: Option<u128>,
+
+    #[clap(long, env = "COORDINATOR_SYNC_INTERVAL", default_value_t = 3000)]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between syncing the L1 and L2 bridge events.
+    pub sync_interval: u64,
+
+    #[clap(long, env = "COORDINATOR_MINE_INTERVAL", default_value_t = 3000)]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between L2 block production runs.
+    pub mine_interval: u64,
+
+    #[clap(long, env = "COORDINATOR_SUBMIT_INTERVAL", default_value_t = 3000)]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between block submission runs, also runs if the L2 chain head changed.
+    pub submit_interval: u64,
+
+    #[clap(long, env = "COORDINATOR_FINALIZE_INTERVAL", default_value_t = 3000)]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between block finalization runs, also runs if a block was submitted.
+    pub finalize_interval: u64,
+
+    #[clap(long, env = "COORDINATOR_RELAY_INTERVAL", default_value_t = 3000)]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between L2 > L1 message relay runs, also runs if a block was finalized.
+    pub relay_interval: u64,
+
+    #[clap(
+        long,
+        env = "COORDINATOR_PROVER_CHECK_INTERVAL",
+        default_value_t = 3000
+    )]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between prover health checks.
+    pub prover_check_interval: u64,
+
+    #[clap(long, env = "COORDINATOR_FAUCET_INTERVAL", default_value_t = 3000)]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between processing faucet requests.
+    pub faucet_interval: u64,
+
+    #[clap(long, env = "COORDINATOR_DROP_INTERVAL", default_value_t = 3000)]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between dropping expired messages, see `drop_expired_min_value`.
+    pub drop_interval: u64,
+
+    #[clap(long, env = "COORDINATOR_PROOF_INTERVAL", default_value_t = 3000)]
+    #[serde(default = "default_interval")]
+    /// Milliseconds between requesting proofs ahead, see `proof_lookahead`.
+    /// Also runs if the L2 chain head changed.
+    pub proof_interval: u64,
+
+    #[clap(long, env = "COORDINATOR_STATE_PATH")]
+    /// Directory of the on-disk state database.
+    /// The state is kept in memory only if not set.
+    pub state_path: Option<String>,
 }
 
 impl Config {
     pub fn from_env() -> Self {
         Self::parse_from(std::env::args().skip(usize::MAX))
     }
+
+    /// Returns the signer spec of `role` or `None` if it uses `l1_priv`.
+    pub fn l1_role_key(&self, role: L1Role) -> Option<&str> {
+        match role {
+            L1Role::Submitter => self.l1_submitter_key.as_deref(),
+            L1Role::Finalizer => self.l1_finalizer_key.as_deref(),
+            L1Role::Relayer => self.l1_relayer_key.as_deref(),
+            L1Role::Faucet => self.l1_faucet_key.as_deref(),
+        }
+    }
+
+    /// Returns `prover_rpcd_url` followed by `prover_rpcd_urls`, without duplicates.
+    pub fn prover_urls(&self) -> Vec<Uri> {
+        let mut urls = vec![self.prover_rpcd_url.clone()];
+        for url in self.prover_rpcd_urls.iter() {
+            if !urls.contains(url) {
+                urls.push(url.clone());
+            }
+        }
+
+        urls
+    }
+}
+
+fn default_interval() -> u64 {
+    3000
+}
+
+fn default_proxy_timeout() -> u64 {
+    30000
+}
+
+fn default_faucet_amount() -> u128 {
+    1000000000000000000
+}
+
+fn default_faucet_min_balance() -> u128 {
+    1000000000000000000
+}
+
+fn default_faucet_cooldown() -> u64 {
+    86400000
+}
+
+fn default_proof_lookahead() -> u64 {
+    8
+}
+
+fn default_submit_batch_max_blocks() -> u64 {
+    1
+}
+
+fn default_submit_batch_max_calldata() -> u64 {
+    120000
+}
+
+fn default_relay_batch_max_gas() -> u64 {
+    3000000
+}
+
+fn default_relay_max_messages() -> u64 {
+    32
+}
+
+fn default_l1_tx_resend_after() -> u64 {
+    30000
+}
+
+fn default_l1_tx_fee_bump() -> u64 {
+    20
+}
+
+fn default_l1_tx_max_fee_per_gas() -> u128 {
+    500000000000
+}
+
+fn default_l1_tx_timeout() -> u64 {
+    120000
+}
+
+fn default_l2_tx_timeout() -> u64 {
+    60000
 }
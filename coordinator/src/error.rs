@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Errors of the coordinator steps.
+/// `context` names the operation that failed, `cause` is the underlying error.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CoordinatorError {
+    /// A request to the L1 node failed
+    L1 { context: String, cause: String },
+    /// A request to the L2 node failed
+    L2 { context: String, cause: String },
+    /// A request to the prover failed or returned an unusable proof
+    Prover { context: String, cause: String },
+    /// A L1 transaction could not be sent, reverted or timed out
+    Transaction { context: String, cause: String },
+    /// Inconsistent state or a panic
+    Internal { context: String, cause: String },
+}
+
+impl CoordinatorError {
+    pub fn l1(context: &str, cause: impl ToString) -> Self {
+        Self::L1 {
+            context: context.to_string(),
+            cause: cause.to_string(),
+        }
+    }
+
+    pub fn l2(context: &str, cause: impl ToString) -> Self {
+        Self::L2 {
+            context: context.to_string(),
+            cause: cause.to_string(),
+        }
+    }
+
+    pub fn prover(context: &str, cause: impl ToString) -> Self {
+        Self::Prover {
+            context: context.to_string(),
+            cause: cause.to_string(),
+        }
+    }
+
+    pub fn transaction(context: &str, cause: impl ToString) -> Self {
+        Self::Transaction {
+            context: context.to_string(),
+            cause: cause.to_string(),
+        }
+    }
+
+    pub fn internal(context: &str, cause: impl ToString) -> Self {
+        Self::Internal {
+            context: context.to_string(),
+            cause: cause.to_string(),
+        }
+    }
+
+    pub fn cause(&self) -> &str {
+        match self {
+            Self::L1 { cause, .. }
+            | Self::L2 { cause, .. }
+            | Self::Prover { cause, .. }
+            | Self::Transaction { cause, .. }
+            | Self::Internal { cause, .. } => cause,
+        }
+    }
+}
+
+impl fmt::Display for CoordinatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, context) = match self {
+            Self::L1 { context, .. } => ("l1", context),
+            Self::L2 { context, .. } => ("l2", context),
+            Self::Prover { context, .. } => ("prover", context),
+            Self::Transaction { context, .. } => ("transaction", context),
+            Self::Internal { context, .. } => ("internal", context),
+        };
+
+        write!(f, "{kind}: {context}: {}", self.cause())
+    }
+}
+
+impl std::error::Error for CoordinatorError {}
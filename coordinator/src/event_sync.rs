@@ -0,0 +1,108 @@
+use ethers_core::types::{Filter, Log, U64};
+use std::cmp;
+use std::fmt::Display;
+use std::future::Future;
+
+/// Upper limit of blocks in a single `eth_getLogs` request.
+pub const MAX_LOG_RANGE: u64 = 4096;
+/// The range shrinks if a response contains more logs than this.
+const MAX_LOGS: usize = 1000;
+/// The range grows if a response contains less logs than this.
+const MIN_LOGS: usize = 100;
+
+/// Fetches logs in block ranges that grow and shrink depending on
+/// the response size and errors. Shared by the L1 and L2 event sync.
+#[derive(Clone, Copy, Debug)]
+pub struct EventSync {
+    /// The number of blocks of the next request
+    range: u64,
+}
+
+impl Default for EventSync {
+    fn default() -> Self {
+        Self { range: 2 }
+    }
+}
+
+impl EventSync {
+    pub fn range(&self) -> u64 {
+        self.range
+    }
+
+    /// Fetches and applies the logs of `filter` for the blocks `from..=latest`, range by range.
+    /// `apply` is called with the first and the last block of each range and its logs,
+    /// it advances the sync cursor of the caller and returns `false` to stop, e.g. on a reorg.
+    pub async fn sync<E, F, Fut, A, AFut>(
+        &mut self,
+        filter: &Filter,
+        mut from: U64,
+        latest: U64,
+        get_logs: F,
+        apply: A,
+    ) -> Result<(), E>
+    where
+        E: Display,
+        F: Fn(Filter) -> Fut,
+        Fut: Future<Output = Result<Vec<Log>, E>>,
+        A: Fn(U64, U64, Vec<Log>) -> AFut,
+        AFut: Future<Output = Result<bool, E>>,
+    {
+        while from <= latest {
+            let (to, logs) = self.next_logs(filter, from, latest, &get_logs).await?;
+            if !apply(from, to, logs).await? {
+                break;
+            }
+            from = to + 1u64;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the logs of `filter` for the blocks `from..=to` via `get_logs`,
+    /// with `to` at most `latest`. Retries with smaller ranges on errors.
+    /// Returns `to` and the logs, except the ones flagged as `removed`.
+    pub async fn next_logs<E, F, Fut>(
+        &mut self,
+        filter: &Filter,
+        from: U64,
+        latest: U64,
+        get_logs: F,
+    ) -> Result<(U64, Vec<Log>), E>
+    where
+        E: Display,
+        F: Fn(Filter) -> Fut,
+        Fut: Future<Output = Result<Vec<Log>, E>>,
+    {
+        loop {
+            let to = cmp::min(from + (self.range - 1), latest);
+            log::trace!("fetching logs from={} to={}", from, to);
+
+            match get_logs(filter.clone().from_block(from).to_block(to)).await {
+                Ok(logs) => {
+                    self.adjust(logs.len());
+                    let logs = logs
+                        .into_iter()
+                        .filter(|log| log.removed != Some(true))
+                        .collect();
+
+                    return Ok((to, logs));
+                }
+                Err(err) => {
+                    if self.range == 1 {
+                        return Err(err);
+                    }
+                    self.range /= 2;
+                    log::debug!("fetching logs failed, range={} {}", self.range, err);
+                }
+            }
+        }
+    }
+
+    fn adjust(&mut self, num_logs: usize) {
+        if num_logs > MAX_LOGS {
+            self.range = cmp::max(1, self.range / 2);
+        } else if num_logs < MIN_LOGS {
+            self.range = cmp::min(MAX_LOG_RANGE, self.range * 2);
+        }
+    }
+}
@@ -1,50 +1,127 @@
+use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::net::IpAddr;
 use std::sync::Arc;
 
 use ethers_core::types::Address;
 use ethers_core::types::U256;
-use ethers_signers::Signer;
 
 use tokio::spawn;
 use tokio::sync::Mutex;
 
+use crate::metrics::{wei_to_f64, FAUCET_PAID, FAUCET_PAYOUTS};
 use crate::shared_state::SharedState;
+use crate::signer::L1Role;
+
+/// Unix timestamp in milliseconds of the latest accepted request, by receiver and by IP.
+#[derive(Default)]
+struct Cooldowns {
+    receivers: HashMap<Address, u64>,
+    ips: HashMap<IpAddr, u64>,
+}
 
 #[derive(Clone)]
 pub struct Faucet {
     pub queue: Arc<Mutex<VecDeque<Address>>>,
+    cooldowns: Arc<Mutex<Cooldowns>>,
 }
 
 impl Default for Faucet {
     fn default() -> Faucet {
         Faucet {
             queue: Arc::new(Mutex::new(VecDeque::new())),
+            cooldowns: Arc::new(Mutex::new(Cooldowns::default())),
         }
     }
 }
 
 impl Faucet {
-    /// Iterates over `queue` and sends ETH with the `shared_state.ro.l1_wallet`.
-    /// To avoid replacing transactions or invoking other race conditions,
-    /// this function should not be run in parallel with any other `SharedState` tasks.
+    /// Queues `receiver` unless it or `ip` had a request accepted within `cooldown` milliseconds.
+    /// Returns the queue position of `receiver`, also if it is already queued,
+    /// or the milliseconds until the next request is accepted.
+    pub async fn request(
+        &self,
+        receiver: Address,
+        ip: Option<IpAddr>,
+        cooldown: u64,
+        now: u64,
+    ) -> Result<usize, u64> {
+        let mut queue = self.queue.lock().await;
+        if let Some(position) = queue.iter().position(|e| *e == receiver) {
+            return Ok(position);
+        }
+
+        let mut cooldowns = self.cooldowns.lock().await;
+        cooldowns.receivers.retain(|_, last| *last + cooldown > now);
+        cooldowns.ips.retain(|_, last| *last + cooldown > now);
+
+        let last = [
+            cooldowns.receivers.get(&receiver),
+            ip.as_ref().and_then(|ip| cooldowns.ips.get(ip)),
+        ]
+        .into_iter()
+        .flatten()
+        .max();
+        if let Some(last) = last {
+            return Err(last + cooldown - now);
+        }
+
+        cooldowns.receivers.insert(receiver, now);
+        if let Some(ip) = ip {
+            cooldowns.ips.insert(ip, now);
+        }
+        queue.push_back(receiver);
+
+        Ok(queue.len() - 1)
+    }
+
+    /// Iterates over `queue` and sends `Config::faucet_amount` ETH with the signer of `L1Role::Faucet`
+    /// and `Config::faucet_l2_amount` ETH on L2 with `RoState::l2_faucet`, if set.
+    /// The transfers go through the `L1Sender` and `L2Sender` of the signers like any other transaction.
+    /// Stops once a transfer would leave less than `Config::faucet_min_balance` on L1
+    /// or `Config::faucet_l2_min_balance` on L2.
     /// Only consumes up to `max_items` items from the queue each time.
     pub async fn drain(&self, shared_state: SharedState, max_items: usize) {
+        let (faucet_amount, l2_amount, min_wallet_balance, min_l2_wallet_balance) = {
+            let config = shared_state.config.lock().await;
+            (
+                U256::from(config.faucet_amount),
+                U256::from(config.faucet_l2_amount),
+                U256::from(config.faucet_min_balance),
+                U256::from(config.faucet_l2_min_balance),
+            )
+        };
         let mut queue = self.queue.lock().await;
         let mut remaining_balance: U256 = shared_state
             .request_l1(
                 "eth_getBalance",
-                (shared_state.ro.l1_wallet.address(), "latest"),
+                (
+                    shared_state.l1_role_senders[&L1Role::Faucet].address(),
+                    "latest",
+                ),
             )
             .await
             .expect("l1 balance");
-
-        // can be made configurable if needed
-        let faucet_amount = U256::from(1000000000000000000u64);
-        let min_wallet_balance = U256::from(1000000000000000000u64);
+        let mut remaining_l2_balance: U256 = if l2_amount.is_zero() {
+            U256::zero()
+        } else {
+            shared_state
+                .request_l2(
+                    "eth_getBalance",
+                    (shared_state.l2_faucet_sender.address(), "latest"),
+                )
+                .await
+                .expect("l2 balance")
+        };
 
         let mut i = 0;
         for receiver in queue.iter().take(max_items) {
-            log::info!("transfer of {} for {:?}", faucet_amount, receiver);
+            log::info!(
+                "transfer of {} (L2: {}) for {:?}",
+                faucet_amount,
+                l2_amount,
+                receiver
+            );
 
             if remaining_balance < faucet_amount {
                 log::warn!(
@@ -58,6 +135,16 @@ impl Faucet {
                 log::warn!("faucet wallet balance is too low ({})", remaining_balance);
                 break;
             }
+            if !l2_amount.is_zero()
+                && (remaining_l2_balance < l2_amount
+                    || remaining_l2_balance - l2_amount < min_l2_wallet_balance)
+            {
+                log::warn!(
+                    "faucet L2 wallet balance is too low ({})",
+                    remaining_l2_balance
+                );
+                break;
+            }
 
             // spawn task to catch panics
             {
@@ -66,9 +153,38 @@ impl Faucet {
                 let shared_state = shared_state.clone();
                 let res = spawn(async move {
                     shared_state
-                        .transaction_to_l1(Some(receiver), faucet_amount, vec![])
+                        .transaction_to_l1_as(L1Role::Faucet, Some(receiver), faucet_amount, vec![])
                         .await
                         .expect("receipt");
+                    shared_state
+                        .metrics
+                        .inc(&FAUCET_PAYOUTS, &[("chain", "l1")], 1.0);
+                    shared_state.metrics.inc(
+                        &FAUCET_PAID,
+                        &[("chain", "l1")],
+                        wei_to_f64(faucet_amount),
+                    );
+
+                    // the L1 transfer is done, a failed L2 transfer is not retried
+                    if !l2_amount.is_zero() {
+                        match shared_state
+                            .l2_faucet_sender
+                            .send(Some(receiver), l2_amount, vec![], None)
+                            .await
+                        {
+                            Ok(_) => {
+                                shared_state
+                                    .metrics
+                                    .inc(&FAUCET_PAYOUTS, &[("chain", "l2")], 1.0);
+                                shared_state.metrics.inc(
+                                    &FAUCET_PAID,
+                                    &[("chain", "l2")],
+                                    wei_to_f64(l2_amount),
+                                );
+                            }
+                            Err(err) => log::error!("L2 transfer for {:?}: {}", receiver, err),
+                        }
+                    }
                 })
                 .await;
 
@@ -79,6 +195,9 @@ impl Faucet {
             }
 
             remaining_balance -= faucet_amount;
+            if !l2_amount.is_zero() {
+                remaining_l2_balance -= l2_amount;
+            }
             i += 1;
         }
 
@@ -0,0 +1,421 @@
+use crate::config::Config;
+use crate::signer::TxSigner;
+use crate::steps::timestamp_ms;
+use crate::structs::L1Fees;
+use crate::utils::*;
+use ethers_core::types::transaction::eip2930::AccessList;
+use ethers_core::types::{
+    Address, Bytes, Eip1559TransactionRequest, TransactionReceipt, H256, U256,
+};
+use ethers_core::utils::keccak256;
+use hyper::client::HttpConnector;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::Mutex;
+use zkevm_common::json_rpc::jsonrpc_call;
+use zkevm_common::json_rpc::jsonrpc_request_client;
+use zkevm_common::json_rpc::JsonRpcClientError;
+
+/// A transaction for `L1Sender` and the channel for its outcome.
+struct L1TxRequest {
+    to: Option<Address>,
+    value: U256,
+    calldata: Vec<u8>,
+    reply: oneshot::Sender<Result<TransactionReceipt, String>>,
+}
+
+/// A L1 transaction that was broadcast but is not yet included.
+#[derive(Clone, Debug, Serialize)]
+pub struct L1PendingTx {
+    pub from: Address,
+    pub nonce: U256,
+    pub to: Option<Address>,
+    /// The hashes of all broadcasts, the latest last
+    pub hashes: Vec<H256>,
+    /// The fees of the latest broadcast
+    pub fees: L1Fees,
+    /// Unix timestamp in milliseconds of the first broadcast
+    pub first_sent: u64,
+    /// Unix timestamp in milliseconds of the latest broadcast
+    pub last_sent: u64,
+    /// Set once the transaction had to be resent or timed out
+    pub stuck: bool,
+    /// Set once the transaction timed out and is replaced by a 0-value self-transfer
+    pub cancelling: bool,
+}
+
+/// Owns the nonce of a L1 signer and sends all L1 transactions one after another,
+/// so that the coordinator tasks don't replace each other's transactions.
+/// Transactions without receipt are resent with higher fees, see `Config::l1_tx_resend_after`,
+/// and replaced by a 0-value self-transfer once they time out, see `Config::l1_tx_timeout`.
+/// The nonce is only reused for the next transaction once either is included.
+/// The fees are never raised above `Config::l1_tx_max_fee_per_gas`.
+#[derive(Clone)]
+pub struct L1Sender {
+    address: Address,
+    queue: mpsc::UnboundedSender<L1TxRequest>,
+    pending: Arc<Mutex<BTreeMap<U256, L1PendingTx>>>,
+}
+
+impl L1Sender {
+    /// Spawns the sender task for `signer`.
+    pub fn spawn(
+        client: hyper::Client<HttpConnector>,
+        config: Arc<Mutex<Config>>,
+        signer: Arc<dyn TxSigner>,
+    ) -> Self {
+        let address = signer.address();
+        let (queue, rx) = mpsc::unbounded_channel();
+        let pending = Arc::new(Mutex::new(BTreeMap::new()));
+        let manager = TxManager {
+            client,
+            config,
+            signer,
+            nonce: None,
+            pending: pending.clone(),
+        };
+        tokio::spawn(manager.run(rx));
+
+        Self {
+            address,
+            queue,
+            pending,
+        }
+    }
+
+    /// The address of the signer.
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Queues the transaction and waits for its receipt.
+    pub async fn send(
+        &self,
+        to: Option<Address>,
+        value: U256,
+        calldata: Vec<u8>,
+    ) -> Result<TransactionReceipt, String> {
+        let (reply, receipt) = oneshot::channel();
+        self.queue
+            .send(L1TxRequest {
+                to,
+                value,
+                calldata,
+                reply,
+            })
+            .map_err(|_| "L1 sender stopped".to_string())?;
+
+        receipt
+            .await
+            .map_err(|_| "L1 sender dropped the transaction".to_string())?
+    }
+
+    /// Returns the transactions that were broadcast but not yet included, by nonce.
+    pub async fn pending(&self) -> Vec<L1PendingTx> {
+        self.pending.lock().await.values().cloned().collect()
+    }
+}
+
+struct TxManager {
+    client: hyper::Client<HttpConnector>,
+    config: Arc<Mutex<Config>>,
+    signer: Arc<dyn TxSigner>,
+    /// The nonce after our latest broadcast, the pending nonce of the node takes precedence
+    nonce: Option<U256>,
+    pending: Arc<Mutex<BTreeMap<U256, L1PendingTx>>>,
+}
+
+impl TxManager {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<L1TxRequest>) {
+        while let Some(req) = rx.recv().await {
+            let res = self.send(req.to, req.value, req.calldata).await;
+
+            // the requesting task may have been aborted
+            let _ = req.reply.send(res);
+        }
+    }
+
+    async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        args: T,
+    ) -> Result<R, String> {
+        let l1_rpc_url = self.config.lock().await.l1_rpc_url.clone();
+
+        jsonrpc_request_client(RPC_REQUEST_TIMEOUT, &self.client, &l1_rpc_url, method, args).await
+    }
+
+    async fn nonce_at(&self, block_tag: &str) -> Result<U256, String> {
+        self.request(
+            "eth_getTransactionCount",
+            (self.signer.address(), block_tag),
+        )
+        .await
+    }
+
+    /// Returns `fees` raised by `Config::l1_tx_fee_bump`,
+    /// `None` once they reached `Config::l1_tx_max_fee_per_gas`.
+    fn bump_fees(fees: L1Fees, config: &Config) -> Option<L1Fees> {
+        let cap = U256::from(config.l1_tx_max_fee_per_gas);
+        if fees.max_fee_per_gas >= cap {
+            return None;
+        }
+
+        Some(fees.bump(config.l1_tx_fee_bump).capped(cap))
+    }
+
+    async fn send(
+        &mut self,
+        to: Option<Address>,
+        value: U256,
+        calldata: Vec<u8>,
+    ) -> Result<TransactionReceipt, String> {
+        const LOG_TAG: &str = "L1Sender:";
+        let config = self.config.lock().await.clone();
+        let latest = self.nonce_at("latest").await?;
+        // covers our transactions in flight and the key being used outside of the coordinator
+        let mut nonce = self.nonce_at("pending").await?;
+        if matches!(self.nonce, Some(next) if next > nonce) {
+            log::warn!(
+                "{} nonces from {} on never reached the node, reusing them",
+                LOG_TAG,
+                nonce
+            );
+        }
+        // transactions below `latest` were included or replaced
+        self.pending
+            .lock()
+            .await
+            .retain(|pending, _| *pending >= latest && *pending < nonce);
+
+        let mut fees = get_l1_fees(&self.client, &config.l1_rpc_url)
+            .await?
+            .capped(U256::from(config.l1_tx_max_fee_per_gas));
+        let mut tx = prepare_transaction_l1(
+            &self.client,
+            &config.l1_rpc_url,
+            &*self.signer,
+            to,
+            value,
+            calldata,
+            nonce,
+            fees,
+        )
+        .await?;
+        let first_sent = timestamp_ms();
+        let mut hashes: Vec<H256> = Vec::new();
+        // the index of the first broadcast of the cancellation in `hashes`
+        let mut cancel_from: Option<usize> = None;
+        // set once the fees reached `l1_tx_max_fee_per_gas`, the transaction is resent as is
+        let mut capped = false;
+
+        loop {
+            if cancel_from.is_none()
+                && !capped
+                && timestamp_ms() - first_sent >= config.l1_tx_timeout
+            {
+                if hashes.is_empty() {
+                    return Err(format!("transaction with nonce {nonce} timed out"));
+                }
+                // the nonce is not reused until either transaction is included
+                log::warn!(
+                    "{} nonce={} timed out, replacing it with a 0-value self-transfer",
+                    LOG_TAG,
+                    nonce
+                );
+                cancel_from = Some(hashes.len());
+                tx = cancel_transaction(tx, self.signer.address());
+            }
+
+            let raw_tx = sign_eip1559(&*self.signer, &tx).await?;
+            let hash = H256::from(keccak256(&raw_tx));
+            // in flight before the broadcast, the node may accept it even if the request fails
+            if !hashes.contains(&hash) {
+                hashes.push(hash);
+            }
+            self.nonce = Some(nonce + 1);
+            let last_sent = timestamp_ms();
+            let mut pending_tx = L1PendingTx {
+                from: self.signer.address(),
+                nonce,
+                to,
+                hashes: hashes.clone(),
+                fees,
+                first_sent,
+                last_sent,
+                stuck: hashes.len() > 1 || cancel_from.is_some(),
+                cancelling: cancel_from.is_some(),
+            };
+            self.pending.lock().await.insert(nonce, pending_tx.clone());
+
+            let resp: Result<H256, JsonRpcClientError> = jsonrpc_call(
+                RPC_REQUEST_TIMEOUT,
+                &self.client,
+                &config.l1_rpc_url,
+                "eth_sendRawTransaction",
+                [&raw_tx],
+            )
+            .await;
+
+            match resp {
+                Err(JsonRpcClientError::Rpc(err)) if !err.message.contains("already known") => {
+                    // rejected by the node
+                    let err = err.message;
+                    hashes.retain(|broadcast| *broadcast != hash);
+                    if err.contains("replacement transaction underpriced") {
+                        log::warn!("{} nonce={} {}", LOG_TAG, nonce, err);
+                        tokio::time::sleep(Duration::from_millis(1000)).await;
+                        if let Some(bumped) = Self::bump_fees(fees, &config) {
+                            fees = bumped;
+                            tx = with_fees(tx, fees);
+                            continue;
+                        }
+                        capped = true;
+                        if hashes.is_empty() {
+                            self.pending.lock().await.remove(&nonce);
+                            return Err(format!(
+                                "nonce {nonce} is taken by a pending transaction \
+                                 with fees above l1_tx_max_fee_per_gas"
+                            ));
+                        }
+                        log::warn!(
+                            "{} nonce={} fees reached l1_tx_max_fee_per_gas, waiting",
+                            LOG_TAG,
+                            nonce
+                        );
+                    } else if err.contains("nonce too low") {
+                        // either a previous broadcast was included or the nonce was used elsewhere
+                        match self.find_receipt(&hashes).await {
+                            Ok(Some(receipt)) => {
+                                return self.finish(nonce, receipt, &hashes, cancel_from).await;
+                            }
+                            Ok(None) => {}
+                            Err(err) => {
+                                log::warn!("{} eth_getTransactionReceipt {}", LOG_TAG, err);
+                                tokio::time::sleep(Duration::from_millis(1000)).await;
+                                continue;
+                            }
+                        }
+                        self.pending.lock().await.remove(&nonce);
+                        if cancel_from.is_some() {
+                            self.nonce = None;
+                            return Err(format!(
+                                "transaction with nonce {nonce} timed out, the nonce was used elsewhere"
+                            ));
+                        }
+                        nonce = self.nonce_at("pending").await?;
+                        log::warn!("{} {}, retrying with nonce={}", LOG_TAG, err, nonce);
+                        tx = tx.nonce(nonce);
+                        hashes.clear();
+                        continue;
+                    } else if hashes.is_empty() {
+                        self.pending.lock().await.remove(&nonce);
+                        self.nonce = Some(nonce);
+                        return Err(format!("eth_sendRawTransaction: {err}"));
+                    } else {
+                        // the previous broadcasts may still be included
+                        log::warn!("{} eth_sendRawTransaction {}", LOG_TAG, err);
+                    }
+                    pending_tx.hashes = hashes.clone();
+                    self.pending.lock().await.insert(nonce, pending_tx);
+                }
+                // already known
+                Ok(_) | Err(JsonRpcClientError::Rpc(_)) => {}
+                // the broadcast may still have reached the node
+                Err(err) => log::warn!("{} eth_sendRawTransaction {}", LOG_TAG, err),
+            }
+
+            // wait for a receipt of any broadcast
+            loop {
+                tokio::time::sleep(Duration::from_millis(1000)).await;
+
+                match self.find_receipt(&hashes).await {
+                    Ok(Some(receipt)) => {
+                        return self.finish(nonce, receipt, &hashes, cancel_from).await;
+                    }
+                    Ok(None) => {}
+                    Err(err) => log::warn!("{} eth_getTransactionReceipt {}", LOG_TAG, err),
+                }
+
+                let now = timestamp_ms();
+                let timed_out = cancel_from.is_none() && now - first_sent >= config.l1_tx_timeout;
+                if (timed_out && !capped) || now - last_sent >= config.l1_tx_resend_after {
+                    match Self::bump_fees(fees, &config) {
+                        Some(bumped) => fees = bumped,
+                        None => capped = true,
+                    }
+                    log::warn!(
+                        "{} no receipt for {:?}, resending nonce={} with {:?}",
+                        LOG_TAG,
+                        hashes.last(),
+                        nonce,
+                        fees
+                    );
+                    tx = with_fees(tx, fees);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Returns the receipt of any of `hashes`, `None` if none of them is included yet.
+    async fn find_receipt(&self, hashes: &[H256]) -> Result<Option<TransactionReceipt>, String> {
+        for hash in hashes.iter().rev() {
+            let receipt: Option<TransactionReceipt> =
+                self.request("eth_getTransactionReceipt", [hash]).await?;
+
+            if receipt.is_some() {
+                return Ok(receipt);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Frees the nonce of the included transaction.
+    /// Fails if the transaction reverted or was cancelled,
+    /// the broadcasts from `cancel_from` on are the cancellation.
+    async fn finish(
+        &mut self,
+        nonce: U256,
+        receipt: TransactionReceipt,
+        hashes: &[H256],
+        cancel_from: Option<usize>,
+    ) -> Result<TransactionReceipt, String> {
+        self.pending.lock().await.remove(&nonce);
+        self.nonce = Some(nonce + 1);
+
+        if let Some(from) = cancel_from {
+            if hashes[from..].contains(&receipt.transaction_hash) {
+                return Err(format!(
+                    "transaction with nonce {nonce} timed out and was cancelled"
+                ));
+            }
+        }
+        if receipt.status.map(|status| status.as_u64()) != Some(1) {
+            return Err("transaction reverted".to_string());
+        }
+
+        Ok(receipt)
+    }
+}
+
+/// Replaces `tx` by a 0-value self-transfer, to use up its nonce.
+fn cancel_transaction(tx: Eip1559TransactionRequest, from: Address) -> Eip1559TransactionRequest {
+    tx.to(from)
+        .value(U256::zero())
+        .data(Bytes::default())
+        .access_list(AccessList::default())
+        .gas(U256::from(21000u64))
+}
+
+fn with_fees(tx: Eip1559TransactionRequest, fees: L1Fees) -> Eip1559TransactionRequest {
+    tx.max_fee_per_gas(fees.max_fee_per_gas)
+        .max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
+}
@@ -0,0 +1,122 @@
+use crate::config::Config;
+use crate::signer::TxSigner;
+use crate::utils::*;
+use ethers_core::types::{Address, Bytes, H256, U256};
+use hyper::client::HttpConnector;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::sync::MutexGuard;
+use zkevm_common::json_rpc::jsonrpc_request_client;
+
+/// Owns the nonce of a L2 signer, so that the coordinator tasks don't reuse each other's nonces.
+/// The coordinator seals the L2 blocks itself: `SharedState::mine` signs its own transactions
+/// under `lock` and includes the transactions sent via `send` that are not yet mined before them.
+#[derive(Clone)]
+pub struct L2Sender {
+    client: hyper::Client<HttpConnector>,
+    config: Arc<Mutex<Config>>,
+    signer: Arc<dyn TxSigner>,
+    /// Raw transactions sent to the L2 node but not yet mined, by nonce
+    pending: Arc<Mutex<BTreeMap<U256, Bytes>>>,
+}
+
+/// The nonce of a `L2Sender`, locked until dropped.
+pub struct L2Nonce<'a> {
+    pending: MutexGuard<'a, BTreeMap<U256, Bytes>>,
+    nonce: U256,
+}
+
+impl L2Nonce<'_> {
+    /// The first nonce after the mined and the pending transactions.
+    pub fn nonce(&self) -> U256 {
+        self.nonce
+    }
+
+    /// The raw transactions sent via `L2Sender::send` that are not yet mined, by nonce.
+    pub fn pending(&self) -> Vec<Bytes> {
+        self.pending.values().cloned().collect()
+    }
+}
+
+impl L2Sender {
+    pub fn new(
+        client: hyper::Client<HttpConnector>,
+        config: Arc<Mutex<Config>>,
+        signer: Arc<dyn TxSigner>,
+    ) -> Self {
+        Self {
+            client,
+            config,
+            signer,
+            pending: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// The address of the signer.
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    pub fn signer(&self) -> &dyn TxSigner {
+        self.signer.as_ref()
+    }
+
+    /// Locks the nonce of the signer until the returned `L2Nonce` is dropped.
+    pub async fn lock(&self) -> Result<L2Nonce<'_>, String> {
+        let mut pending = self.pending.lock().await;
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+        let latest: U256 = jsonrpc_request_client(
+            RPC_REQUEST_TIMEOUT,
+            &self.client,
+            &l2_rpc_url,
+            "eth_getTransactionCount",
+            (self.signer.address(), "latest"),
+        )
+        .await?;
+        // transactions below `latest` were mined
+        pending.retain(|nonce, _| *nonce >= latest);
+        let nonce = match pending.keys().next_back() {
+            Some(last) => *last + 1,
+            None => latest,
+        };
+
+        Ok(L2Nonce { pending, nonce })
+    }
+
+    /// Signs a transaction with the next nonce and sends it to the L2 node.
+    /// The transaction is mined by the next `SharedState::mine` run.
+    pub async fn send(
+        &self,
+        to: Option<Address>,
+        value: U256,
+        calldata: Vec<u8>,
+        gas_limit: Option<U256>,
+    ) -> Result<H256, String> {
+        let mut lock = self.lock().await?;
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+        let raw_tx = sign_transaction_l2(
+            &self.client,
+            &l2_rpc_url,
+            self.signer.as_ref(),
+            to,
+            value,
+            lock.nonce,
+            calldata,
+            gas_limit,
+            "latest",
+        )
+        .await?;
+        let hash = jsonrpc_request_client(
+            RPC_REQUEST_TIMEOUT,
+            &self.client,
+            &l2_rpc_url,
+            "eth_sendRawTransaction",
+            [&raw_tx],
+        )
+        .await?;
+        lock.pending.insert(lock.nonce, raw_tx);
+
+        Ok(hash)
+    }
+}
@@ -1,6 +1,18 @@
 pub mod config;
+pub mod error;
+pub mod event_sync;
 pub mod faucet;
+pub mod l1_sender;
+pub mod l2_sender;
 pub mod macros;
+pub mod metrics;
+pub mod provers;
+pub mod proxy;
 pub mod shared_state;
+pub mod signer;
+pub mod steps;
+pub mod store;
 pub mod structs;
 pub mod utils;
+pub mod witness;
+pub mod ws;
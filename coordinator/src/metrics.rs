@@ -0,0 +1,252 @@
+use ethers_core::types::{H256, U256};
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    /// Exposed as `<name>_sum` and `<name>_count`
+    Summary,
+}
+
+/// A metric family, its samples are distinguished by labels.
+#[derive(Debug)]
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+}
+
+/// label `kind`: head, safe or finalized
+pub static L2_BLOCK: Metric = Metric {
+    name: "coordinator_l2_block",
+    help: "Number of the head, safe and finalized L2 block.",
+    kind: MetricKind::Gauge,
+};
+/// label `kind`: unsubmitted (head - safe) or unfinalized (safe - finalized)
+pub static L2_BLOCK_LAG: Metric = Metric {
+    name: "coordinator_l2_block_lag",
+    help: "Number of L2 blocks not yet submitted or finalized on L1.",
+    kind: MetricKind::Gauge,
+};
+/// label `chain`: l1 or l2
+pub static SYNC_LAG: Metric = Metric {
+    name: "coordinator_sync_lag_blocks",
+    help: "Number of blocks between the latest block and the event sync cursor.",
+    kind: MetricKind::Gauge,
+};
+/// label `direction`: l1_to_l2 or l2_to_l1
+pub static MESSAGE_QUEUE: Metric = Metric {
+    name: "coordinator_message_queue_length",
+    help: "Number of bridge messages waiting to be delivered.",
+    kind: MetricKind::Gauge,
+};
+/// label `direction`: l1_to_l2 or l2_to_l1
+pub static MESSAGE_DELIVERY: Metric = Metric {
+    name: "coordinator_message_delivery_seconds",
+    help: "Time from queuing a bridge message until the coordinator delivered it.",
+    kind: MetricKind::Summary,
+};
+/// label `role`: the `L1Role` of the sender
+pub static L1_GAS_USED: Metric = Metric {
+    name: "coordinator_l1_gas_used_total",
+    help: "Gas used by the L1 transactions of the coordinator.",
+    kind: MetricKind::Counter,
+};
+/// label `role`: the `L1Role` of the sender
+pub static L1_FEES: Metric = Metric {
+    name: "coordinator_l1_fees_wei_total",
+    help: "Fees paid by the L1 transactions of the coordinator.",
+    kind: MetricKind::Counter,
+};
+/// label `chain`: l1 or l2
+pub static FAUCET_PAYOUTS: Metric = Metric {
+    name: "coordinator_faucet_payouts_total",
+    help: "Number of faucet transfers.",
+    kind: MetricKind::Counter,
+};
+/// label `chain`: l1 or l2
+pub static FAUCET_PAID: Metric = Metric {
+    name: "coordinator_faucet_paid_wei_total",
+    help: "Value sent by the faucet.",
+    kind: MetricKind::Counter,
+};
+/// label `circuit`: `Config::circuit_name`
+pub static PROOF_LATENCY: Metric = Metric {
+    name: "coordinator_proof_seconds",
+    help: "Time from the first proof request of a block until the proof is ready.",
+    kind: MetricKind::Summary,
+};
+/// labels `circuit` and `kind`: `Config::circuit_name` and the `ProverError` kind
+pub static PROOF_FAILURES: Metric = Metric {
+    name: "coordinator_proof_failures_total",
+    help: "Number of proof requests answered with a prover error.",
+    kind: MetricKind::Counter,
+};
+/// labels `method` and `node`: the json-rpc method and the upstream node
+pub static PROXY_REQUESTS: Metric = Metric {
+    name: "coordinator_proxy_requests_total",
+    help: "Number of proxied json-rpc requests.",
+    kind: MetricKind::Counter,
+};
+
+#[derive(Default)]
+struct Sample {
+    value: f64,
+    count: u64,
+}
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Default)]
+struct Registry {
+    families: BTreeMap<&'static str, (&'static Metric, BTreeMap<Labels, Sample>)>,
+    /// message id > unix timestamp in milliseconds when the message was queued
+    queued_messages: HashMap<H256, u64>,
+    /// block number > unix timestamp in milliseconds of the first proof request
+    proof_requests: HashMap<u64, u64>,
+}
+
+impl Registry {
+    fn sample(&mut self, metric: &'static Metric, labels: &[(&'static str, &str)]) -> &mut Sample {
+        let labels = labels
+            .iter()
+            .map(|(key, value)| (*key, value.to_string()))
+            .collect();
+
+        self.families
+            .entry(metric.name)
+            .or_insert_with(|| (metric, BTreeMap::new()))
+            .1
+            .entry(labels)
+            .or_default()
+    }
+}
+
+/// Collects the coordinator metrics and renders them in the Prometheus text format.
+#[derive(Clone, Default)]
+pub struct Metrics(Arc<Mutex<Registry>>);
+
+impl Metrics {
+    /// Adds `by` to a counter.
+    pub fn inc(&self, metric: &'static Metric, labels: &[(&'static str, &str)], by: f64) {
+        self.0.lock().unwrap().sample(metric, labels).value += by;
+    }
+
+    /// Sets a gauge.
+    pub fn set(&self, metric: &'static Metric, labels: &[(&'static str, &str)], value: f64) {
+        self.0.lock().unwrap().sample(metric, labels).value = value;
+    }
+
+    /// Records an observation of a summary.
+    pub fn observe(&self, metric: &'static Metric, labels: &[(&'static str, &str)], value: f64) {
+        let mut registry = self.0.lock().unwrap();
+        let sample = registry.sample(metric, labels);
+        sample.value += value;
+        sample.count += 1;
+    }
+
+    /// Starts the delivery timer of a message, if not already started.
+    pub fn message_queued(&self, id: H256, now: u64) {
+        self.0
+            .lock()
+            .unwrap()
+            .queued_messages
+            .entry(id)
+            .or_insert(now);
+    }
+
+    /// Records the delivery latency of a message queued with `message_queued`.
+    pub fn message_delivered(&self, direction: &str, id: H256, now: u64) {
+        let queued = self.0.lock().unwrap().queued_messages.remove(&id);
+        if let Some(queued) = queued {
+            let seconds = now.saturating_sub(queued) as f64 / 1000.0;
+            self.observe(&MESSAGE_DELIVERY, &[("direction", direction)], seconds);
+        }
+    }
+
+    /// Stops the delivery timers of messages that are no longer queued.
+    pub fn retain_messages(&self, queued: impl Fn(&H256) -> bool) {
+        self.0
+            .lock()
+            .unwrap()
+            .queued_messages
+            .retain(|id, _| queued(id));
+    }
+
+    /// Starts the proof timer of `block`, if not already started.
+    pub fn proof_requested(&self, block: u64, now: u64) {
+        self.0
+            .lock()
+            .unwrap()
+            .proof_requests
+            .entry(block)
+            .or_insert(now);
+    }
+
+    /// Records the proof latency of `block`.
+    pub fn proof_ready(&self, circuit: &str, block: u64, now: u64) {
+        let requested = self.0.lock().unwrap().proof_requests.remove(&block);
+        if let Some(requested) = requested {
+            let seconds = now.saturating_sub(requested) as f64 / 1000.0;
+            self.observe(&PROOF_LATENCY, &[("circuit", circuit)], seconds);
+        }
+    }
+
+    /// Returns all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let registry = self.0.lock().unwrap();
+        let mut out = String::new();
+
+        for (name, (metric, samples)) in registry.families.iter() {
+            let kind = match metric.kind {
+                MetricKind::Counter => "counter",
+                MetricKind::Gauge => "gauge",
+                MetricKind::Summary => "summary",
+            };
+            writeln!(out, "# HELP {} {}", name, metric.help).unwrap();
+            writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+
+            for (labels, sample) in samples.iter() {
+                let labels = render_labels(labels);
+                match metric.kind {
+                    MetricKind::Summary => {
+                        writeln!(out, "{}_sum{} {}", name, labels, sample.value).unwrap();
+                        writeln!(out, "{}_count{} {}", name, labels, sample.count).unwrap();
+                    }
+                    _ => writeln!(out, "{}{} {}", name, labels, sample.value).unwrap(),
+                }
+            }
+        }
+
+        out
+    }
+}
+
+fn render_labels(labels: &Labels) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", key, value)
+        })
+        .collect();
+
+    format!("{{{}}}", labels.join(","))
+}
+
+/// Converts wei amounts for counters, precision loss is fine for metrics.
+pub fn wei_to_f64(value: U256) -> f64 {
+    value.to_string().parse().unwrap_or(f64::MAX)
+}
@@ -0,0 +1,161 @@
+use hyper::Uri;
+use serde::Serialize;
+use serde_with::serde_as;
+use serde_with::DisplayFromStr;
+use std::collections::BTreeMap;
+
+/// A prover node and its latest health check.
+#[serde_as]
+#[derive(Clone, Debug, Serialize)]
+pub struct ProverBackend {
+    #[serde_as(as = "DisplayFromStr")]
+    pub url: Uri,
+    /// `false` if the latest health check or request failed
+    pub healthy: bool,
+    /// `true` if the prover reported to be working on a task
+    pub busy: bool,
+    /// Number of blocks assigned to this prover
+    pub assigned: usize,
+    pub last_error: Option<String>,
+    /// Unix timestamp in milliseconds of the latest health check
+    pub last_check: u64,
+}
+
+/// Dispatches proof requests to the least busy healthy prover.
+/// A block sticks to its prover until the proof is done or the prover fails.
+#[derive(Clone, Debug, Default)]
+pub struct ProverPool {
+    backends: Vec<ProverBackend>,
+    /// block number > index into `backends`
+    assignments: BTreeMap<u64, usize>,
+    /// round-robin offset among equally busy provers
+    next: usize,
+}
+
+impl ProverPool {
+    pub fn new(urls: &[Uri]) -> Self {
+        let mut pool = Self::default();
+        pool.set_urls(urls);
+
+        pool
+    }
+
+    /// Updates the list of provers, keeping the state of existing ones.
+    pub fn set_urls(&mut self, urls: &[Uri]) {
+        if self
+            .backends
+            .iter()
+            .map(|backend| &backend.url)
+            .eq(urls.iter())
+        {
+            return;
+        }
+
+        let backends: Vec<ProverBackend> = urls
+            .iter()
+            .map(|url| {
+                self.backends
+                    .iter()
+                    .find(|backend| backend.url == *url)
+                    .cloned()
+                    .unwrap_or_else(|| ProverBackend {
+                        url: url.clone(),
+                        // until the first health check
+                        healthy: true,
+                        busy: false,
+                        assigned: 0,
+                        last_error: None,
+                        last_check: 0,
+                    })
+            })
+            .collect();
+
+        // move assignments to the new indices, dropping removed provers
+        let assignments = self
+            .assignments
+            .iter()
+            .filter_map(|(block, index)| {
+                let url = &self.backends[*index].url;
+                let index = backends.iter().position(|backend| backend.url == *url)?;
+                Some((*block, index))
+            })
+            .collect();
+
+        self.backends = backends;
+        self.assignments = assignments;
+        self.recount();
+    }
+
+    pub fn backends(&self) -> &[ProverBackend] {
+        &self.backends
+    }
+
+    /// Returns the prover for `block`, assigning one if needed.
+    /// `None` if no prover is healthy.
+    pub fn select(&mut self, block: u64) -> Option<Uri> {
+        if let Some(index) = self.assignments.get(&block) {
+            if self.backends[*index].healthy {
+                return Some(self.backends[*index].url.clone());
+            }
+        }
+
+        let len = self.backends.len();
+        let index = (0..len)
+            .map(|i| (self.next + i) % len)
+            .filter(|i| self.backends[*i].healthy)
+            .min_by_key(|i| self.backends[*i].assigned + self.backends[*i].busy as usize)?;
+
+        self.next = (index + 1) % len;
+        self.assignments.insert(block, index);
+        self.recount();
+
+        Some(self.backends[index].url.clone())
+    }
+
+    /// Removes the assignment of `block` after its proof is done.
+    pub fn release(&mut self, block: u64) {
+        self.assignments.remove(&block);
+        self.recount();
+    }
+
+    /// Marks `url` as unhealthy, its blocks get assigned to another prover.
+    pub fn fail(&mut self, url: &Uri, err: String) {
+        if let Some(index) = self.backends.iter().position(|backend| backend.url == *url) {
+            let backend = &mut self.backends[index];
+            backend.healthy = false;
+            backend.last_error = Some(err);
+            self.assignments.retain(|_, assigned| *assigned != index);
+            self.recount();
+        }
+    }
+
+    /// Records the outcome of a health check of `url`.
+    pub fn record_check(&mut self, url: &Uri, result: Result<bool, String>, now: u64) {
+        let index = match self.backends.iter().position(|backend| backend.url == *url) {
+            Some(index) => index,
+            None => return,
+        };
+
+        match result {
+            Ok(busy) => {
+                let backend = &mut self.backends[index];
+                backend.healthy = true;
+                backend.busy = busy;
+                backend.last_check = now;
+            }
+            Err(err) => {
+                self.fail(url, err);
+                self.backends[index].last_check = now;
+            }
+        }
+    }
+
+    fn recount(&mut self) {
+        for backend in self.backends.iter_mut() {
+            backend.assigned = 0;
+        }
+        for index in self.assignments.values() {
+            self.backends[*index].assigned += 1;
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use ethers_core::types::H256;
+use hyper::client::HttpConnector;
+use hyper::{Body, Request, Uri};
+use serde::Deserialize;
+use serde_json::Value;
+use zkevm_common::json_rpc::JsonRpcError;
+use zkevm_common::json_rpc::JsonRpcResponseError;
+
+use crate::metrics::PROXY_REQUESTS;
+use crate::shared_state::SharedState;
+
+/// allowed jsonrpc methods
+pub const PROXY_ALLOWED_METHODS: [&str; 40] = [
+    "eth_chainId",
+    "eth_gasPrice",
+    "eth_blockNumber",
+    "eth_estimateGas",
+    "eth_call",
+    "eth_getCode",
+    "eth_createAccessList",
+    "eth_feeHistory",
+    "eth_getLogs",
+    "eth_getBalance",
+    "eth_getStorageAt",
+    "eth_getTransactionCount",
+    "eth_sendRawTransaction",
+    "eth_getTransactionReceipt",
+    "eth_getTransactionByHash",
+    "net_version",
+    "web3_clientVersion",
+    "eth_getHeaderByNumber",
+    "eth_getHeaderByHash",
+    "eth_getBlockByNumber",
+    "eth_getBlockByHash",
+    "eth_getTransactionByBlockHashAndIndex",
+    "eth_getTransactionByBlockNumberAndIndex",
+    "eth_getBlockTransactionCountByHash",
+    "eth_getBlockTransactionCountByNumber",
+    "eth_getRawTransactionByHash",
+    "eth_getProof",
+    "debug_accountRange",
+    "debug_getHeaderRlp",
+    "debug_getBlockRlp",
+    "debug_dumpBlock",
+    "debug_traceBlock",
+    "debug_intermediateRoots",
+    "debug_traceBlockByNumber",
+    "debug_traceBlockByHash",
+    "debug_traceTransaction",
+    "debug_traceCall",
+    "debug_storageRangeAt",
+    "debug_getModifiedAccountsByNumber",
+    "debug_getModifiedAccountsByHash",
+];
+
+/// Answers a single request for a method of the coordinator itself like `message_status`,
+/// `None` for any other request.
+pub async fn local_response(shared_state: &SharedState, body: &[u8]) -> Option<Value> {
+    let req: Value = serde_json::from_slice(body).ok()?;
+    let id = req.get("id").cloned().unwrap_or_default();
+    let param = |index: usize| req.get("params").and_then(|params| params.get(index));
+
+    let result = match req.get("method").and_then(Value::as_str)? {
+        // the lifecycle of a bridge message
+        "message_status" => match param(0).map(H256::deserialize) {
+            Some(Ok(msg_id)) => Ok(shared_state.message_status(msg_id).await),
+            _ => Err("expected a message id"),
+        },
+        _ => return None,
+    };
+
+    Some(match result {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => serde_json::to_value(rpc_error(id, -32602, err)).unwrap(),
+    })
+}
+
+/// Methods that are not retried on the next node once they may have reached a node.
+/// A transaction sent twice could be broadcast with a stale view of the pool.
+pub const NON_IDEMPOTENT_METHODS: [&str; 1] = ["eth_sendRawTransaction"];
+
+/// A json-rpc request or batch of a proxy client.
+/// The entries with allowed methods are forwarded upstream, the others are answered with an error.
+#[derive(Debug)]
+pub struct ProxyRequest {
+    /// `true` if the client sent a batch, the response is a batch too
+    pub batch: bool,
+    /// The method of each forwarded entry
+    pub methods: Vec<String>,
+    /// The entries forwarded upstream
+    pub forward: Vec<Value>,
+    /// The error responses of the rejected entries
+    pub rejected: Vec<JsonRpcResponseError>,
+}
+
+impl ProxyRequest {
+    /// Parses a request or batch.
+    /// Returns the error response if `body` is not json or an empty batch.
+    pub fn parse(body: &[u8]) -> Result<Self, JsonRpcResponseError> {
+        let value: Value = serde_json::from_slice(body)
+            .map_err(|_| rpc_error(Value::Null, -32700, "parse error"))?;
+        let (batch, entries) = match value {
+            Value::Array(entries) if entries.is_empty() => {
+                return Err(rpc_error(Value::Null, -32600, "empty batch"))
+            }
+            Value::Array(entries) => (true, entries),
+            entry => (false, vec![entry]),
+        };
+
+        let mut req = Self {
+            batch,
+            methods: Vec::new(),
+            forward: Vec::new(),
+            rejected: Vec::new(),
+        };
+        for entry in entries {
+            let id = entry.get("id").cloned().unwrap_or_default();
+            match entry.get("method").and_then(|method| method.as_str()) {
+                None => req.rejected.push(rpc_error(id, -32600, "invalid request")),
+                Some(method) if !PROXY_ALLOWED_METHODS.contains(&method) => req
+                    .rejected
+                    .push(rpc_error(id, -32601, "this method is not available")),
+                Some(method) => {
+                    req.methods.push(method.to_string());
+                    req.forward.push(entry);
+                }
+            }
+        }
+
+        Ok(req)
+    }
+
+    /// `false` if the request must not be sent twice, like `eth_sendRawTransaction`.
+    pub fn idempotent(&self) -> bool {
+        !self
+            .methods
+            .iter()
+            .any(|method| NON_IDEMPOTENT_METHODS.contains(&method.as_str()))
+    }
+
+    /// The body for the upstream node, only a batch if the client sent one.
+    pub fn upstream_body(&self) -> Vec<u8> {
+        if self.batch {
+            serde_json::to_vec(&self.forward).unwrap()
+        } else {
+            serde_json::to_vec(&self.forward[0]).unwrap()
+        }
+    }
+
+    /// Combines the `upstream` response with the rejected entries.
+    /// If `upstream` failed, each forwarded entry is answered with the error.
+    /// `upstream` is ignored if nothing was forwarded.
+    pub fn response(&self, upstream: Result<Value, String>) -> Value {
+        let mut responses = Vec::new();
+        if !self.forward.is_empty() {
+            match upstream {
+                Ok(Value::Array(entries)) => responses.extend(entries),
+                Ok(entry) => responses.push(entry),
+                Err(err) => responses.extend(self.forward.iter().map(|entry| {
+                    let id = entry.get("id").cloned().unwrap_or_default();
+                    serde_json::to_value(rpc_error(id, -32603, &err)).unwrap()
+                })),
+            }
+        }
+        responses.extend(
+            self.rejected
+                .iter()
+                .map(|err| serde_json::to_value(err).unwrap()),
+        );
+
+        if self.batch {
+            Value::Array(responses)
+        } else {
+            responses.pop().unwrap_or_default()
+        }
+    }
+}
+
+fn rpc_error(id: Value, code: i32, message: &str) -> JsonRpcResponseError {
+    JsonRpcResponseError {
+        jsonrpc: "2.0".to_string(),
+        id,
+        error: JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        },
+    }
+}
+
+/// Returns `nodes` in the order they should be tried for `client` (rendezvous hashing).
+/// The order of two nodes only depends on the client and the nodes themselves,
+/// so a client sticks to the same node as long as it is healthy, also if other nodes come and go.
+pub fn node_order<T: Hash>(client: &T, nodes: &[Uri]) -> Vec<Uri> {
+    let mut nodes: Vec<(u64, &Uri)> = nodes
+        .iter()
+        .map(|node| {
+            let mut hasher = DefaultHasher::new();
+            client.hash(&mut hasher);
+            node.hash(&mut hasher);
+            (hasher.finish(), node)
+        })
+        .collect();
+    nodes.sort_unstable_by_key(|(hash, _)| std::cmp::Reverse(*hash));
+
+    nodes.into_iter().map(|(_, node)| node.clone()).collect()
+}
+
+/// Forwards `req` to the rpc nodes in the order of `node_order` for `client_ip`
+/// and returns the upstream response.
+pub async fn proxy(
+    shared_state: &SharedState,
+    client: &hyper::Client<HttpConnector>,
+    client_ip: IpAddr,
+    req: &ProxyRequest,
+) -> Result<Value, String> {
+    let nodes = node_order(&client_ip, &shared_state.rw.lock().await.nodes);
+    let timeout = shared_state.config.lock().await.proxy_timeout;
+    let upstream = forward(
+        client,
+        &nodes,
+        req.upstream_body(),
+        timeout,
+        req.idempotent(),
+    )
+    .await;
+    let node = match &upstream {
+        Ok((node, _)) => node.to_string(),
+        Err(_) => "none".to_string(),
+    };
+    for method in req.methods.iter() {
+        shared_state
+            .metrics
+            .inc(&PROXY_REQUESTS, &[("method", method), ("node", &node)], 1.0);
+    }
+
+    upstream.map(|(_, value)| value)
+}
+
+/// Sends `body` to the first of `nodes` that answers with json within `timeout` milliseconds.
+/// Returns that node and its response.
+/// Unless `idempotent`, the next node is only tried if the previous one couldn't be reached.
+pub async fn forward(
+    client: &hyper::Client<HttpConnector>,
+    nodes: &[Uri],
+    body: Vec<u8>,
+    timeout: u64,
+    idempotent: bool,
+) -> Result<(Uri, Value), String> {
+    for node in nodes {
+        match forward_to(client, node, body.clone(), timeout).await {
+            Ok(value) => return Ok((node.clone(), value)),
+            Err((err, sent)) if sent && !idempotent => {
+                log::warn!("proxy: {} failed after sending the request: {}", node, err);
+                return Err(err);
+            }
+            Err((err, _)) => log::warn!("proxy: {} failed, trying the next node: {}", node, err),
+        }
+    }
+
+    Err("no rpc node available".to_string())
+}
+
+/// Errors with `true` if the request may have reached `node`.
+async fn forward_to(
+    client: &hyper::Client<HttpConnector>,
+    node: &Uri,
+    body: Vec<u8>,
+    timeout: u64,
+) -> Result<Value, (String, bool)> {
+    let node_req = Request::post(node)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_millis(timeout), async {
+        let resp = client
+            .request(node_req)
+            .await
+            .map_err(|e| (e.to_string(), !e.is_connect()))?;
+        if resp.status().is_server_error() {
+            return Err((format!("status {}", resp.status()), true));
+        }
+        let body = hyper::body::to_bytes(resp.into_body())
+            .await
+            .map_err(|e| (e.to_string(), true))?;
+
+        serde_json::from_slice(&body).map_err(|e| (e.to_string(), true))
+    })
+    .await
+    .map_err(|_| (format!("timeout after {timeout}ms"), true))?
+}
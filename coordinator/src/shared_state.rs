@@ -1,6 +1,18 @@
 use crate::config::Config;
+use crate::error::CoordinatorError;
+use crate::event_sync::{EventSync, MAX_LOG_RANGE};
+use crate::l1_sender::{L1PendingTx, L1Sender};
+use crate::l2_sender::L2Sender;
+use crate::metrics::*;
+use crate::provers::ProverPool;
+use crate::signer::{load_signer, L1Role, LocalSigner, TxSigner};
+use crate::steps::timestamp_ms;
+use crate::steps::StepStatus;
+use crate::store::PersistentState;
+use crate::store::Store;
 use crate::structs::*;
 use crate::utils::*;
+use crate::ws::NewHead;
 use ethers_core::abi::Abi;
 use ethers_core::abi::AbiParser;
 use ethers_core::abi::RawLog;
@@ -20,15 +32,34 @@ use hyper::Uri;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::cmp;
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio::sync::watch;
 use tokio::sync::Mutex;
+use zkevm_common::json_rpc::jsonrpc_call;
 use zkevm_common::json_rpc::jsonrpc_request;
 use zkevm_common::json_rpc::jsonrpc_request_client;
+use zkevm_common::json_rpc::JsonRpcClientError;
+use zkevm_common::prover::ProofRandomness;
 use zkevm_common::prover::ProofRequestOptions;
 use zkevm_common::prover::Proofs;
+use zkevm_common::prover::ProverError;
+use zkevm_common::prover::WitnessSource;
+
+/// The maximum L1 reorg depth, in synced ranges with events, that can be rolled back.
+const MAX_L1_CHECKPOINTS: usize = 128;
+/// Delivered, dropped or expired messages are forgotten after this many milliseconds.
+const MESSAGE_RECORD_TTL: u64 = 7 * 24 * 3600 * 1000;
+/// Number of blocks buffered for slow `new_heads` receivers
+const NEW_HEADS_CAPACITY: usize = 64;
 
 pub struct RoState {
     pub l2_message_deliverer_addr: Address,
@@ -40,17 +71,57 @@ pub struct RoState {
     pub message_delivered_topic: H256,
 
     pub http_client: hyper::Client<HttpConnector>,
+    /// The wallet of `Config::l1_priv`
     pub l1_wallet: LocalWallet,
-    pub l2_wallet: LocalWallet,
+    /// Signs the L2 transactions, `Config::l2_key` or `Config::l1_priv`
+    pub l2_wallet: Arc<dyn TxSigner>,
+    /// Delivers the L1 > L2 messages, `l2_wallet` if `Config::l2_deliverer_key` is not set
+    pub l2_deliverer: Arc<dyn TxSigner>,
+    /// Sends the faucet L2 payouts, `l2_wallet` if `Config::l2_faucet_key` is not set
+    pub l2_faucet: Arc<dyn TxSigner>,
 
     pub bridge_abi: Abi,
 }
 
 impl RoState {
     pub async fn new(config: &Config) -> Self {
+        let http_client = hyper::Client::new();
         let l1_wallet = get_wallet(&config.l1_rpc_url, &config.l1_priv).await;
-        // TODO: support different keys for L1 and L2
-        let l2_wallet = get_wallet(&config.l2_rpc_url, &config.l1_priv).await;
+        let l1_priv_l2_wallet = get_wallet(&config.l2_rpc_url, &config.l1_priv).await;
+        let l2_chain_id = l1_priv_l2_wallet.chain_id();
+        let l2_wallet: Arc<dyn TxSigner> = match &config.l2_key {
+            Some(spec) => load_signer(
+                &http_client,
+                spec,
+                config.keystore_password.as_deref(),
+                l2_chain_id,
+            )
+            .await
+            .expect("l2 signer"),
+            None => Arc::new(LocalSigner(l1_priv_l2_wallet)),
+        };
+        let l2_deliverer: Arc<dyn TxSigner> = match &config.l2_deliverer_key {
+            Some(spec) => load_signer(
+                &http_client,
+                spec,
+                config.keystore_password.as_deref(),
+                l2_chain_id,
+            )
+            .await
+            .expect("l2 deliverer signer"),
+            None => l2_wallet.clone(),
+        };
+        let l2_faucet: Arc<dyn TxSigner> = match &config.l2_faucet_key {
+            Some(spec) => load_signer(
+                &http_client,
+                spec,
+                config.keystore_password.as_deref(),
+                l2_chain_id,
+            )
+            .await
+            .expect("l2 faucet signer"),
+            None => l2_wallet.clone(),
+        };
 
         let abi = get_abi();
 
@@ -72,18 +143,24 @@ impl RoState {
             message_dispatched_topic,
             message_delivered_topic,
 
-            http_client: hyper::Client::new(),
+            http_client,
             l1_wallet,
             l2_wallet,
+            l2_deliverer,
+            l2_faucet,
             bridge_abi: abi,
         }
     }
 }
 
 pub struct RwState {
+    /// Hash of the L2 genesis block, set by `SharedState::init`
+    pub genesis_hash: H256,
     pub chain_state: ForkchoiceStateV1,
     pub nodes: Vec<Uri>,
+    /// Proof requests by block number, see `SharedState::request_proofs_ahead`
     pub prover_requests: HashMap<U64, Option<Proofs>>,
+    /// Number of `prover_requests` without a proof yet
     pub pending_proofs: u32,
     pub l1_last_sync_block: U64,
     pub l2_last_sync_block: U64,
@@ -91,14 +168,184 @@ pub struct RwState {
     pub l2_delivered_messages: Vec<H256>,
     pub l2_message_queue: Vec<MessageBeacon>,
     pub l1_delivered_messages: Vec<H256>,
+    /// The most recent `MAX_L1_CHECKPOINTS` checkpoints, oldest first
+    pub l1_checkpoints: VecDeque<L1Checkpoint>,
+    /// The changes of the bridge events since the latest checkpoint
+    pub l1_changes: L1Changes,
+    pub l1_events: EventSync,
+    pub l2_events: EventSync,
+    /// Failures and retry schedule of each step run via `SharedState::run_step`
+    pub steps: BTreeMap<&'static str, StepStatus>,
+    /// L1 block of the last `submitBlock` transaction, see `RwState::awaits_sync`
+    pub l1_submit_block: Option<U64>,
+    /// L1 block of the last `finalizeBlock` transaction
+    pub l1_finalize_block: Option<U64>,
+    pub provers: ProverPool,
+    /// Block production is paused via the admin RPC
+    pub paused: bool,
+    /// `sync` rolls back to the newest checkpoint before this L1 block
+    pub l1_resync: Option<U64>,
+    /// The lifecycle of each bridge message, by message id
+    pub messages: HashMap<H256, MessageRecord>,
+    /// The dropped messages, `sync` doesn't queue them again after a `resync_l1`
+    pub dropped_messages: HashSet<H256>,
+    /// The delivery estimate of each queued L1 > L2 message for the latest imported L1 block
+    pub delivery_estimates: HashMap<H256, DeliveryEstimate>,
+    /// Expired L1 > L2 messages to drop on L1, see `SharedState::drop_expired_messages`
+    pub l1_expired_messages: Vec<MessageBeacon>,
+    /// Expired L2 > L1 messages to drop on L2
+    pub l2_expired_messages: Vec<MessageBeacon>,
 
     /// keeps track of the timestamp used for preparing the last block
     _prev_timestamp: u64,
 }
 
+impl RwState {
+    /// Records the L1 derived state after syncing up to L1 block `num`
+    /// and advances `l1_last_sync_block`.
+    /// `events` tells if bridge events were applied since the previous checkpoint,
+    /// a checkpoint without events is replaced by the next one.
+    pub fn push_l1_checkpoint(&mut self, num: U64, hash: H256, events: bool) {
+        self.l1_last_sync_block = num;
+        self.prune_delivered_messages();
+        let mut checkpoint = L1Checkpoint {
+            number: num,
+            hash,
+            events,
+            safe_block_hash: self.chain_state.safe_block_hash,
+            finalized_block_hash: self.chain_state.finalized_block_hash,
+            changes: std::mem::take(&mut self.l1_changes),
+        };
+        if self.l1_checkpoints.len() > 1 && !self.l1_checkpoints.back().unwrap().events {
+            let mut prev = self.l1_checkpoints.pop_back().unwrap();
+            prev.changes.extend(checkpoint.changes);
+            checkpoint.changes = prev.changes;
+        }
+        self.l1_checkpoints.push_back(checkpoint);
+        while self.l1_checkpoints.len() > MAX_L1_CHECKPOINTS {
+            self.l1_checkpoints.pop_front();
+        }
+    }
+
+    /// Rolls the L1 derived state back to the checkpoint at `index`.
+    /// Undoes the changes since the latest checkpoint and those of the newer checkpoints,
+    /// the messages first seen in them are forgotten, including their tombstones.
+    pub fn rollback_l1(&mut self, index: usize) {
+        let mut undo = vec![std::mem::take(&mut self.l1_changes)];
+        while self.l1_checkpoints.len() > index + 1 {
+            undo.push(self.l1_checkpoints.pop_back().unwrap().changes);
+        }
+        // newest first
+        for changes in undo {
+            let l1_queued: HashSet<H256> = changes.l1_queued.into_iter().collect();
+            let l1_delivered: HashSet<H256> = changes.l1_delivered.into_iter().collect();
+            let l2_queued: HashSet<H256> = changes.l2_queued.into_iter().collect();
+            self.l1_message_queue
+                .retain(|msg| !l1_queued.contains(&msg.id));
+            self.l1_delivered_messages
+                .retain(|id| !l1_delivered.contains(id));
+            self.l2_message_queue
+                .retain(|msg| !l2_queued.contains(&msg.id));
+            for (id, record) in changes.records {
+                match record {
+                    Some(record) => {
+                        self.messages.insert(id, record);
+                    }
+                    None => {
+                        self.messages.remove(&id);
+                        self.dropped_messages.remove(&id);
+                    }
+                }
+            }
+        }
+
+        let checkpoint = self.l1_checkpoints.back().expect("checkpoint");
+        log::warn!("L1: rolling back to block {}", checkpoint.number);
+        self.l1_last_sync_block = checkpoint.number;
+        self.chain_state.safe_block_hash = checkpoint.safe_block_hash;
+        self.chain_state.finalized_block_hash = checkpoint.finalized_block_hash;
+        let queued: HashSet<H256> = self.l1_message_queue.iter().map(|msg| msg.id).collect();
+        self.delivery_estimates.retain(|id, _| queued.contains(id));
+    }
+
+    /// Tells if `sync` didn't apply the L1 block `tx_block` of a transaction yet.
+    /// `submit_blocks` and `finalize_blocks` wait for it to not redo the same blocks.
+    pub fn awaits_sync(&self, tx_block: Option<U64>) -> bool {
+        matches!(tx_block, Some(num) if num > self.l1_last_sync_block)
+    }
+
+    /// Forgets the delivered messages that are no longer queued.
+    /// Deliveries seen before the dispatch of the message are kept until the dispatch is synced.
+    pub fn prune_delivered_messages(&mut self) {
+        let messages = &self.messages;
+        let undispatched =
+            |id: &H256| matches!(messages.get(id), Some(record) if record.dispatch_tx.is_none());
+
+        let queued: HashSet<H256> = self
+            .l2_message_queue
+            .iter()
+            .chain(self.l2_expired_messages.iter())
+            .map(|msg| msg.id)
+            .collect();
+        self.l1_delivered_messages
+            .retain(|id| queued.contains(id) || undispatched(id));
+
+        let queued: HashSet<H256> = self
+            .l1_message_queue
+            .iter()
+            .chain(self.l1_expired_messages.iter())
+            .map(|msg| msg.id)
+            .collect();
+        self.l2_delivered_messages
+            .retain(|id| queued.contains(id) || undispatched(id));
+    }
+
+    /// Returns the record of message `id`, created if unknown.
+    pub fn message_record(
+        &mut self,
+        id: H256,
+        direction: MessageDirection,
+        now: u64,
+    ) -> &mut MessageRecord {
+        if !self.messages.contains_key(&id) {
+            self.messages.retain(|_, record| {
+                record.state < MessageState::Dropped || record.updated_at + MESSAGE_RECORD_TTL > now
+            });
+        }
+
+        self.messages
+            .entry(id)
+            .or_insert_with(|| MessageRecord::new(id, direction, now))
+    }
+
+    /// Like `message_record`, records the current record in `l1_changes` for a rollback.
+    /// Used for the changes of the L1 bridge events.
+    pub fn l1_message_record(
+        &mut self,
+        id: H256,
+        direction: MessageDirection,
+        now: u64,
+    ) -> &mut MessageRecord {
+        if !self.l1_changes.records.contains_key(&id) {
+            let record = self.messages.get(&id).cloned();
+            self.l1_changes.records.insert(id, record);
+        }
+
+        self.message_record(id, direction, now)
+    }
+
+    /// Marks the message `id` as dropped for good.
+    pub fn tombstone_message(&mut self, id: H256, direction: MessageDirection, now: u64) {
+        self.message_record(id, direction, now)
+            .advance(MessageState::Dropped, now);
+        self.dropped_messages.insert(id);
+    }
+}
+
 impl Default for RwState {
     fn default() -> Self {
         RwState {
+            genesis_hash: H256::zero(),
             chain_state: ForkchoiceStateV1 {
                 head_block_hash: H256::zero(),
                 safe_block_hash: H256::zero(),
@@ -113,6 +360,21 @@ impl Default for RwState {
             l2_delivered_messages: Vec::new(),
             l2_message_queue: Vec::new(),
             l1_delivered_messages: Vec::new(),
+            l1_checkpoints: VecDeque::new(),
+            l1_changes: L1Changes::default(),
+            l1_events: EventSync::default(),
+            l2_events: EventSync::default(),
+            steps: BTreeMap::new(),
+            l1_submit_block: None,
+            l1_finalize_block: None,
+            provers: ProverPool::default(),
+            paused: false,
+            l1_resync: None,
+            messages: HashMap::new(),
+            dropped_messages: HashSet::new(),
+            delivery_estimates: HashMap::new(),
+            l1_expired_messages: Vec::new(),
+            l2_expired_messages: Vec::new(),
 
             _prev_timestamp: 0,
         }
@@ -124,22 +386,116 @@ pub struct SharedState {
     pub config: Arc<Mutex<Config>>,
     pub ro: Arc<RoState>,
     pub rw: Arc<Mutex<RwState>>,
+    /// Persists parts of `rw` if `Config::state_path` is set
+    pub store: Option<Store>,
+    /// Sends all L1 transactions of `ro.l1_wallet`
+    pub l1_sender: L1Sender,
+    /// The sender of each role, roles with the same key share the sender
+    pub l1_role_senders: BTreeMap<L1Role, L1Sender>,
+    /// Sends the L2 transactions of `ro.l2_wallet`
+    pub l2_sender: L2Sender,
+    /// Signs the L1 > L2 message deliveries of `ro.l2_deliverer`, shared with `l2_sender` for the same key
+    pub l2_deliverer_sender: L2Sender,
+    /// Sends the faucet L2 payouts of `ro.l2_faucet`, shared with the senders above for the same key
+    pub l2_faucet_sender: L2Sender,
+    pub metrics: Metrics,
+    chain_state_tx: Arc<watch::Sender<ForkchoiceStateV1>>,
+    /// Receives `rw.chain_state` after each step that changed it
+    pub chain_state_rx: watch::Receiver<ForkchoiceStateV1>,
+    /// Sends the header and logs of each block set by `set_chain_head`, see `ws`
+    pub new_heads: broadcast::Sender<NewHead>,
+    /// Number of open websocket connections, see `ws::upgrade`
+    pub ws_connections: Arc<AtomicUsize>,
+    /// Serializes `persist` so that an older snapshot doesn't overwrite a newer one
+    persist_lock: Arc<Mutex<()>>,
 }
 
 impl SharedState {
-    pub async fn new(config: &Config) -> Self {
+    /// The state is persisted to `store`, if given.
+    pub async fn new(config: &Config, store: Option<Store>) -> Self {
+        let config = Arc::new(Mutex::new(config.clone()));
+        let ro = RoState::new(&*config.lock().await).await;
+        let l1_sender = L1Sender::spawn(
+            ro.http_client.clone(),
+            config.clone(),
+            Arc::new(LocalSigner(ro.l1_wallet.clone())),
+        );
+        let mut l1_role_senders = BTreeMap::new();
+        for role in L1Role::ALL {
+            let spec = config.lock().await.l1_role_key(role).map(String::from);
+            let sender = match spec {
+                None => l1_sender.clone(),
+                Some(spec) => {
+                    let password = config.lock().await.keystore_password.clone();
+                    let signer = load_signer(
+                        &ro.http_client,
+                        &spec,
+                        password.as_deref(),
+                        ro.l1_wallet.chain_id(),
+                    )
+                    .await
+                    .unwrap_or_else(|err| panic!("{role:?} signer: {err}"));
+                    let existing = l1_role_senders
+                        .values()
+                        .chain([&l1_sender])
+                        .find(|sender: &&L1Sender| sender.address() == signer.address())
+                        .cloned();
+
+                    existing.unwrap_or_else(|| {
+                        L1Sender::spawn(ro.http_client.clone(), config.clone(), signer)
+                    })
+                }
+            };
+            log::info!("L1 {:?}: {:?}", role, sender.address());
+            l1_role_senders.insert(role, sender);
+        }
+        // one sender per L2 key, it owns the nonce
+        let mut l2_senders: Vec<L2Sender> = Vec::new();
+        let mut l2_sender_for = |signer: &Arc<dyn TxSigner>| {
+            let existing = l2_senders
+                .iter()
+                .find(|sender| sender.address() == signer.address())
+                .cloned();
+
+            existing.unwrap_or_else(|| {
+                let sender = L2Sender::new(ro.http_client.clone(), config.clone(), signer.clone());
+                l2_senders.push(sender.clone());
+                sender
+            })
+        };
+        let l2_sender = l2_sender_for(&ro.l2_wallet);
+        let l2_deliverer_sender = l2_sender_for(&ro.l2_deliverer);
+        let l2_faucet_sender = l2_sender_for(&ro.l2_faucet);
+        let (chain_state_tx, chain_state_rx) = watch::channel(ForkchoiceStateV1::default());
+
         Self {
-            config: Arc::new(Mutex::new(config.clone())),
-            ro: Arc::new(RoState::new(config).await),
+            config,
+            ro: Arc::new(ro),
             rw: Arc::new(Mutex::new(RwState::default())),
+            store,
+            l1_sender,
+            l1_role_senders,
+            l2_sender,
+            l2_deliverer_sender,
+            l2_faucet_sender,
+            metrics: Metrics::default(),
+            chain_state_tx: Arc::new(chain_state_tx),
+            chain_state_rx,
+            new_heads: broadcast::channel(NEW_HEADS_CAPACITY).0,
+            ws_connections: Arc::new(AtomicUsize::new(0)),
+            persist_lock: Arc::new(Mutex::new(())),
         }
     }
 
     /// Initiates configuration from environment variables only.
     pub async fn from_env() -> Self {
         let config = Config::from_env();
+        let store = config
+            .state_path
+            .as_ref()
+            .map(|path| Store::open(path).expect("open state database"));
 
-        Self::new(&config).await
+        Self::new(&config, store).await
     }
 
     pub async fn init(&self) {
@@ -152,6 +508,12 @@ impl SharedState {
             .await
             .expect("genesis block");
         let h = genesis.hash.unwrap();
+        self.rw.lock().await.genesis_hash = h;
+
+        if self.restore(h).await {
+            return;
+        }
+
         log::info!("init with genesis: {:?}", h);
 
         let chain_state = &mut self.rw.lock().await.chain_state;
@@ -180,15 +542,166 @@ impl SharedState {
         }
     }
 
-    pub async fn sync(&self) {
+    /// Loads the state from `store`, if any.
+    /// Returns `true` if a previous state was restored.
+    /// A state of another L2 chain is ignored, it is overwritten by the next `persist`.
+    /// Panics if the L2 node does not know the blocks of the state.
+    async fn restore(&self, genesis_hash: H256) -> bool {
+        let state = match &self.store {
+            Some(store) => store.load().expect("load state"),
+            None => None,
+        };
+        let state = match state {
+            Some(state) => state,
+            None => return false,
+        };
+
+        if state.genesis_hash != genesis_hash {
+            log::warn!(
+                "stored state is for the L2 genesis {:?} instead of {:?}, starting over",
+                state.genesis_hash,
+                genesis_hash
+            );
+            return false;
+        }
+        for (kind, hash) in [
+            ("finalized", state.chain_state.finalized_block_hash),
+            ("safe", state.chain_state.safe_block_hash),
+            ("head", state.chain_state.head_block_hash),
+        ] {
+            if let Err(err) = self
+                .request_l2::<_, BlockHeader>("eth_getHeaderByHash", [hash])
+                .await
+            {
+                panic!(
+                    "restored {kind} block {hash:?} is not on the L2 node ({err}), \
+                     the state store may belong to another chain"
+                );
+            }
+        }
+
+        let mut rw = self.rw.lock().await;
+        rw.chain_state = state.chain_state;
+        rw.l1_last_sync_block = state.l1_last_sync_block;
+        rw.l2_last_sync_block = state.l2_last_sync_block;
+        rw.l1_message_queue = state.l1_message_queue;
+        rw.l2_delivered_messages = state.l2_delivered_messages;
+        rw.l2_message_queue = state.l2_message_queue;
+        rw.l1_delivered_messages = state.l1_delivered_messages;
+        rw.l1_checkpoints = state.l1_checkpoints;
+        rw.l1_changes = state.l1_changes;
+        rw.messages = state.messages;
+        rw.dropped_messages = state.dropped_messages;
+        rw.l1_expired_messages = state.l1_expired_messages;
+        rw.l2_expired_messages = state.l2_expired_messages;
+        // a L1 range that was persisted while it was applied is synced again
+        if !rw.l1_checkpoints.is_empty() {
+            let index = rw.l1_checkpoints.len() - 1;
+            rw.rollback_l1(index);
+        }
+        log::info!(
+            "restored state: head={:?} l1_last_sync_block={} l2_last_sync_block={}",
+            rw.chain_state.head_block_hash,
+            rw.l1_last_sync_block,
+            rw.l2_last_sync_block
+        );
+
+        true
+    }
+
+    /// Writes the current state to `store`, if configured.
+    /// Only the changed fields are written, a failed write is retried by the next call.
+    pub async fn persist(&self) {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return,
+        };
+
+        let _persist_lock = self.persist_lock.lock().await;
+        let state = {
+            let rw = self.rw.lock().await;
+            PersistentState {
+                genesis_hash: rw.genesis_hash,
+                chain_state: rw.chain_state,
+                l1_last_sync_block: rw.l1_last_sync_block,
+                l2_last_sync_block: rw.l2_last_sync_block,
+                l1_message_queue: rw.l1_message_queue.clone(),
+                l2_delivered_messages: rw.l2_delivered_messages.clone(),
+                l2_message_queue: rw.l2_message_queue.clone(),
+                l1_delivered_messages: rw.l1_delivered_messages.clone(),
+                l1_checkpoints: rw.l1_checkpoints.clone(),
+                l1_changes: rw.l1_changes.clone(),
+                messages: rw.messages.clone(),
+                dropped_messages: rw.dropped_messages.clone(),
+                l1_expired_messages: rw.l1_expired_messages.clone(),
+                l2_expired_messages: rw.l2_expired_messages.clone(),
+            }
+        };
+
+        if let Err(err) = store.save(&state).await {
+            log::error!("persist: {}", err);
+        }
+    }
+
+    /// Runs `step` in its own task unless it is backing off from previous failures,
+    /// records the outcome under `name` and persists the state.
+    /// Errors and panics are logged and retried later.
+    pub async fn run_step<F, Fut>(&self, name: &'static str, step: F)
+    where
+        F: FnOnce(SharedState) -> Fut,
+        Fut: Future<Output = Result<(), CoordinatorError>> + Send + 'static,
+    {
+        if !self
+            .rw
+            .lock()
+            .await
+            .steps
+            .entry(name)
+            .or_default()
+            .ready(timestamp_ms())
+        {
+            log::trace!("{}: backing off", name);
+            return;
+        }
+
+        let res = match tokio::spawn(step(self.clone())).await {
+            Ok(res) => res,
+            Err(err) => Err(CoordinatorError::internal(name, err)),
+        };
+        if let Err(err) = &res {
+            log::error!("{}: {}", name, err);
+        }
+        self.rw
+            .lock()
+            .await
+            .steps
+            .entry(name)
+            .or_default()
+            .record(&res, timestamp_ms());
+
+        let chain_state = self.rw.lock().await.chain_state;
+        if *self.chain_state_rx.borrow() != chain_state {
+            // can't fail as long as `chain_state_rx` exists
+            let _ = self.chain_state_tx.send(chain_state);
+        }
+
+        self.persist().await;
+    }
+
+    pub async fn sync(&self) -> Result<(), CoordinatorError> {
         // sync events
         let latest_block: U64 = self
             .request_l1("eth_blockNumber", ())
             .await
-            .expect("eth_blockNumber");
-        let mut last_to_block: U64 = U64::zero();
-        let mut from: U64 = self.rw.lock().await.l1_last_sync_block + 1;
-        let mut filter = Filter::new()
+            .map_err(|e| CoordinatorError::l1("eth_blockNumber", e))?;
+        // only act on events with enough confirmations
+        let latest_block =
+            latest_block.saturating_sub(U64::from(self.config.lock().await.l1_confirmations));
+        self.apply_l1_resync().await;
+        self.check_l1_reorg().await?;
+        let from: U64 = self.rw.lock().await.l1_last_sync_block + 1;
+        let mut events = self.rw.lock().await.l1_events;
+        let filter = Filter::new()
             .address(ValueOrArray::Value(self.config.lock().await.l1_bridge))
             .topic0(ValueOrArray::Array(vec![
                 self.ro.block_beacon_topic,
@@ -197,103 +710,337 @@ impl SharedState {
                 self.ro.message_delivered_topic,
             ]));
 
-        while from <= latest_block {
-            // TODO: increase or decrease request range depending on fetch success
-            let to = cmp::min(from + 1u64, latest_block);
-            log::trace!("fetching l1 logs from={} to={}", from, to);
-            filter = filter.from_block(from).to_block(to);
+        let res = events
+            .sync(
+                &filter,
+                from,
+                latest_block,
+                |filter| async move {
+                    self.request_l1::<_, Vec<Log>>("eth_getLogs", [filter])
+                        .await
+                        .map_err(|e| CoordinatorError::l1("eth_getLogs", e))
+                },
+                |from, to, logs| self.apply_l1_range(from, to, logs),
+            )
+            .await;
+        self.rw.lock().await.l1_events = events;
+        res?;
 
-            let logs: Vec<Log> = self
-                .request_l1("eth_getLogs", [&filter])
-                .await
-                .expect("eth_getLogs");
-            // TODO: ugly hack to fix geth inconstency issues
-            if !logs.is_empty() {
-                last_to_block = to;
+        self.sync_l2().await
+    }
+
+    /// Applies the L1 logs of the blocks `from..=to` and pushes a checkpoint for `to`.
+    /// Returns `false` if the range doesn't link to the latest checkpoint or its logs
+    /// are not canonical, the reorg is handled by the next `sync`.
+    async fn apply_l1_range(
+        &self,
+        from: U64,
+        to: U64,
+        logs: Vec<Log>,
+    ) -> Result<bool, CoordinatorError> {
+        let prev_hash = self
+            .rw
+            .lock()
+            .await
+            .l1_checkpoints
+            .back()
+            .expect("checkpoint")
+            .hash;
+        // the range has to link to the previous one and all logs must belong
+        // to canonical blocks, otherwise L1 reorged in the meantime
+        let linked = match self.l1_header(from).await {
+            Ok(header) => header.parent_hash == prev_hash,
+            Err(_) => false,
+        };
+        if !linked {
+            log::warn!("L1 block {} doesn't link to {:?}, reorg?", from, prev_hash);
+            return Ok(false);
+        }
+        let to_hash = match self.l1_header(to).await {
+            Ok(header) if self.l1_logs_canonical(&logs).await => header.hash,
+            _ => {
+                log::warn!("L1 logs from={} to={} are not canonical, reorg?", from, to);
+                return Ok(false);
+            }
+        };
+
+        // a partially applied range is reverted, it is synced again on the next run
+        let events = !logs.is_empty();
+        if let Err(err) = self.apply_l1_logs(logs).await {
+            let mut rw = self.rw.lock().await;
+            let index = rw.l1_checkpoints.len() - 1;
+            rw.rollback_l1(index);
+            return Err(err);
+        }
+
+        self.rw.lock().await.push_l1_checkpoint(to, to_hash, events);
+        Ok(true)
+    }
+
+    /// Returns the hashes of the blocks submitted by the calldata of a
+    /// `submitBlock` or `submitBlocks` transaction, in submission order.
+    fn submitted_block_hashes(&self, input: &[u8]) -> Result<Vec<H256>, CoordinatorError> {
+        let mut tokens = Vec::new();
+        for name in ["submitBlock", "submitBlocks"] {
+            let function = self.ro.bridge_abi.function(name).unwrap();
+            if input.len() >= 4 && input[0..4] == function.short_signature() {
+                tokens = function
+                    .decode_input(&input[4..])
+                    .map_err(|e| CoordinatorError::internal("submitBlock input", e))?;
             }
+        }
+        let witnesses = match tokens.pop() {
+            Some(Token::Bytes(witness)) => vec![witness],
+            Some(Token::Array(witnesses)) => witnesses
+                .into_iter()
+                .filter_map(|token| token.into_bytes())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut hashes = Vec::new();
+
+        for block_data in witnesses {
+            let rlp = rlp::Rlp::new(&block_data);
+            let info = rlp
+                .payload_info()
+                .map_err(|e| CoordinatorError::internal("submitBlock payload", e))?;
+            let block_header = &rlp.as_raw()[0..info.header_len + info.value_len];
+            hashes.push(H256::from(keccak256(block_header)));
+        }
+
+        Ok(hashes)
+    }
 
-            for log in logs {
-                let topic = log.topics[0];
+    /// Applies the bridge events of `logs` to the state.
+    async fn apply_l1_logs(&self, logs: Vec<Log>) -> Result<(), CoordinatorError> {
+        // the blocks submitted per transaction and the number of their events seen so far
+        let mut submitted: HashMap<H256, (Vec<H256>, usize)> = HashMap::new();
 
-                if topic == self.ro.block_beacon_topic {
-                    let tx_hash = log.transaction_hash.expect("log txhash");
+        for log in logs {
+            let topic = log.topics[0];
+
+            if topic == self.ro.block_beacon_topic {
+                let tx_hash = log.transaction_hash.ok_or_else(|| {
+                    CoordinatorError::l1("BlockSubmitted", "log without transaction hash")
+                })?;
+                if let Entry::Vacant(entry) = submitted.entry(tx_hash) {
                     let tx: Transaction = self
                         .request_l1("eth_getTransactionByHash", [tx_hash])
                         .await
-                        .expect("tx");
-
-                    let tx_data = tx.input.as_ref();
+                        .map_err(|e| CoordinatorError::l1("eth_getTransactionByHash", e))?;
+                    entry.insert((self.submitted_block_hashes(tx.input.as_ref())?, 0));
+                }
 
-                    // TODO: handle the case if len < 68
-                    let len = U256::from(&tx_data[36..68]).as_usize();
-                    let start = 68;
-                    let end = start + len;
-                    if end > tx_data.len() {
-                        log::warn!("TODO: zeropad block data");
-                    }
-                    let rlp = rlp::Rlp::new(&tx_data[start..end]);
-                    let info = rlp.payload_info().expect("payload_info");
-                    let block_header = &rlp.as_raw()[0..info.header_len + info.value_len];
-                    let block_hash = H256::from(keccak256(block_header));
-                    log::info!("BlockSubmitted: {:?} via {:?}", block_hash, tx_hash);
-
-                    let resp: Result<serde_json::Value, String> =
-                        self.request_l2("eth_getHeaderByHash", [block_hash]).await;
-
-                    if resp.is_err() {
-                        log::error!(
-                            "TODO: block not found {} {}",
-                            block_hash,
-                            resp.err().unwrap()
-                        );
-                    }
+                // `submitBlocks` emits the events in the order of its witnesses
+                let (hashes, index) = submitted.get_mut(&tx_hash).unwrap();
+                let block_hash = *hashes.get(*index).ok_or_else(|| {
+                    CoordinatorError::internal(
+                        "BlockSubmitted",
+                        format!("no submitted block #{} in {:?}", index, tx_hash),
+                    )
+                })?;
+                *index += 1;
+                log::info!("BlockSubmitted: {:?} via {:?}", block_hash, tx_hash);
 
-                    self.rw.lock().await.chain_state.safe_block_hash = block_hash;
-                    continue;
-                }
+                let resp: Result<serde_json::Value, String> =
+                    self.request_l2("eth_getHeaderByHash", [block_hash]).await;
 
-                if topic == self.ro.block_finalized_topic {
-                    let block_hash = H256::from_slice(log.data.as_ref());
-                    log::info!(
-                        "BlockFinalized: {:?} via {:?}",
+                if resp.is_err() {
+                    log::error!(
+                        "TODO: block not found {} {}",
                         block_hash,
-                        log.transaction_hash
+                        resp.err().unwrap()
                     );
+                }
 
-                    self.rw.lock().await.chain_state.finalized_block_hash = block_hash;
-                    self.record_l2_messages(block_hash).await;
-                    continue;
+                self.rw.lock().await.chain_state.safe_block_hash = block_hash;
+                continue;
+            }
+
+            if topic == self.ro.block_finalized_topic {
+                let block_hash = H256::from_slice(log.data.as_ref());
+                log::info!(
+                    "BlockFinalized: {:?} via {:?}",
+                    block_hash,
+                    log.transaction_hash
+                );
+
+                let mut rw = self.rw.lock().await;
+                rw.chain_state.finalized_block_hash = block_hash;
+                // L1 > L2 messages delivered in this block
+                let now = timestamp_ms();
+                let delivered: Vec<H256> = rw
+                    .messages
+                    .values()
+                    .filter(|record| {
+                        record.direction == MessageDirection::L1ToL2
+                            && record.l2_block == Some(block_hash)
+                    })
+                    .map(|record| record.id)
+                    .collect();
+                for id in delivered {
+                    let record = rw.l1_message_record(id, MessageDirection::L1ToL2, now);
+                    if record.advance(MessageState::Delivered, now) {
+                        record.finalize_tx = log.transaction_hash;
+                    }
                 }
+                drop(rw);
 
-                if topic == self.ro.message_dispatched_topic {
-                    let beacon = self._parse_message_beacon(log);
-                    log::info!("L1:MessageDispatched:{:?}", beacon.id);
-                    log::debug!("{:?}", beacon);
-                    self.rw.lock().await.l1_message_queue.push_back(beacon);
+                self.record_l2_messages(block_hash, log.transaction_hash)
+                    .await?;
+                continue;
+            }
+
+            if topic == self.ro.message_dispatched_topic {
+                let tx_hash = log.transaction_hash;
+                let beacon = self._parse_message_beacon(log);
+                log::info!("L1:MessageDispatched:{:?}", beacon.id);
+                log::debug!("{:?}", beacon);
+                self.metrics.message_queued(beacon.id, timestamp_ms());
+                let mut rw = self.rw.lock().await;
+                rw.l1_message_record(beacon.id, MessageDirection::L1ToL2, timestamp_ms())
+                    .dispatch_tx = tx_hash;
+                if rw.dropped_messages.contains(&beacon.id) {
+                    log::info!("L1:MessageDispatched:{:?} was dropped", beacon.id);
                     continue;
                 }
+                rw.l1_changes.l1_queued.push(beacon.id);
+                rw.l1_message_queue.push_back(beacon);
+                continue;
+            }
 
-                if topic == self.ro.message_delivered_topic {
-                    let id = H256::from_slice(log.data.as_ref());
-                    log::info!("L1:MessageDelivered:{:?}", id);
-                    self.rw.lock().await.l1_delivered_messages.push(id);
-                    continue;
+            if topic == self.ro.message_delivered_topic {
+                let id = H256::from_slice(log.data.as_ref());
+                log::info!("L1:MessageDelivered:{:?}", id);
+                let mut rw = self.rw.lock().await;
+                let now = timestamp_ms();
+                let record = rw.l1_message_record(id, MessageDirection::L2ToL1, now);
+                if record.advance(MessageState::Delivered, now) {
+                    record.deliver_tx = log.transaction_hash;
                 }
+                rw.l1_changes.l1_delivered.push(id);
+                rw.l1_delivered_messages.push(id);
+                continue;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn l1_header(&self, num: U64) -> Result<BlockHeader, String> {
+        self.request_l1("eth_getHeaderByNumber", [num]).await
+    }
+
+    /// Checks the block hash of each log against the canonical L1 header of that block.
+    async fn l1_logs_canonical(&self, logs: &[Log]) -> bool {
+        let mut canonical: HashMap<U64, H256> = HashMap::new();
+        for log in logs {
+            let num = match log.block_number {
+                Some(num) => num,
+                // a pending log
+                None => return false,
+            };
+            let hash = match canonical.get(&num) {
+                Some(hash) => *hash,
+                None => match self.l1_header(num).await {
+                    Ok(header) => *canonical.entry(num).or_insert(header.hash),
+                    Err(_) => return false,
+                },
+            };
+            if log.block_hash != Some(hash) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Compares the latest L1 checkpoint with the canonical L1 chain and
+    /// rolls back to the most recent checkpoint that is still canonical.
+    async fn check_l1_reorg(&self) -> Result<(), CoordinatorError> {
+        if self.rw.lock().await.l1_checkpoints.is_empty() {
+            // the initial checkpoint, either genesis or a state from before checkpoints existed
+            let num = self.rw.lock().await.l1_last_sync_block;
+            let hash = self
+                .l1_header(num)
+                .await
+                .map_err(|e| CoordinatorError::l1("eth_getHeaderByNumber", e))?
+                .hash;
+            self.rw.lock().await.push_l1_checkpoint(num, hash, true);
+        }
+
+        loop {
+            let (num, hash) = {
+                let rw = self.rw.lock().await;
+                let checkpoint = rw.l1_checkpoints.back().expect("checkpoint");
+                (checkpoint.number, checkpoint.hash)
+            };
+            let canonical = self
+                .l1_header(num)
+                .await
+                .map_err(|e| CoordinatorError::l1("eth_getHeaderByNumber", e))?
+                .hash;
+            if canonical == hash {
+                return Ok(());
             }
 
-            from = to + 1u64;
+            log::warn!(
+                "L1 reorg: block {} is {:?} instead of {:?}",
+                num,
+                canonical,
+                hash
+            );
+            // under the lock `mine` updates the queues with
+            let mut rw = self.rw.lock().await;
+            if rw.l1_checkpoints.len() == 1 {
+                return Err(CoordinatorError::internal(
+                    "check_l1_reorg",
+                    "L1 reorg deeper than the retained checkpoints",
+                ));
+            }
+            let index = rw.l1_checkpoints.len() - 2;
+            rw.rollback_l1(index);
         }
+    }
+
+    /// Schedules a resync of the L1 events from block `from`, applied on the next `sync`.
+    /// Fails if there is no checkpoint before `from`.
+    pub async fn request_l1_resync(&self, from: U64) -> Result<U64, String> {
+        let mut rw = self.rw.lock().await;
+        let checkpoint = rw
+            .l1_checkpoints
+            .iter()
+            .rev()
+            .find(|checkpoint| checkpoint.number < from)
+            .ok_or_else(|| format!("no L1 checkpoint before block {from}"))?;
+        let number = checkpoint.number;
+        rw.l1_resync = Some(from);
+
+        Ok(number + 1)
+    }
 
-        if last_to_block != U64::zero() {
-            self.rw.lock().await.l1_last_sync_block = last_to_block;
+    async fn apply_l1_resync(&self) {
+        let mut rw = self.rw.lock().await;
+        let from = match rw.l1_resync.take() {
+            Some(from) => from,
+            None => return,
+        };
+        match rw.l1_checkpoints.iter().rposition(|cp| cp.number < from) {
+            Some(index) => rw.rollback_l1(index),
+            None => log::error!("L1 resync: no checkpoint before block {}", from),
         }
-        self.sync_l2().await;
     }
 
-    pub async fn mine(&self) {
+    pub async fn mine(&self) -> Result<(), CoordinatorError> {
+        if self.rw.lock().await.paused {
+            log::debug!("mine: paused");
+            return Ok(());
+        }
+
         // TODO: verify that head_hash is correct
         let head_hash = get_chain_head(&self.ro.http_client, &self.config.lock().await.l2_rpc_url)
             .await
+            .map_err(|e| CoordinatorError::l2("chain head", e))?
             .hash;
         self.rw.lock().await.chain_state.head_block_hash = head_hash;
 
@@ -307,13 +1054,13 @@ impl SharedState {
             // check l1 > l2 message queue
             let len = self.rw.lock().await.l1_message_queue.len();
             if len > 0 {
-                let mut nonce: U256 = self
-                    .request_l2(
-                        "eth_getTransactionCount",
-                        (self.ro.l2_wallet.address(), "latest"),
-                    )
+                // no other transactions of the deliverer until the block is set
+                let l2_nonce = self
+                    .l2_deliverer_sender
+                    .lock()
                     .await
-                    .expect("nonce");
+                    .map_err(|e| CoordinatorError::l2("eth_getTransactionCount", e))?;
+                let mut nonce = l2_nonce.nonce();
 
                 const LOG_TAG: &str = "L2:deliverMessage:";
 
@@ -321,22 +1068,23 @@ impl SharedState {
                 let l1_block_header: BlockHeader = self
                     .request_l1("eth_getHeaderByNumber", ["latest"])
                     .await
-                    .expect("l1 block header");
+                    .map_err(|e| CoordinatorError::l1("eth_getHeaderByNumber", e))?;
                 // TODO: figure out how to get by hash - gonna be safer
                 // Or just hash it and compare against l1_block_header.hash.
                 let block_data: Bytes = self
                     .request_l1("debug_getHeaderRlp", [l1_block_header.number.as_u64()])
                     .await
-                    .expect("block_data");
+                    .map_err(|e| CoordinatorError::l1("debug_getHeaderRlp", e))?;
                 let account_proof: Bytes = {
                     let l1_bridge_addr = self.config.lock().await.l1_bridge;
                     let proof_obj: MerkleProofRequest = self
                         .request_l1("eth_getProof", (l1_bridge_addr, (), l1_block_header.hash))
                         .await
-                        .expect("eth_getProof");
+                        .map_err(|e| CoordinatorError::l1("eth_getProof", e))?;
                     Bytes::from(marshal_proof_single(&proof_obj.account_proof))
                 };
-                let mut messages = Vec::new();
+                // the transactions sent with the same key go first
+                let mut messages = l2_nonce.pending();
                 // authorize l1 block
                 {
                     let calldata = self
@@ -350,13 +1098,16 @@ impl SharedState {
                         ])
                         .expect("calldata");
                     let tx = self
-                        .sign_l2(
+                        .sign_l2_with(
+                            &*self.ro.l2_deliverer,
                             Some(self.ro.l2_message_deliverer_addr),
                             U256::zero(),
                             nonce,
                             calldata,
+                            None,
                         )
-                        .await;
+                        .await
+                        .map_err(|e| CoordinatorError::l2("importForeignBlock", e))?;
                     messages.push(tx);
                     nonce = nonce + 1;
                 }
@@ -366,7 +1117,7 @@ impl SharedState {
                 let temporary_block = self
                     .prepare_block(block_timestamp, Some(&messages))
                     .await
-                    .expect("prepare block with import tx");
+                    .map_err(|e| CoordinatorError::l2("prepare block with import tx", e))?;
                 // import block header
                 {
                     let calldata = self
@@ -377,7 +1128,8 @@ impl SharedState {
                         .encode_input(&[block_data.into_token(), account_proof.into_token()])
                         .expect("calldata");
                     let tx = self
-                        .sign_l2_given_block_tag(
+                        .sign_l2_with(
+                            &*self.ro.l2_deliverer,
                             Some(self.ro.l2_message_deliverer_addr),
                             U256::zero(),
                             nonce,
@@ -385,32 +1137,54 @@ impl SharedState {
                             Some(format!("{:#066x}", temporary_block.hash.unwrap())),
                         )
                         .await
-                        .expect("importForeignBridgeState on temporary_block");
+                        .map_err(|e| {
+                            CoordinatorError::l2("importForeignBridgeState on temporary_block", e)
+                        })?;
                     messages.push(tx);
                     nonce = nonce + 1;
                 }
                 let mut temporary_block = self
                     .prepare_block(block_timestamp, Some(&messages))
                     .await
-                    .expect("prepare block with import tx");
+                    .map_err(|e| CoordinatorError::l2("prepare block with import tx", e))?;
                 let ts = U256::from(block_timestamp);
-                let mut drop_idxs = Vec::new();
-                let mut i = 0;
+                let mut drop_ids = Vec::new();
+                let mut delivered_ids = Vec::new();
                 let l1_bridge_addr = self.config.lock().await.l1_bridge;
-                loop {
-                    let rw = self.rw.lock().await;
-                    let msg = rw.l1_message_queue.get(i);
-                    if msg.is_none() {
-                        break;
-                    }
-                    let msg = msg.unwrap().clone();
-                    drop(rw);
+                let (fee_margin, max_messages) = {
+                    let config = self.config.lock().await;
+                    (config.message_fee_margin, config.max_messages_per_block)
+                };
+                // the gas price of `sign_l2_with`
+                let gas_price = self
+                    .request_l2::<_, U256>("eth_gasPrice", ())
+                    .await
+                    .map_err(|e| CoordinatorError::l2("eth_gasPrice", e))?
+                    * 2u64;
+                let queue: Vec<MessageBeacon> = self
+                    .rw
+                    .lock()
+                    .await
+                    .l1_message_queue
+                    .iter()
+                    .cloned()
+                    .collect();
 
+                // estimate the delivery costs of each message against the block with the imports
+                let mut candidates = Vec::new();
+                for msg in queue {
                     if msg.deadline < ts {
                         log::info!("{} {:?} deadline exceeded", LOG_TAG, msg.id);
                         log::debug!("{:?}", msg);
-                        drop_idxs.push(i);
-                        i += 1;
+                        self.set_message_state(
+                            msg.id,
+                            MessageDirection::L1ToL2,
+                            MessageState::Expired,
+                        )
+                        .await;
+                        self.queue_expired_message(MessageDirection::L1ToL2, &msg)
+                            .await;
+                        drop_ids.push(msg.id);
                         continue;
                     }
 
@@ -427,25 +1201,36 @@ impl SharedState {
                         log::debug!("{:?}", msg);
 
                         if found {
-                            drop_idxs.push(i);
-                            i += 1;
+                            drop_ids.push(msg.id);
                             continue;
                         }
                     }
 
-                    let storage_proof: Bytes = {
-                        // calculate the storage slot for this message
-                        let storage_slot = msg.storage_slot();
-                        // request proof
-                        let proof_obj: MerkleProofRequest = self
-                            .request_l1(
-                                "eth_getProof",
-                                (l1_bridge_addr, [storage_slot], l1_block_header.hash),
-                            )
-                            .await
-                            .expect("eth_getProof");
-                        // encode proof
-                        Bytes::from(marshal_proof_single(&proof_obj.storage_proof[0].proof))
+                    // the proof and the estimate only change with the imported L1 block
+                    let cached = self
+                        .rw
+                        .lock()
+                        .await
+                        .delivery_estimates
+                        .get(&msg.id)
+                        .filter(|estimate| estimate.l1_block == l1_block_header.hash)
+                        .cloned();
+                    let storage_proof: Bytes = match &cached {
+                        Some(estimate) => estimate.storage_proof.clone(),
+                        None => {
+                            // calculate the storage slot for this message
+                            let storage_slot = msg.storage_slot();
+                            // request proof
+                            let proof_obj: MerkleProofRequest = self
+                                .request_l1(
+                                    "eth_getProof",
+                                    (l1_bridge_addr, [storage_slot], l1_block_header.hash),
+                                )
+                                .await
+                                .map_err(|e| CoordinatorError::l1("eth_getProof", e))?;
+                            // encode proof
+                            Bytes::from(marshal_proof_single(&proof_obj.storage_proof[0].proof))
+                        }
                     };
                     let calldata = self
                         .ro
@@ -459,14 +1244,84 @@ impl SharedState {
                             msg.fee.into_token(),
                             msg.deadline.into_token(),
                             msg.nonce.into_token(),
-                            Token::Bytes(msg.calldata),
-                            storage_proof.into_token(),
+                            Token::Bytes(msg.calldata.clone()),
+                            storage_proof.clone().into_token(),
                         ])
                         .expect("calldata");
 
+                    let gas = match cached {
+                        Some(estimate) => estimate.gas,
+                        None => {
+                            let tx = TransactionRequest::new()
+                                .from(self.ro.l2_deliverer.address())
+                                .to(self.ro.l2_message_deliverer_addr)
+                                .data(calldata.clone());
+                            let gas = jsonrpc_call::<_, U256>(
+                                RPC_REQUEST_TIMEOUT,
+                                &self.ro.http_client,
+                                &self.config.lock().await.l2_rpc_url,
+                                "eth_estimateGas",
+                                (&tx, format!("{:#066x}", temporary_block.hash.unwrap())),
+                            )
+                            .await;
+                            match gas {
+                                Ok(gas) => gas,
+                                Err(JsonRpcClientError::Rpc(err)) if is_execution_revert(&err) => {
+                                    log::debug!("{} simulate tx {}", LOG_TAG, err.message);
+                                    self.rw.lock().await.tombstone_message(
+                                        msg.id,
+                                        MessageDirection::L1ToL2,
+                                        timestamp_ms(),
+                                    );
+                                    drop_ids.push(msg.id);
+                                    continue;
+                                }
+                                Err(err) => {
+                                    // the message stays queued
+                                    log::warn!("{} estimate {:?}: {}", LOG_TAG, msg.id, err);
+                                    continue;
+                                }
+                            }
+                        }
+                    };
+                    self.rw.lock().await.delivery_estimates.insert(
+                        msg.id,
+                        DeliveryEstimate {
+                            l1_block: l1_block_header.hash,
+                            storage_proof,
+                            gas,
+                        },
+                    );
+
+                    // unprofitable messages stay queued until the gas price drops or they expire
+                    let min_fee = gas * gas_price * fee_margin / 100;
+                    if msg.fee < min_fee {
+                        log::debug!(
+                            "{} {:?} fee={} below min_fee={}",
+                            LOG_TAG,
+                            msg.id,
+                            msg.fee,
+                            min_fee
+                        );
+                        continue;
+                    }
+
+                    candidates.push((msg, calldata, gas));
+                }
+
+                // highest fee per gas first, in queue order otherwise
+                candidates.sort_by(|(a, _, a_gas), (b, _, b_gas)| {
+                    b.fee.full_mul(*a_gas).cmp(&a.fee.full_mul(*b_gas))
+                });
+                if max_messages > 0 {
+                    candidates.truncate(max_messages as usize);
+                }
+
+                for (msg, calldata, _) in candidates {
                     // simulate against temporary block
                     let tx = self
-                        .sign_l2_given_block_tag(
+                        .sign_l2_with(
+                            &*self.ro.l2_deliverer,
                             Some(self.ro.l2_message_deliverer_addr),
                             U256::zero(),
                             nonce,
@@ -476,8 +1331,12 @@ impl SharedState {
                         .await;
                     if let Err(err) = tx {
                         log::debug!("{} simulate tx {}", LOG_TAG, err);
-                        drop_idxs.push(i);
-                        i += 1;
+                        self.rw.lock().await.tombstone_message(
+                            msg.id,
+                            MessageDirection::L1ToL2,
+                            timestamp_ms(),
+                        );
+                        drop_ids.push(msg.id);
                         continue;
                     }
 
@@ -496,8 +1355,12 @@ impl SharedState {
                             }
                             _ => {
                                 // another error, probably a revert
-                                drop_idxs.push(i);
-                                i += 1;
+                                self.rw.lock().await.tombstone_message(
+                                    msg.id,
+                                    MessageDirection::L1ToL2,
+                                    timestamp_ms(),
+                                );
+                                drop_ids.push(msg.id);
                                 continue;
                             }
                         }
@@ -512,102 +1375,443 @@ impl SharedState {
                         temporary_block.gas_limit
                     );
                     nonce = nonce + 1;
-                    drop_idxs.push(i);
-                    i += 1;
+                    drop_ids.push(msg.id);
+                    delivered_ids.push(msg.id);
                 }
 
                 // final step
                 if temporary_block.transactions.len() > 1 {
                     self.set_chain_head(temporary_block.hash.unwrap())
                         .await
-                        .expect("set_chain_head relay");
+                        .map_err(|e| CoordinatorError::l2("set_chain_head relay", e))?;
                 }
+                drop(l2_nonce);
 
                 // everything went well
+                // by id, `sync` may have changed the queue in the meantime
                 let mut rw = self.rw.lock().await;
-                for (i, original_pos) in drop_idxs.into_iter().enumerate() {
-                    rw.l1_message_queue.remove(original_pos - i);
+                rw.l1_message_queue
+                    .retain(|msg| !drop_ids.contains(&msg.id));
+                let queued: HashSet<H256> = rw.l1_message_queue.iter().map(|msg| msg.id).collect();
+                rw.delivery_estimates.retain(|id, _| queued.contains(id));
+                drop(rw);
+                for id in delivered_ids {
+                    self.metrics
+                        .message_delivered("l1_to_l2", id, timestamp_ms());
                 }
             }
         }
 
         // check if we can mine a block
-        let resp: TxpoolStatus = self.request_l2("txpool_status", ()).await.unwrap();
+        let resp: TxpoolStatus = self
+            .request_l2("txpool_status", ())
+            .await
+            .map_err(|e| CoordinatorError::l2("txpool_status", e))?;
         let pending_txs = resp.pending.as_u64();
 
         if pending_txs != 0 {
-            self.mine_block(None).await.expect("mine_block regular");
+            self.mine_block(None)
+                .await
+                .map_err(|e| CoordinatorError::l2("mine_block regular", e))?;
         }
-    }
-
-    pub async fn submit_blocks(&self) {
-        // block submission
-        let safe_hash = self.rw.lock().await.chain_state.safe_block_hash;
-        let head_hash = self.rw.lock().await.chain_state.head_block_hash;
-        if safe_hash != head_hash {
-            // find all the blocks since `safe_hash`
-            let blocks = get_blocks_between(
-                &self.ro.http_client,
-                &self.config.lock().await.l2_rpc_url,
-                &safe_hash,
-                &head_hash,
-            )
-            .await;
-            let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
 
-            log::trace!("blocks to be submitted: {:?}", blocks.len());
-            for block in blocks.iter().rev() {
-                log::info!("submit_block: {}", format_block(block));
-                {
-                    let witness = self
-                        .request_witness(&block.number.unwrap())
-                        .await
-                        .expect("witness");
-                    let block_data = witness.input;
-                    let calldata = self
-                        .ro
-                        .bridge_abi
-                        .function("submitBlock")
-                        .unwrap()
-                        .encode_input(&[block_data.into_token()])
-                        .expect("calldata");
+        Ok(())
+    }
 
-                    self.transaction_to_l1(l1_bridge_addr, U256::zero(), calldata)
-                        .await
-                        .expect("receipt");
-                }
-            }
+    /// Returns the state of up to `limit` blocks after the finalized block.
+    pub async fn block_status(&self, limit: u64) -> Result<Vec<BlockStatus>, String> {
+        let chain_state = self.rw.lock().await.chain_state;
+        let mut numbers = Vec::new();
+        for hash in [
+            chain_state.finalized_block_hash,
+            chain_state.safe_block_hash,
+            chain_state.head_block_hash,
+        ] {
+            let header: BlockHeader = self.request_l2("eth_getHeaderByHash", [hash]).await?;
+            numbers.push(header.number.as_u64());
         }
+        let (finalized, safe, head) = (numbers[0], numbers[1], numbers[2]);
+
+        let rw = self.rw.lock().await;
+        let blocks = (finalized..=cmp::min(head, finalized + limit))
+            .map(|number| BlockStatus {
+                number: number.into(),
+                submitted: number <= safe,
+                finalized: number <= finalized,
+                proof_ready: rw
+                    .prover_requests
+                    .get(&number.into())
+                    .map(|proofs| proofs.is_some()),
+            })
+            .collect();
+
+        Ok(blocks)
     }
 
-    pub async fn finalize_blocks(&self) -> Result<(), String> {
-        // block finalization
-        let safe_hash = self.rw.lock().await.chain_state.safe_block_hash;
-        let final_hash = self.rw.lock().await.chain_state.finalized_block_hash;
-        if final_hash != safe_hash {
-            let blocks = get_blocks_between(
-                &self.ro.http_client,
-                &self.config.lock().await.l2_rpc_url,
-                &final_hash,
-                &safe_hash,
-            )
-            .await;
+    /// Removes the message `id` from the message queues.
+    /// Returns `false` if it was not queued.
+    pub async fn drop_queued_message(&self, id: H256) -> bool {
+        let mut rw = self.rw.lock().await;
+        let (l1_len, l2_len) = (rw.l1_message_queue.len(), rw.l2_message_queue.len());
+        rw.l1_message_queue.retain(|msg| msg.id != id);
+        rw.l2_message_queue.retain(|msg| msg.id != id);
+
+        let direction = if l1_len != rw.l1_message_queue.len() {
+            MessageDirection::L1ToL2
+        } else if l2_len != rw.l2_message_queue.len() {
+            MessageDirection::L2ToL1
+        } else {
+            return false;
+        };
+        rw.tombstone_message(id, direction, timestamp_ms());
 
-            log::trace!("blocks for finalization: {:?}", blocks.len());
-            for block in blocks.iter().rev() {
-                self.finalize_block(block).await?;
-            }
-        }
+        true
+    }
 
-        Ok(())
+    /// Returns the lifecycle record of the message `id`, `None` if unknown.
+    pub async fn message_status(&self, id: H256) -> Option<MessageRecord> {
+        self.rw.lock().await.messages.get(&id).cloned()
     }
 
-    pub async fn finalize_block(&self, block: &Block<H256>) -> Result<(), String> {
+    async fn set_message_state(&self, id: H256, direction: MessageDirection, state: MessageState) {
+        let now = timestamp_ms();
+        self.rw
+            .lock()
+            .await
+            .message_record(id, direction, now)
+            .advance(state, now);
+    }
+
+    /// Updates the chain, sync and queue gauges and returns all metrics
+    /// in the Prometheus text format.
+    pub async fn render_metrics(&self) -> Result<String, String> {
+        let (chain_state, l1_last_sync_block, l2_last_sync_block) = {
+            let rw = self.rw.lock().await;
+            let (l1_queue, l2_queue) = (&rw.l1_message_queue, &rw.l2_message_queue);
+            self.metrics.set(
+                &MESSAGE_QUEUE,
+                &[("direction", "l1_to_l2")],
+                l1_queue.len() as f64,
+            );
+            self.metrics.set(
+                &MESSAGE_QUEUE,
+                &[("direction", "l2_to_l1")],
+                l2_queue.len() as f64,
+            );
+            // dropped or reorged messages
+            self.metrics.retain_messages(|id| {
+                l1_queue.iter().any(|msg| msg.id == *id) || l2_queue.iter().any(|msg| msg.id == *id)
+            });
+
+            (rw.chain_state, rw.l1_last_sync_block, rw.l2_last_sync_block)
+        };
+
+        let mut numbers = Vec::new();
+        for hash in [
+            chain_state.head_block_hash,
+            chain_state.safe_block_hash,
+            chain_state.finalized_block_hash,
+        ] {
+            let header: BlockHeader = self.request_l2("eth_getHeaderByHash", [hash]).await?;
+            numbers.push(header.number.as_u64());
+        }
+        let (head, safe, finalized) = (numbers[0], numbers[1], numbers[2]);
+        self.metrics
+            .set(&L2_BLOCK, &[("kind", "head")], head as f64);
+        self.metrics
+            .set(&L2_BLOCK, &[("kind", "safe")], safe as f64);
+        self.metrics
+            .set(&L2_BLOCK, &[("kind", "finalized")], finalized as f64);
+        self.metrics.set(
+            &L2_BLOCK_LAG,
+            &[("kind", "unsubmitted")],
+            head.saturating_sub(safe) as f64,
+        );
+        self.metrics.set(
+            &L2_BLOCK_LAG,
+            &[("kind", "unfinalized")],
+            safe.saturating_sub(finalized) as f64,
+        );
+
+        let l1_latest: U64 = self.request_l1("eth_blockNumber", ()).await?;
+        let l2_latest: U64 = self.request_l2("eth_blockNumber", ()).await?;
+        self.metrics.set(
+            &SYNC_LAG,
+            &[("chain", "l1")],
+            l1_latest.saturating_sub(l1_last_sync_block).as_u64() as f64,
+        );
+        self.metrics.set(
+            &SYNC_LAG,
+            &[("chain", "l2")],
+            l2_latest.saturating_sub(l2_last_sync_block).as_u64() as f64,
+        );
+
+        Ok(self.metrics.render())
+    }
+
+    /// Requests proofs for up to `Config::proof_lookahead` blocks after the finalized block
+    /// and keeps finished proofs in `prover_requests` for `finalize_block`.
+    pub async fn request_proofs_ahead(&self) -> Result<(), CoordinatorError> {
+        let (lookahead, dummy_prover) = {
+            let config = self.config.lock().await;
+            (config.proof_lookahead, config.dummy_prover)
+        };
+        // the dummy prover needs the parent block to be submitted
+        if lookahead == 0 || dummy_prover {
+            return Ok(());
+        }
+
+        let chain_state = self.rw.lock().await.chain_state;
+        let finalized: BlockHeader = self
+            .request_l2("eth_getHeaderByHash", [chain_state.finalized_block_hash])
+            .await
+            .map_err(|e| CoordinatorError::l2("finalized block", e))?;
+        let head: BlockHeader = self
+            .request_l2("eth_getHeaderByHash", [chain_state.head_block_hash])
+            .await
+            .map_err(|e| CoordinatorError::l2("head block", e))?;
+        let from = finalized.number.as_u64() + 1;
+        let to = cmp::min(head.number.as_u64(), finalized.number.as_u64() + lookahead);
+
+        // the proofs are requested by hash, the numbers can be reorged.
+        // walks back from block `to`, only the blocks ahead are fetched
+        let mut hashes: HashMap<U64, H256> = HashMap::new();
+        if from <= to {
+            let mut header: BlockHeader = self
+                .request_l2("eth_getHeaderByNumber", [U64::from(to)])
+                .await
+                .map_err(|e| CoordinatorError::l2("blocks to prove", e))?;
+            loop {
+                hashes.insert(header.number, header.hash);
+                if header.number.as_u64() <= from {
+                    break;
+                }
+                header = self
+                    .request_l2("eth_getHeaderByHash", [header.parent_hash])
+                    .await
+                    .map_err(|e| CoordinatorError::l2("blocks to prove", e))?;
+            }
+            if header.parent_hash != finalized.hash {
+                log::warn!(
+                    "L2 block {} doesn't link to the finalized block, reorg?",
+                    from
+                );
+                return Ok(());
+            }
+        }
+
+        let mut pending: Vec<(U64, H256)> = {
+            let mut rw = self.rw.lock().await;
+            let finalized: Vec<U64> = rw
+                .prover_requests
+                .keys()
+                .filter(|block_num| block_num.as_u64() < from)
+                .cloned()
+                .collect();
+            for block_num in finalized {
+                rw.prover_requests.remove(&block_num);
+                rw.provers.release(block_num.as_u64());
+            }
+            for block_num in from..=to {
+                rw.prover_requests.entry(block_num.into()).or_insert(None);
+            }
+
+            rw.prover_requests
+                .iter()
+                .filter(|(_, proofs)| proofs.is_none())
+                .filter_map(|(block_num, _)| Some((*block_num, *hashes.get(block_num)?)))
+                .collect()
+        };
+        pending.sort_unstable();
+
+        // the provers work on the blocks in parallel
+        let requests: Vec<_> = pending
+            .iter()
+            .map(|(block_num, block_hash)| {
+                let ctx = self.clone();
+                let (block_num, block_hash) = (*block_num, *block_hash);
+                tokio::spawn(async move { ctx.request_proof(&block_num, &block_hash).await })
+            })
+            .collect();
+
+        let mut done = 0;
+        for ((block_num, _), request) in pending.iter().zip(requests) {
+            let result = request
+                .await
+                .map_err(|e| CoordinatorError::internal("request_proof", e))?;
+            match result {
+                Ok(None) => {}
+                Ok(Some(proofs)) => {
+                    log::info!("proof ready for block {}", block_num);
+                    self.rw
+                        .lock()
+                        .await
+                        .prover_requests
+                        .insert(*block_num, Some(proofs));
+                    done += 1;
+                }
+                Err(err) => log::warn!("proof request for block {}: {}", block_num, err),
+            }
+        }
+        self.rw.lock().await.pending_proofs = (pending.len() - done) as u32;
+
+        Ok(())
+    }
+
+    pub async fn submit_blocks(&self) -> Result<(), CoordinatorError> {
+        // block submission
+        let (safe_hash, head_hash) = {
+            let rw = self.rw.lock().await;
+            if rw.awaits_sync(rw.l1_submit_block) {
+                log::debug!(
+                    "submit_blocks: waiting for sync of {:?}",
+                    rw.l1_submit_block
+                );
+                return Ok(());
+            }
+            (
+                rw.chain_state.safe_block_hash,
+                rw.chain_state.head_block_hash,
+            )
+        };
+        if safe_hash != head_hash {
+            // find all the blocks since `safe_hash`
+            let blocks = get_blocks_between(
+                &self.ro.http_client,
+                &self.config.lock().await.l2_rpc_url,
+                &safe_hash,
+                &head_hash,
+            )
+            .await
+            .map_err(|e| CoordinatorError::l2("blocks to submit", e))?;
+            let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
+
+            let (max_blocks, max_calldata) = {
+                let config = self.config.lock().await;
+                (
+                    config.submit_batch_max_blocks as usize,
+                    config.submit_batch_max_calldata as usize,
+                )
+            };
+
+            log::trace!("blocks to be submitted: {:?}", blocks.len());
+            let mut batch: Vec<Bytes> = Vec::new();
+            // the `submitBlocks` selector plus the offset and length of the array
+            let mut batch_size = 4 + 64;
+            for block in blocks.iter().rev() {
+                log::info!("submit_block: {}", format_block(block));
+                let witness = self
+                    .request_witness(&block.number.unwrap())
+                    .await
+                    .map_err(|e| CoordinatorError::l2("witness", e))?;
+                let block_data = witness.input;
+                // offset, length and the padded witness
+                let size = 64 + (block_data.len() + 31) / 32 * 32;
+
+                if !batch.is_empty()
+                    && (batch.len() >= max_blocks || batch_size + size > max_calldata)
+                {
+                    self.submit_batch(l1_bridge_addr, &batch).await?;
+                    batch.clear();
+                    batch_size = 4 + 64;
+                }
+                batch_size += size;
+                batch.push(block_data);
+            }
+
+            if !batch.is_empty() {
+                self.submit_batch(l1_bridge_addr, &batch).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Submits the block witnesses of `batch` in one L1 transaction,
+    /// via `submitBlocks` if there is more than one.
+    async fn submit_batch(
+        &self,
+        l1_bridge_addr: Option<Address>,
+        batch: &[Bytes],
+    ) -> Result<(), CoordinatorError> {
+        let (function, token) = if let [block_data] = batch {
+            ("submitBlock", block_data.clone().into_token())
+        } else {
+            log::info!("submit_blocks: {} blocks via submitBlocks", batch.len());
+            (
+                "submitBlocks",
+                Token::Array(batch.iter().map(|data| data.clone().into_token()).collect()),
+            )
+        };
+        let calldata = self
+            .ro
+            .bridge_abi
+            .function(function)
+            .unwrap()
+            .encode_input(&[token])
+            .expect("calldata");
+        let receipt = self
+            .transaction_to_l1_as(L1Role::Submitter, l1_bridge_addr, U256::zero(), calldata)
+            .await
+            .map_err(|e| CoordinatorError::transaction(function, e))?;
+        self.rw.lock().await.l1_submit_block = receipt.block_number;
+
+        Ok(())
+    }
+
+    pub async fn finalize_blocks(&self) -> Result<(), CoordinatorError> {
+        // block finalization
+        let (safe_hash, final_hash) = {
+            let rw = self.rw.lock().await;
+            if rw.awaits_sync(rw.l1_finalize_block) {
+                log::debug!(
+                    "finalize_blocks: waiting for sync of {:?}",
+                    rw.l1_finalize_block
+                );
+                return Ok(());
+            }
+            (
+                rw.chain_state.safe_block_hash,
+                rw.chain_state.finalized_block_hash,
+            )
+        };
+        if final_hash != safe_hash {
+            let blocks = get_blocks_between(
+                &self.ro.http_client,
+                &self.config.lock().await.l2_rpc_url,
+                &final_hash,
+                &safe_hash,
+            )
+            .await
+            .map_err(|e| CoordinatorError::l2("blocks to finalize", e))?;
+
+            log::trace!("blocks for finalization: {:?}", blocks.len());
+            for block in blocks.iter().rev() {
+                self.finalize_block(block).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn finalize_block(&self, block: &Block<H256>) -> Result<(), CoordinatorError> {
         const LOG_TAG: &str = "L1:finalize_block:";
         log::trace!("{} {}", LOG_TAG, format_block(block));
 
         let block_num = block.number.unwrap();
-        let proofs: Result<Option<Proofs>, String> = self.request_proof(&block_num).await;
+        // collected by `request_proofs_ahead` or requested now
+        let ready = self
+            .rw
+            .lock()
+            .await
+            .prover_requests
+            .get(&block_num)
+            .cloned()
+            .flatten();
+        let proofs: Result<Option<Proofs>, CoordinatorError> = match ready {
+            Some(proofs) => Ok(Some(proofs)),
+            None => self.request_proof(&block_num, &block.hash.unwrap()).await,
+        };
 
         if let Err(err) = proofs {
             log::error!("{}:{} {:?}", LOG_TAG, block_num, err);
@@ -620,6 +1824,19 @@ impl SharedState {
             Some(proof) => {
                 log::info!("{} found proof: {:#?} for {}", LOG_TAG, proof, block_num);
 
+                // the dummy prover doesn't record the block hash
+                if !proof.block_hash.is_zero() && Some(proof.block_hash) != block.hash {
+                    // the block was reorged, request it again
+                    self.rw.lock().await.prover_requests.remove(&block_num);
+                    return Err(CoordinatorError::prover(
+                        "proof",
+                        format!(
+                            "proof for {:?} doesn't match block {}",
+                            proof.block_hash, block_num
+                        ),
+                    ));
+                }
+
                 // choose the aggregation proof if not empty
                 let (is_aggregated, proof_result) = {
                     if proof.aggregation.proof.len() != 0 {
@@ -666,9 +1883,13 @@ impl SharedState {
                     .expect("calldata");
 
                 let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
-                self.transaction_to_l1(l1_bridge_addr, U256::zero(), calldata)
+                let receipt = self
+                    .transaction_to_l1_as(L1Role::Finalizer, l1_bridge_addr, U256::zero(), calldata)
                     .await
-                    .expect("receipt");
+                    .map_err(|e| CoordinatorError::transaction("finalizeBlock", e))?;
+                let mut rw = self.rw.lock().await;
+                rw.prover_requests.remove(&block_num);
+                rw.l1_finalize_block = receipt.block_number;
             }
         }
 
@@ -681,17 +1902,52 @@ impl SharedState {
         value: U256,
         calldata: Vec<u8>,
     ) -> Result<TransactionReceipt, String> {
-        send_transaction_to_l1(
-            &self.ro.http_client,
-            &self.config.lock().await.l1_rpc_url,
-            &self.ro.l1_wallet,
-            to,
-            value,
-            calldata,
-        )
-        .await
+        self.l1_sender.send(to, value, calldata).await
     }
 
+    /// Sends a L1 transaction with the key of `role`.
+    pub async fn transaction_to_l1_as(
+        &self,
+        role: L1Role,
+        to: Option<Address>,
+        value: U256,
+        calldata: Vec<u8>,
+    ) -> Result<TransactionReceipt, String> {
+        let receipt = self.l1_role_senders[&role]
+            .send(to, value, calldata)
+            .await?;
+
+        let role = serde_json::to_value(role).unwrap();
+        let labels = [("role", role.as_str().unwrap_or_default())];
+        let gas_used = receipt.gas_used.unwrap_or_default();
+        self.metrics
+            .inc(&L1_GAS_USED, &labels, wei_to_f64(gas_used));
+        if let Some(price) = receipt.effective_gas_price {
+            self.metrics
+                .inc(&L1_FEES, &labels, wei_to_f64(gas_used * price));
+        }
+
+        Ok(receipt)
+    }
+
+    /// Returns the pending L1 transactions of all signers.
+    pub async fn l1_pending_transactions(&self) -> Vec<L1PendingTx> {
+        let mut senders = vec![&self.l1_sender];
+        for sender in self.l1_role_senders.values() {
+            if !senders.iter().any(|s| s.address() == sender.address()) {
+                senders.push(sender);
+            }
+        }
+
+        let mut pending = Vec::new();
+        for sender in senders {
+            pending.extend(sender.pending().await);
+        }
+
+        pending
+    }
+
+    /// Sends a L2 transaction with `ro.l2_wallet` via `l2_sender`.
     pub async fn transaction_to_l2(
         &self,
         to: Option<Address>,
@@ -699,16 +1955,7 @@ impl SharedState {
         calldata: Vec<u8>,
         gas_limit: Option<U256>,
     ) -> Result<H256, String> {
-        send_transaction_to_l2(
-            &self.ro.http_client,
-            &self.config.lock().await.l2_rpc_url,
-            &self.ro.l2_wallet,
-            to,
-            value,
-            calldata,
-            gas_limit,
-        )
-        .await
+        self.l2_sender.send(to, value, calldata, gas_limit).await
     }
 
     /// Estimates gas against "latest" block and returns a raw signed transaction.
@@ -735,28 +1982,41 @@ impl SharedState {
         calldata: Vec<u8>,
         option_block: Option<String>,
     ) -> Result<Bytes, String> {
-        let wallet = &self.ro.l2_wallet;
-        let wallet_addr: Address = wallet.address();
-        let gas_price: U256 = self.request_l2("eth_gasPrice", ()).await?;
-        let mut tx = TransactionRequest::new()
-            .chain_id(wallet.chain_id())
-            .from(wallet_addr)
-            .nonce(nonce)
-            .value(value)
-            .gas_price(gas_price * 2u64)
-            .data(calldata);
-        if let Some(to) = to {
-            tx = tx.to(to);
-        };
-        let block_tag = option_block.unwrap_or_else(|| "latest".into());
-        let estimate: U256 = self.request_l2("eth_estimateGas", (&tx, block_tag)).await?;
-        let tx = tx.gas(estimate).into();
-        let sig = wallet
-            .sign_transaction(&tx)
-            .await
-            .map_err(|e| e.to_string())?;
+        self.sign_l2_with(
+            self.ro.l2_wallet.as_ref(),
+            to,
+            value,
+            nonce,
+            calldata,
+            option_block,
+        )
+        .await
+    }
 
-        Ok(tx.rlp_signed(&sig))
+    /// Like `sign_l2_given_block_tag`, but signs with `signer`.
+    async fn sign_l2_with(
+        &self,
+        signer: &dyn TxSigner,
+        to: Option<Address>,
+        value: U256,
+        nonce: U256,
+        calldata: Vec<u8>,
+        option_block: Option<String>,
+    ) -> Result<Bytes, String> {
+        let block_tag = option_block.unwrap_or_else(|| "latest".into());
+        let l2_rpc_url = self.config.lock().await.l2_rpc_url.clone();
+        sign_transaction_l2(
+            &self.ro.http_client,
+            &l2_rpc_url,
+            signer,
+            to,
+            value,
+            nonce,
+            calldata,
+            None,
+            &block_tag,
+        )
+        .await
     }
 
     pub async fn request_l1<T: Serialize + Send + Sync, R: DeserializeOwned>(
@@ -842,6 +2102,35 @@ impl SharedState {
         }
 
         self.rw.lock().await.chain_state.head_block_hash = block_hash;
+
+        if self.new_heads.receiver_count() > 0 {
+            // the logs are fetched once for all subscriptions
+            let head = async {
+                let mut header: serde_json::Value = self
+                    .request_l2("eth_getBlockByHash", (block_hash, false))
+                    .await?;
+                let logs: Vec<serde_json::Value> = self
+                    .request_l2(
+                        "eth_getLogs",
+                        [serde_json::json!({ "blockHash": block_hash })],
+                    )
+                    .await?;
+                if let Some(fields) = header.as_object_mut() {
+                    for key in ["transactions", "uncles", "size", "totalDifficulty"] {
+                        fields.remove(key);
+                    }
+                }
+                Ok::<_, String>(NewHead { header, logs })
+            };
+            match head.await {
+                // can't fail as long as there are receivers
+                Ok(head) => {
+                    let _ = self.new_heads.send(head);
+                }
+                Err(err) => log::warn!("new_heads: {}", err),
+            }
+        }
+
         Ok(())
     }
 
@@ -860,51 +2149,82 @@ impl SharedState {
     }
 
     /// keeps track of l2 bridge message events
-    async fn sync_l2(&self) {
-        // TODO: DRY syncing mechanics w/ l1
+    async fn sync_l2(&self) -> Result<(), CoordinatorError> {
         let latest_block: U64 = self
             .request_l2("eth_blockNumber", ())
             .await
-            .expect("eth_blockNumber");
-        let mut last_to_block: U64 = U64::zero();
-        let mut from: U64 = self.rw.lock().await.l2_last_sync_block + 1;
-        let mut filter = Filter::new()
-            .address(ValueOrArray::Value(self.ro.l2_message_deliverer_addr))
-            .topic0(ValueOrArray::Value(self.ro.message_delivered_topic));
-        let mut executed_msgs = vec![];
+            .map_err(|e| CoordinatorError::l2("eth_blockNumber", e))?;
+        let from: U64 = self.rw.lock().await.l2_last_sync_block + 1;
+        let mut events = self.rw.lock().await.l2_events;
+        let filter = Filter::new()
+            .address(ValueOrArray::Array(vec![
+                self.ro.l2_message_deliverer_addr,
+                self.ro.l2_message_dispatcher_addr,
+            ]))
+            .topic0(ValueOrArray::Array(vec![
+                self.ro.message_delivered_topic,
+                self.ro.message_dispatched_topic,
+            ]));
 
-        while from <= latest_block {
-            // TODO: increase or decrease request range depending on fetch success
-            let to = cmp::min(from + 1u64, latest_block);
-            log::trace!("fetching logs from={} to={}", from, to);
-            filter = filter.from_block(from).to_block(to);
+        let res = events
+            .sync(
+                &filter,
+                from,
+                latest_block,
+                |filter| async move {
+                    self.request_l2::<_, Vec<Log>>("eth_getLogs", [filter])
+                        .await
+                        .map_err(|e| CoordinatorError::l2("eth_getLogs", e))
+                },
+                |_, to, logs| self.apply_l2_logs(to, logs),
+            )
+            .await;
+        self.rw.lock().await.l2_events = events;
+        res
+    }
 
-            let logs: Vec<Log> = self
-                .request_l2("eth_getLogs", [&filter])
-                .await
-                .expect("eth_getLogs");
-            // TODO: ugly hack to fix geth inconstency issues
-            if !logs.is_empty() {
-                last_to_block = to;
+    /// Applies the L2 bridge message events up to block `to`.
+    async fn apply_l2_logs(&self, to: U64, logs: Vec<Log>) -> Result<bool, CoordinatorError> {
+        let mut rw = self.rw.lock().await;
+        let now = timestamp_ms();
+        for log in logs {
+            if log.address == self.ro.l2_message_dispatcher_addr
+                && log.topics[0] == self.ro.message_dispatched_topic
+            {
+                let (tx_hash, block_hash) = (log.transaction_hash, log.block_hash);
+                let id = self._parse_message_beacon(log).id;
+                let record = rw.message_record(id, MessageDirection::L2ToL1, now);
+                record.dispatch_tx = tx_hash;
+                record.l2_block = block_hash;
+                record.advance(MessageState::Included, now);
+                continue;
             }
 
-            for log in logs {
+            if log.address == self.ro.l2_message_deliverer_addr
+                && log.topics[0] == self.ro.message_delivered_topic
+            {
                 let message_id = H256::from_slice(log.data.as_ref());
-                executed_msgs.push(message_id);
+                let record = rw.message_record(message_id, MessageDirection::L1ToL2, now);
+                // also for dropped messages, finalizing the block delivers them
+                if record.deliver_tx.is_none() {
+                    record.deliver_tx = log.transaction_hash;
+                    record.l2_block = log.block_hash;
+                }
+                record.advance(MessageState::Included, now);
+                rw.l2_delivered_messages.push(message_id);
             }
-
-            from = to + 1u64;
         }
+        rw.l2_last_sync_block = to;
 
-        if last_to_block != U64::zero() {
-            let mut rw = self.rw.lock().await;
-            rw.l2_last_sync_block = last_to_block;
-            rw.l2_delivered_messages.extend_from_slice(&executed_msgs);
-        }
+        Ok(true)
     }
 
     /// keeps track of L2 > L1 message events
-    async fn record_l2_messages(&self, block_hash: H256) {
+    async fn record_l2_messages(
+        &self,
+        block_hash: H256,
+        finalize_tx: Option<H256>,
+    ) -> Result<(), CoordinatorError> {
         let filter = Filter::new()
             .address(ValueOrArray::Value(self.ro.l2_message_dispatcher_addr))
             .topic0(ValueOrArray::Value(self.ro.message_dispatched_topic))
@@ -912,122 +2232,411 @@ impl SharedState {
         let logs: Vec<Log> = self
             .request_l2("eth_getLogs", [&filter])
             .await
-            .expect("eth_getLogs");
+            .map_err(|e| CoordinatorError::l2("eth_getLogs", e))?;
 
         log::trace!("L2: {} relay events for {}", logs.len(), block_hash);
         let mut pending = vec![];
+        let mut rw = self.rw.lock().await;
+        let now = timestamp_ms();
         for log in logs {
+            let tx_hash = log.transaction_hash;
             let beacon = self._parse_message_beacon(log);
             log::info!("L1Relay: {:?}", beacon.id);
             log::debug!("{:?}", beacon);
+            self.metrics.message_queued(beacon.id, timestamp_ms());
+            let record = rw.l1_message_record(beacon.id, MessageDirection::L2ToL1, now);
+            record.dispatch_tx = tx_hash;
+            record.l2_block = Some(block_hash);
+            if record.advance(MessageState::Finalized, now) {
+                record.finalize_tx = finalize_tx;
+            }
+            if rw.dropped_messages.contains(&beacon.id) {
+                log::info!("L1Relay: {:?} was dropped", beacon.id);
+                continue;
+            }
             pending.push(beacon);
         }
 
-        let mut rw = self.rw.lock().await;
+        rw.l1_changes
+            .l2_queued
+            .extend(pending.iter().map(|msg| msg.id));
         rw.l2_message_queue.extend(pending);
+
+        Ok(())
     }
 
-    pub async fn relay_to_l1(&self) {
-        let mut rw = self.rw.lock().await;
-        let len = rw.l2_message_queue.len();
+    /// Relays up to `Config::relay_max_messages` messages of `l2_message_queue` to L1,
+    /// batched into multicalls.
+    /// Messages stay in the queue until they are relayed, delivered or expired.
+    /// If a batch fails, the remaining messages are relayed one by one so that
+    /// a failed message doesn't hold up the others, the first error is returned.
+    pub async fn relay_to_l1(&self) -> Result<(), CoordinatorError> {
+        let max_messages = self.config.lock().await.relay_max_messages as usize;
+        let todo: Vec<MessageBeacon> = self
+            .rw
+            .lock()
+            .await
+            .l2_message_queue
+            .iter()
+            .take(max_messages)
+            .cloned()
+            .collect();
 
-        if len == 0 {
-            return;
+        const LOG_TAG: &str = "L1:deliverMessageWithProof:";
+        let mut done = Vec::new();
+        let mut result = Ok(());
+        if let Err(err) = self.relay_batch_to_l1(&todo, &mut done).await {
+            log::warn!("{} batch failed, relaying one by one: {}", LOG_TAG, err);
+
+            let remaining: Vec<MessageBeacon> = todo
+                .into_iter()
+                .filter(|msg| !done.contains(&msg.id))
+                .collect();
+            for msg in remaining {
+                let id = msg.id;
+                match self.relay_message_to_l1(msg).await {
+                    Ok(_) => done.push(id),
+                    Err(err) => {
+                        log::error!("{} {:?} {}", LOG_TAG, id, err);
+                        if result.is_ok() {
+                            result = Err(err);
+                        }
+                    }
+                }
+            }
         }
 
-        // TODO: we are going to lose messages if we panic below
-        let todo: Vec<MessageBeacon> = rw.l2_message_queue.drain(0..cmp::min(32, len)).collect();
-        drop(rw);
+        self.rw
+            .lock()
+            .await
+            .l2_message_queue
+            .retain(|msg| !done.contains(&msg.id));
+
+        result
+    }
 
+    /// Relays `msgs` in multicalls of at most `Config::relay_batch_max_gas`,
+    /// sharing one `importForeignBridgeState`.
+    /// Adds the ids of the relayed, delivered or expired messages to `done`
+    /// and stops at the first error, e.g. if a batch reverts.
+    async fn relay_batch_to_l1(
+        &self,
+        msgs: &[MessageBeacon],
+        done: &mut Vec<H256>,
+    ) -> Result<(), CoordinatorError> {
         const LOG_TAG: &str = "L1:deliverMessageWithProof:";
-        let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
-        for msg in todo {
-            {
-                // check deadline
-                let ts_with_padding = U256::from(timestamp() + 900);
-                if msg.deadline < ts_with_padding {
-                    log::info!("{} {:?} deadline exceeded", LOG_TAG, msg.id);
-                    log::debug!("{:?}", msg);
-                    continue;
-                }
+        let mut pending = Vec::new();
+        for msg in msgs {
+            if self.relay_skipped(msg).await {
+                done.push(msg.id);
+            } else {
+                pending.push(msg.clone());
             }
+        }
+        if pending.is_empty() {
+            return Ok(());
+        }
 
-            let found = self
-                .rw
-                .lock()
-                .await
-                .l1_delivered_messages
-                .iter()
-                .any(|&e| e == msg.id);
+        let (mut import, delivers) = self
+            .l2_proof_calls("deliverMessageWithProof", &pending)
+            .await?;
+        let (l1_bridge_addr, max_gas) = {
+            let config = self.config.lock().await;
+            (config.l1_bridge, U256::from(config.relay_batch_max_gas))
+        };
+        let relayer = self.l1_role_senders[&L1Role::Relayer].address();
+
+        let mut start = 0;
+        while start < pending.len() {
+            // halve the batch until it fits into the gas budget
+            let mut len = pending.len() - start;
+            let calldata = loop {
+                let calls: Vec<Vec<u8>> = import
+                    .iter()
+                    .chain(delivers[start..start + len].iter())
+                    .cloned()
+                    .collect();
+                let calldata = encode_multicall(&calls);
+                let tx = TransactionRequest::new()
+                    .from(relayer)
+                    .to(l1_bridge_addr)
+                    .data(calldata.clone());
+                let gas: U256 = self
+                    .request_l1("eth_estimateGas", [&tx])
+                    .await
+                    .map_err(|e| CoordinatorError::l1("eth_estimateGas", e))?;
 
-            log::trace!("{} skip={} {:?}", LOG_TAG, found, msg.id);
-            log::debug!("{:?}", msg);
-            if found {
-                continue;
-            }
+                if gas <= max_gas || len == 1 {
+                    break calldata;
+                }
+                len /= 2;
+            };
 
-            // latest finalized block hash
-            let block_hash = self.rw.lock().await.chain_state.finalized_block_hash;
-            // calculate the storage slot for this message
-            let storage_slot = msg.storage_slot();
-            // request proof
-            let proof_obj: MerkleProofRequest = self
-                .request_l2(
-                    "eth_getProof",
-                    (
-                        self.ro.l2_message_dispatcher_addr,
-                        [storage_slot],
-                        block_hash,
-                    ),
+            let receipt = self
+                .transaction_to_l1_as(
+                    L1Role::Relayer,
+                    Some(l1_bridge_addr),
+                    U256::zero(),
+                    calldata,
                 )
                 .await
-                .expect("eth_getProof");
-            let l2_block_header: BlockHeader = self
-                .request_l2("eth_getHeaderByHash", [block_hash])
+                .map_err(|e| CoordinatorError::transaction("deliverMessageWithProof", e))?;
+            log::info!(
+                "{} relayed {} messages via {:?}",
+                LOG_TAG,
+                len,
+                receipt.transaction_hash
+            );
+            for msg in &pending[start..start + len] {
+                self.message_relayed(msg.id, receipt.transaction_hash).await;
+                done.push(msg.id);
+            }
+
+            // imported by the first batch
+            import = None;
+            start += len;
+        }
+
+        Ok(())
+    }
+
+    async fn relay_message_to_l1(&self, msg: MessageBeacon) -> Result<(), CoordinatorError> {
+        if self.relay_skipped(&msg).await {
+            return Ok(());
+        }
+
+        let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
+        let (import, delivers) = self
+            .l2_proof_calls("deliverMessageWithProof", std::slice::from_ref(&msg))
+            .await?;
+        let calls: Vec<Vec<u8>> = import.into_iter().chain(delivers).collect();
+        let receipt = self
+            .transaction_to_l1_as(
+                L1Role::Relayer,
+                l1_bridge_addr,
+                U256::zero(),
+                encode_multicall(&calls),
+            )
+            .await
+            .map_err(|e| CoordinatorError::transaction("deliverMessageWithProof", e))?;
+        self.message_relayed(msg.id, receipt.transaction_hash).await;
+
+        Ok(())
+    }
+
+    /// Returns `true` if `msg` is expired or already delivered on L1.
+    async fn relay_skipped(&self, msg: &MessageBeacon) -> bool {
+        const LOG_TAG: &str = "L1:deliverMessageWithProof:";
+        {
+            // check deadline
+            let ts_with_padding = U256::from(timestamp() + 900);
+            if msg.deadline < ts_with_padding {
+                log::info!("{} {:?} deadline exceeded", LOG_TAG, msg.id);
+                log::debug!("{:?}", msg);
+                self.set_message_state(msg.id, MessageDirection::L2ToL1, MessageState::Expired)
+                    .await;
+                self.queue_expired_message(MessageDirection::L2ToL1, msg)
+                    .await;
+                return true;
+            }
+        }
+
+        let found = self
+            .rw
+            .lock()
+            .await
+            .l1_delivered_messages
+            .iter()
+            .any(|&e| e == msg.id);
+
+        log::trace!("{} skip={} {:?}", LOG_TAG, found, msg.id);
+        log::debug!("{:?}", msg);
+
+        found
+    }
+
+    /// Returns the `importForeignBridgeState` call if L1 doesn't know the L2 bridge state yet
+    /// and the L1 bridge call `function` (`deliverMessageWithProof` or `dropMessage`)
+    /// of each message, with proofs against the finalized L2 block.
+    async fn l2_proof_calls(
+        &self,
+        function: &str,
+        msgs: &[MessageBeacon],
+    ) -> Result<(Option<Vec<u8>>, Vec<Vec<u8>>), CoordinatorError> {
+        // latest finalized block hash
+        let block_hash = self.rw.lock().await.chain_state.finalized_block_hash;
+        // calculate the storage slot for each message
+        let storage_slots: Vec<H256> = msgs.iter().map(|msg| msg.storage_slot()).collect();
+        // request proofs
+        let proof_obj: MerkleProofRequest = self
+            .request_l2(
+                "eth_getProof",
+                (
+                    self.ro.l2_message_dispatcher_addr,
+                    storage_slots,
+                    block_hash,
+                ),
+            )
+            .await
+            .map_err(|e| CoordinatorError::l2("eth_getProof", e))?;
+        let l2_block_header: BlockHeader = self
+            .request_l2("eth_getHeaderByHash", [block_hash])
+            .await
+            .map_err(|e| CoordinatorError::l2("eth_getHeaderByHash", e))?;
+        let storage_root = keccak256(proof_obj.storage_proof[0].proof[0].as_ref());
+        let origin_timestamp = self
+            .call_fn_l1("getTimestampForStorageRoot", &[storage_root.into_token()])
+            .await
+            .map_err(|e| CoordinatorError::l1("getTimestampForStorageRoot", e))?;
+
+        // block data
+        let mut import = None;
+        if origin_timestamp.is_zero() {
+            let block_data: Bytes = self
+                .request_l2("debug_getHeaderRlp", [l2_block_header.number.as_u64()])
                 .await
-                .expect("eth_getHeaderByHash");
-            let mut tmp = vec![0u8; 32];
-            let mut bytes = self
+                .map_err(|e| CoordinatorError::l2("debug_getHeaderRlp", e))?;
+            let account_proof: Bytes = Bytes::from(marshal_proof_single(&proof_obj.account_proof));
+            let calldata = self
                 .ro
                 .bridge_abi
-                .function("multicall")
+                .function("importForeignBridgeState")
                 .unwrap()
-                .encode_input(&[])
-                .unwrap();
-            let storage_root = keccak256(proof_obj.storage_proof[0].proof[0].as_ref());
-            let origin_timestamp = self
-                .call_fn_l1("getTimestampForStorageRoot", &[storage_root.into_token()])
-                .await
-                .expect("getTimestampForStorageRoot");
+                .encode_input(&[block_data.into_token(), account_proof.into_token()])
+                .expect("importForeignBridgeState");
+            import = Some(calldata);
+        }
 
-            // block data
-            if origin_timestamp.is_zero() {
-                let block_data: Bytes = self
-                    .request_l2("debug_getHeaderRlp", [l2_block_header.number.as_u64()])
-                    .await
-                    .expect("block_data");
-                let account_proof: Bytes =
-                    Bytes::from(marshal_proof_single(&proof_obj.account_proof));
-                let calldata = self
-                    .ro
+        // message calls
+        let calls = msgs
+            .iter()
+            .zip(proof_obj.storage_proof.iter())
+            .map(|(msg, storage_proof)| {
+                let proof: Bytes = Bytes::from(marshal_proof_single(&storage_proof.proof));
+                self.ro
                     .bridge_abi
-                    .function("importForeignBridgeState")
+                    .function(function)
                     .unwrap()
-                    .encode_input(&[block_data.into_token(), account_proof.into_token()])
-                    .expect("importForeignBridgeState");
-                U256::from(calldata.len()).to_big_endian(&mut tmp);
-                bytes.extend(&tmp[28..32]);
-                bytes.extend(calldata);
+                    .encode_input(&[
+                        msg.from.into_token(),
+                        msg.to.into_token(),
+                        msg.value.into_token(),
+                        msg.fee.into_token(),
+                        msg.deadline.into_token(),
+                        msg.nonce.into_token(),
+                        Token::Bytes(msg.calldata.clone()),
+                        proof.into_token(),
+                    ])
+                    .expect("calldata")
+            })
+            .collect();
+
+        Ok((import, calls))
+    }
+
+    /// Records the delivery of the L2 > L1 message `id` by `tx_hash`.
+    async fn message_relayed(&self, id: H256, tx_hash: H256) {
+        self.metrics
+            .message_delivered("l2_to_l1", id, timestamp_ms());
+        let mut rw = self.rw.lock().await;
+        let now = timestamp_ms();
+        let record = rw.message_record(id, MessageDirection::L2ToL1, now);
+        if record.advance(MessageState::Delivered, now) {
+            record.deliver_tx = Some(tx_hash);
+        }
+    }
+
+    /// Queues the expired `msg` for `drop_expired_messages` if its value is above
+    /// `Config::drop_expired_min_value`.
+    async fn queue_expired_message(&self, direction: MessageDirection, msg: &MessageBeacon) {
+        let min_value = match self.config.lock().await.drop_expired_min_value {
+            Some(min_value) => U256::from(min_value),
+            None => return,
+        };
+        match msg.value.checked_add(msg.fee) {
+            Some(total) if total >= min_value => {}
+            Some(_) => return,
+            None => {
+                log::warn!("expired message {:?}: value plus fee overflows", msg.id);
+                return;
             }
+        }
 
-            // relay message
-            {
-                let proof: Bytes =
-                    Bytes::from(marshal_proof_single(&proof_obj.storage_proof[0].proof));
+        let mut rw = self.rw.lock().await;
+        let queue = match direction {
+            MessageDirection::L1ToL2 => &mut rw.l1_expired_messages,
+            MessageDirection::L2ToL1 => &mut rw.l2_expired_messages,
+        };
+        if !queue.iter().any(|e| e.id == msg.id) {
+            queue.push(msg.clone());
+        }
+    }
+
+    /// Refunds the senders of expired messages via `dropMessage` on the origin chain,
+    /// see `Config::drop_expired_min_value`.
+    /// A message is retried until the other chain's state imported into the origin chain
+    /// is past its deadline. Messages that can't be dropped anymore are discarded.
+    pub async fn drop_expired_messages(&self) -> Result<(), CoordinatorError> {
+        let (l1_expired, l2_expired) = {
+            let rw = self.rw.lock().await;
+            (
+                rw.l1_expired_messages.clone(),
+                rw.l2_expired_messages.clone(),
+            )
+        };
+        let mut done = Vec::new();
+        let mut result = Ok(());
+
+        // L1 > L2 messages are dropped on L1 with a proof of the finalized L2 state
+        let l1_bridge_addr = Some(self.config.lock().await.l1_bridge);
+        for msg in l1_expired {
+            if self.rw.lock().await.l2_delivered_messages.contains(&msg.id) {
+                done.push(msg.id);
+                continue;
+            }
+
+            let res = async {
+                let (import, drops) = self
+                    .l2_proof_calls("dropMessage", std::slice::from_ref(&msg))
+                    .await?;
+                let calls: Vec<Vec<u8>> = import.into_iter().chain(drops).collect();
+                self.transaction_to_l1_as(
+                    L1Role::Relayer,
+                    l1_bridge_addr,
+                    U256::zero(),
+                    encode_multicall(&calls),
+                )
+                .await
+                .map(|receipt| receipt.transaction_hash)
+                .map_err(|e| CoordinatorError::transaction("L1:dropMessage", e))
+            }
+            .await;
+            self.message_drop_result(&msg, MessageDirection::L1ToL2, res, &mut done, &mut result)
+                .await;
+        }
+
+        // L2 > L1 messages are dropped on L2 with a proof of the L1 state imported into L2
+        for msg in l2_expired {
+            if self.rw.lock().await.l1_delivered_messages.contains(&msg.id) {
+                done.push(msg.id);
+                continue;
+            }
+
+            let res = async {
+                let block_hash = self.l1_block_imported_into_l2().await?;
+                let l1_bridge_addr = self.config.lock().await.l1_bridge;
+                let proof_obj: MerkleProofRequest = self
+                    .request_l1(
+                        "eth_getProof",
+                        (l1_bridge_addr, [msg.storage_slot()], block_hash),
+                    )
+                    .await
+                    .map_err(|e| CoordinatorError::l1("eth_getProof", e))?;
+                let proof = Bytes::from(marshal_proof_single(&proof_obj.storage_proof[0].proof));
                 let calldata = self
                     .ro
                     .bridge_abi
-                    .function("deliverMessageWithProof")
+                    .function("dropMessage")
                     .unwrap()
                     .encode_input(&[
                         msg.from.into_token(),
@@ -1036,22 +2645,132 @@ impl SharedState {
                         msg.fee.into_token(),
                         msg.deadline.into_token(),
                         msg.nonce.into_token(),
-                        Token::Bytes(msg.calldata),
+                        Token::Bytes(msg.calldata.clone()),
                         proof.into_token(),
                     ])
                     .expect("calldata");
-                U256::from(calldata.len()).to_big_endian(&mut tmp);
-                bytes.extend(&tmp[28..32]);
-                bytes.extend(calldata);
+                let tx_hash = self
+                    .transaction_to_l2(
+                        Some(self.ro.l2_message_dispatcher_addr),
+                        U256::zero(),
+                        calldata,
+                        None,
+                    )
+                    .await
+                    .map_err(|e| CoordinatorError::l2("L2:dropMessage", e))?;
+                self.wait_for_l2_receipt(tx_hash)
+                    .await
+                    .map(|receipt| receipt.transaction_hash)
+                    .map_err(|e| CoordinatorError::transaction("L2:dropMessage", e))
             }
+            .await;
+            self.message_drop_result(&msg, MessageDirection::L2ToL1, res, &mut done, &mut result)
+                .await;
+        }
 
-            // TODO: support relaying multiple messages at once
-            self.transaction_to_l1(l1_bridge_addr, U256::zero(), bytes)
-                .await
-                .expect("receipt");
+        let mut rw = self.rw.lock().await;
+        rw.l1_expired_messages.retain(|msg| !done.contains(&msg.id));
+        rw.l2_expired_messages.retain(|msg| !done.contains(&msg.id));
+
+        result
+    }
+
+    async fn message_drop_result(
+        &self,
+        msg: &MessageBeacon,
+        direction: MessageDirection,
+        res: Result<H256, CoordinatorError>,
+        done: &mut Vec<H256>,
+        result: &mut Result<(), CoordinatorError>,
+    ) {
+        const LOG_TAG: &str = "dropMessage:";
+        match res {
+            Ok(tx_hash) => {
+                log::info!("{} {:?} via {:?}", LOG_TAG, msg.id, tx_hash);
+                let mut rw = self.rw.lock().await;
+                let now = timestamp_ms();
+                let record = rw.message_record(msg.id, direction, now);
+                if record.advance(MessageState::Refunded, now) {
+                    record.refund_tx = Some(tx_hash);
+                }
+                done.push(msg.id);
+            }
+            Err(err) => match drop_message_revert(err.cause()) {
+                Some(DropMessageRevert::NotExpired) => {
+                    log::debug!("{} {:?} {}", LOG_TAG, msg.id, err);
+                }
+                Some(DropMessageRevert::Settled) => {
+                    log::info!("{} {:?} {}", LOG_TAG, msg.id, err);
+                    done.push(msg.id);
+                }
+                None => {
+                    log::error!("{} {:?} {}", LOG_TAG, msg.id, err);
+                    if result.is_ok() {
+                        *result = Err(err);
+                    }
+                }
+            },
         }
     }
 
+    /// Waits up to `Config::l2_tx_timeout` for the receipt of the L2 transaction `tx_hash`,
+    /// the transaction is included by the next `mine` run.
+    async fn wait_for_l2_receipt(&self, tx_hash: H256) -> Result<TransactionReceipt, String> {
+        let timeout = self.config.lock().await.l2_tx_timeout;
+        let started = timestamp_ms();
+        loop {
+            let receipt: Option<TransactionReceipt> = self
+                .request_l2("eth_getTransactionReceipt", [tx_hash])
+                .await?;
+            match receipt {
+                Some(receipt) if receipt.status.map(|status| status.as_u64()) == Some(1) => {
+                    return Ok(receipt)
+                }
+                Some(_) => return Err(format!("transaction {tx_hash:?} reverted")),
+                None if timestamp_ms() > started + timeout => {
+                    return Err(format!(
+                        "transaction {tx_hash:?} not mined after {timeout}ms"
+                    ))
+                }
+                None => tokio::time::sleep(std::time::Duration::from_millis(1000)).await,
+            }
+        }
+    }
+
+    /// Returns the hash of the latest L1 block whose bridge state was imported into L2.
+    async fn l1_block_imported_into_l2(&self) -> Result<H256, CoordinatorError> {
+        let latest_block: U64 = self
+            .request_l2("eth_blockNumber", ())
+            .await
+            .map_err(|e| CoordinatorError::l2("eth_blockNumber", e))?;
+        let evt = self
+            .ro
+            .bridge_abi
+            .event("ForeignBridgeStateImported")
+            .unwrap();
+        let filter = Filter::new()
+            .from_block(latest_block.saturating_sub(U64::from(MAX_LOG_RANGE - 1)))
+            .to_block(latest_block)
+            .address(ValueOrArray::Value(self.ro.l2_message_deliverer_addr))
+            .topic0(ValueOrArray::Value(evt.signature()));
+        let logs: Vec<Log> = self
+            .request_l2("eth_getLogs", [&filter])
+            .await
+            .map_err(|e| CoordinatorError::l2("eth_getLogs", e))?;
+        let log = logs.last().ok_or_else(|| {
+            CoordinatorError::internal(
+                "ForeignBridgeStateImported",
+                "no L1 state imported into L2 recently",
+            )
+        })?;
+        let evt = evt
+            .parse_log(RawLog::from((log.topics.to_owned(), log.data.to_vec())))
+            .map_err(|e| CoordinatorError::l2("ForeignBridgeStateImported", e))?;
+
+        H256::from_token(evt.params[0].value.to_owned())
+            .map_err(|e| CoordinatorError::l2("ForeignBridgeStateImported", e))
+    }
+
     fn _parse_message_beacon(&self, log: Log) -> MessageBeacon {
         // TODO: this is really ugly. consider finding a alternative
         let evt = self.ro.bridge_abi.event("MessageDispatched").unwrap();
@@ -1109,8 +2828,7 @@ impl SharedState {
     pub async fn request_witness(&self, block_num: &U64) -> Result<Witness, String> {
         let block: Block<Transaction> = self
             .request_l2("eth_getBlockByNumber", (block_num, true))
-            .await
-            .expect("block");
+            .await?;
         let mut history_hashes = vec![H256::zero(); 256];
         let mut block_hash = block.parent_hash;
         history_hashes[255] = block_hash;
@@ -1132,19 +2850,25 @@ impl SharedState {
         Ok(witness)
     }
 
-    pub async fn request_proof(&self, block_num: &U64) -> Result<Option<Proofs>, String> {
+    /// Requests the proof of block `block_num` with hash `block_hash`,
+    /// the prover rejects the task if the block was reorged.
+    pub async fn request_proof(
+        &self,
+        block_num: &U64,
+        block_hash: &H256,
+    ) -> Result<Option<Proofs>, CoordinatorError> {
         if self.config.lock().await.dummy_prover {
             log::warn!("COORDINATOR_DUMMY_PROVER");
             let instance: Vec<U256> = {
                 let block_data = self
                     .request_witness(block_num)
                     .await
-                    .expect("witness")
+                    .map_err(|e| CoordinatorError::l2("witness", e))?
                     .input;
                 let func = self.ro.bridge_abi.function("buildCommitment").unwrap();
                 let calldata = Bytes::from(
                     func.encode_input(&[block_data.into_token()])
-                        .expect("calldata"),
+                        .map_err(|e| CoordinatorError::internal("buildCommitment calldata", e))?,
                 );
                 let l1_bridge_addr = self.config.lock().await.l1_bridge;
                 let result: Bytes = self
@@ -1153,21 +2877,17 @@ impl SharedState {
                         serde_json::json!([{ "to": l1_bridge_addr, "data": calldata }, "latest"]),
                     )
                     .await
-                    .expect("eth_call buildCommitment");
-                let result: Vec<Token> = func
-                    .decode_output(&result)
-                    .expect("decode_output")
-                    .get(0)
-                    .unwrap()
-                    .to_owned()
-                    .into_array()
-                    .expect("into_array");
-                let result: Vec<U256> = result
-                    .iter()
-                    .map(|item| item.to_owned().into_uint().expect("into_uint"))
-                    .collect();
-
-                result
+                    .map_err(|e| CoordinatorError::l1("eth_call buildCommitment", e))?;
+                let invalid_output =
+                    || CoordinatorError::l1("buildCommitment output", "expected uint256[]");
+                func.decode_output(&result)
+                    .map_err(|e| CoordinatorError::l1("buildCommitment output", e))?
+                    .pop()
+                    .and_then(|token| token.into_array())
+                    .ok_or_else(invalid_output)?
+                    .into_iter()
+                    .map(|item| item.into_uint().ok_or_else(invalid_output))
+                    .collect::<Result<_, _>>()?
             };
             let mut proofs = Proofs::default();
             proofs.circuit.proof = vec![0u8; 256].into();
@@ -1178,10 +2898,11 @@ impl SharedState {
         }
 
         let config = self.config.lock().await;
-        let prover_rpcd_url = config.prover_rpcd_url.clone();
+        let prover_urls = config.prover_urls();
         let proof_options = ProofRequestOptions {
             circuit: config.circuit_name.clone(),
             block: block_num.as_u64(),
+            block_hash: Some(*block_hash),
             rpc: config.l2_rpc_url.to_string(),
             retry: false,
             param: config.params_path.clone(),
@@ -1189,30 +2910,126 @@ impl SharedState {
             aggregate: config.aggregate_proof,
             mock_feedback: config.mock_prover_if_error,
             verify_proof: config.verify_proof,
+            // use the default of the prover
+            randomness: config.fixed_rng.then_some(ProofRandomness::Fixed),
+            witness_source: WitnessSource::default(),
         };
         drop(config);
+        let circuit = proof_options.circuit.clone();
+        self.metrics
+            .proof_requested(block_num.as_u64(), timestamp_ms());
+
+        self.rw.lock().await.provers.set_urls(&prover_urls);
+        // an unreachable prover is marked as failed and the request goes to the next one,
+        // a request that may have reached the prover sticks to it, see `check_provers`
+        loop {
+            let prover_url = self
+                .rw
+                .lock()
+                .await
+                .provers
+                .select(block_num.as_u64())
+                .ok_or_else(|| CoordinatorError::prover("proof", "no healthy prover"))?;
 
-        let resp = jsonrpc_request_client(
-            RPC_REQUEST_TIMEOUT,
-            &self.ro.http_client,
-            &prover_rpcd_url,
-            "proof",
-            [proof_options],
-        )
-        .await;
-
-        match resp {
-            Err(err) => {
-                match err.as_ref() {
-                    "no result in response" => {
-                        // ...not an error
-                        Ok(None)
+            let resp = jsonrpc_call(
+                RPC_REQUEST_TIMEOUT,
+                &self.ro.http_client,
+                &prover_url,
+                "proof",
+                [&proof_options],
+            )
+            .await;
+
+            return match resp {
+                // ...not an error, the proof is in progress
+                Err(JsonRpcClientError::NoResult) => Ok(None),
+                Err(JsonRpcClientError::Timeout(err) | JsonRpcClientError::Transport(err)) => {
+                    log::warn!("prover {} block {}: {}", prover_url, block_num, err);
+                    Ok(None)
+                }
+                Err(JsonRpcClientError::Connect(err) | JsonRpcClientError::Response(err)) => {
+                    log::warn!("prover {} failed: {}", prover_url, err);
+                    self.rw.lock().await.provers.fail(&prover_url, err);
+                    continue;
+                }
+                Err(JsonRpcClientError::Rpc(err)) => {
+                    // the next attempt may go to another prover
+                    self.rw.lock().await.provers.release(block_num.as_u64());
+                    match ProverError::from_rpc_error(&err) {
+                        Some(err) => {
+                            log::warn!("prover {} block {}: {}", prover_url, block_num, err);
+                            self.metrics.inc(
+                                &PROOF_FAILURES,
+                                &[("circuit", &circuit), ("kind", err.kind())],
+                                1.0,
+                            );
+                            Err(CoordinatorError::prover("proof", err))
+                        }
+                        None => Err(CoordinatorError::prover("proof", err.message)),
                     }
-                    _ => Err(err),
                 }
+                Ok(val) => {
+                    self.rw.lock().await.provers.release(block_num.as_u64());
+                    self.metrics
+                        .proof_ready(&circuit, block_num.as_u64(), timestamp_ms());
+                    Ok(Some(val))
+                }
+            };
+        }
+    }
+
+    /// Checks the provers via their `/health` and `/status` endpoints.
+    pub async fn check_provers(&self) -> Result<(), CoordinatorError> {
+        let urls = self.config.lock().await.prover_urls();
+        self.rw.lock().await.provers.set_urls(&urls);
+
+        for url in urls {
+            let result = self.prover_status(&url).await;
+            if let Err(err) = &result {
+                log::warn!("prover {} unavailable: {}", url, err);
             }
-            Ok(val) => Ok(Some(val)),
+            self.rw
+                .lock()
+                .await
+                .provers
+                .record_check(&url, result, timestamp_ms());
         }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the prover at `url` is busy.
+    async fn prover_status(&self, url: &Uri) -> Result<bool, String> {
+        let base = url.to_string();
+        let base = base.trim_end_matches('/');
+
+        let health = self.prover_get(&format!("{base}/health")).await?;
+        if !health.is_success() {
+            return Err(format!("/health returned {health}"));
+        }
+
+        // 200 if busy else 204
+        let status = self.prover_get(&format!("{base}/status")).await?;
+        match status {
+            hyper::StatusCode::OK => Ok(true),
+            hyper::StatusCode::NO_CONTENT => Ok(false),
+            _ => Err(format!("/status returned {status}")),
+        }
+    }
+
+    async fn prover_get(&self, url: &str) -> Result<hyper::StatusCode, String> {
+        let uri: Uri = url
+            .parse()
+            .map_err(|e: hyper::http::uri::InvalidUri| e.to_string())?;
+        let resp = tokio::time::timeout(
+            std::time::Duration::from_millis(RPC_REQUEST_TIMEOUT),
+            self.ro.http_client.get(uri),
+        )
+        .await
+        .map_err(|e| format!("{url}: {e}"))?
+        .map_err(|e| format!("{url}: {e}"))?;
+
+        Ok(resp.status())
     }
 
     /// Returns the current coordinator configuration.
@@ -1235,9 +3052,12 @@ fn get_abi() -> Abi {
             "event BlockFinalized(bytes32 blockHash)",
             "event MessageDispatched(address from, address to, uint256 value, uint256 fee, uint256 deadline, uint256 nonce, bytes data)",
             "event MessageDelivered(bytes32 id)",
+            "event ForeignBridgeStateImported(bytes32 indexed blockHash, bytes32 indexed stateRoot, uint256 timestamp)",
             "function submitBlock(bytes)",
+            "function submitBlocks(bytes[])",
             "function finalizeBlock(bytes proof)",
             "function deliverMessageWithProof(address from, address to, uint256 value, uint256 fee, uint256 deadline, uint256 nonce, bytes data, bytes proof)",
+            "function dropMessage(address from, address to, uint256 value, uint256 fee, uint256 deadline, uint256 nonce, bytes data, bytes proof)",
             "function stateRoots(bytes32 blockHash) returns (bytes32)",
             "function importForeignBlock(uint256 blockNumber, bytes32 blockHash)",
             "function initGenesis(bytes32 blockHash, bytes32 stateRoot)",
@@ -0,0 +1,168 @@
+use crate::utils::RPC_REQUEST_TIMEOUT;
+use async_trait::async_trait;
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{Address, Bytes};
+use ethers_signers::{LocalWallet, Signer};
+use hyper::client::HttpConnector;
+use hyper::Uri;
+use serde::Serialize;
+use std::sync::Arc;
+use zkevm_common::json_rpc::jsonrpc_request_client;
+
+/// Signs the transactions of a coordinator role.
+#[async_trait]
+pub trait TxSigner: Send + Sync {
+    fn address(&self) -> Address;
+
+    fn chain_id(&self) -> u64;
+
+    /// Returns the raw signed transaction.
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Bytes, String>;
+}
+
+/// Signs with a private key held in memory.
+#[derive(Clone, Debug)]
+pub struct LocalSigner(pub LocalWallet);
+
+#[async_trait]
+impl TxSigner for LocalSigner {
+    fn address(&self) -> Address {
+        self.0.address()
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.0.chain_id()
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Bytes, String> {
+        let sig = self
+            .0
+            .sign_transaction(tx)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(tx.rlp_signed(&sig))
+    }
+}
+
+/// Signs via `eth_signTransaction` of a remote signer (e.g. clef or web3signer)
+/// with the first account of `eth_accounts`.
+pub struct RemoteSigner {
+    client: hyper::Client<HttpConnector>,
+    url: Uri,
+    address: Address,
+    chain_id: u64,
+}
+
+impl RemoteSigner {
+    pub async fn connect(
+        client: hyper::Client<HttpConnector>,
+        url: Uri,
+        chain_id: u64,
+    ) -> Result<Self, String> {
+        let accounts: Vec<Address> =
+            jsonrpc_request_client(RPC_REQUEST_TIMEOUT, &client, &url, "eth_accounts", ()).await?;
+        let address = *accounts
+            .first()
+            .ok_or_else(|| format!("no accounts at remote signer {url}"))?;
+
+        Ok(Self {
+            client,
+            url,
+            address,
+            chain_id,
+        })
+    }
+}
+
+#[async_trait]
+impl TxSigner for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Bytes, String> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum SignResponse {
+            Raw(Bytes),
+            // geth/clef style
+            Object { raw: Bytes },
+        }
+
+        let mut tx = tx.clone();
+        tx.set_from(self.address);
+        tx.set_chain_id(self.chain_id);
+        let resp: SignResponse = jsonrpc_request_client(
+            RPC_REQUEST_TIMEOUT,
+            &self.client,
+            &self.url,
+            "eth_signTransaction",
+            [tx],
+        )
+        .await?;
+
+        match resp {
+            SignResponse::Raw(raw) | SignResponse::Object { raw } => Ok(raw),
+        }
+    }
+}
+
+/// Loads the signer described by `spec` for `chain_id`, either
+/// - a hex private key
+/// - `keystore:<path>` of an encrypted keystore file, decrypted with `password`
+/// - a `http(s)://` URL of a remote signer
+pub async fn load_signer(
+    client: &hyper::Client<HttpConnector>,
+    spec: &str,
+    password: Option<&str>,
+    chain_id: u64,
+) -> Result<Arc<dyn TxSigner>, String> {
+    if let Some(path) = spec.strip_prefix("keystore:") {
+        let password = password.ok_or("keystore password not set")?;
+        let wallet = LocalWallet::decrypt_keystore(path, password)
+            .map_err(|e| format!("keystore {path}: {e}"))?;
+
+        return Ok(Arc::new(LocalSigner(wallet.with_chain_id(chain_id))));
+    }
+
+    if spec.starts_with("http://") || spec.starts_with("https://") {
+        let url: Uri = spec.parse().map_err(|e| format!("remote signer: {e}"))?;
+        let signer = RemoteSigner::connect(client.clone(), url, chain_id).await?;
+
+        return Ok(Arc::new(signer));
+    }
+
+    let wallet = spec
+        .parse::<LocalWallet>()
+        .map_err(|e| format!("private key: {e}"))?;
+
+    Ok(Arc::new(LocalSigner(wallet.with_chain_id(chain_id))))
+}
+
+/// The coordinator roles sending L1 transactions, each with its own key if configured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum L1Role {
+    /// Sends `submitBlock`
+    Submitter,
+    /// Sends `finalizeBlock`
+    Finalizer,
+    /// Relays L2 > L1 messages
+    Relayer,
+    /// Sends the faucet transfers
+    Faucet,
+}
+
+impl L1Role {
+    pub const ALL: [L1Role; 4] = [
+        L1Role::Submitter,
+        L1Role::Finalizer,
+        L1Role::Relayer,
+        L1Role::Faucet,
+    ];
+}
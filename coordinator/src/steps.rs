@@ -0,0 +1,66 @@
+use crate::error::CoordinatorError;
+use serde::Serialize;
+use std::cmp;
+use std::time::SystemTime;
+
+/// Retry delay after the first failure of a step, doubles with each consecutive failure.
+const MIN_BACKOFF_MS: u64 = 1000;
+/// Upper limit of the retry delay.
+const MAX_BACKOFF_MS: u64 = 300_000;
+
+/// Failure statistics and retry schedule of a coordinator step.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StepStatus {
+    /// Number of finished runs
+    pub runs: u64,
+    /// Number of failed runs
+    pub errors: u64,
+    /// Failed runs since the last success
+    pub consecutive_errors: u32,
+    pub last_error: Option<CoordinatorError>,
+    /// Unix timestamp in milliseconds, the step is skipped until then
+    pub retry_at: u64,
+}
+
+impl StepStatus {
+    pub fn ready(&self, now: u64) -> bool {
+        now >= self.retry_at
+    }
+
+    /// Records the outcome of a run that finished at `now`
+    /// and schedules the next attempt if it failed.
+    pub fn record(&mut self, result: &Result<(), CoordinatorError>, now: u64) {
+        self.runs += 1;
+
+        match result {
+            Ok(_) => {
+                self.consecutive_errors = 0;
+                self.retry_at = 0;
+            }
+            Err(err) => {
+                self.errors += 1;
+                self.consecutive_errors += 1;
+                self.last_error = Some(err.clone());
+                self.retry_at = now + self.backoff();
+            }
+        }
+    }
+
+    /// Returns the current retry delay in milliseconds.
+    pub fn backoff(&self) -> u64 {
+        if self.consecutive_errors == 0 {
+            return 0;
+        }
+
+        let exp = cmp::min(self.consecutive_errors - 1, 16);
+        cmp::min(MAX_BACKOFF_MS, MIN_BACKOFF_MS << exp)
+    }
+}
+
+/// Returns the current unix timestamp in milliseconds.
+pub fn timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("time")
+        .as_millis() as u64
+}
@@ -0,0 +1,116 @@
+use crate::structs::*;
+use ethers_core::types::{H256, U64};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// The subset of `RwState` that survives restarts.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistentState {
+    /// The L2 genesis of the state
+    pub genesis_hash: H256,
+    pub chain_state: ForkchoiceStateV1,
+    pub l1_last_sync_block: U64,
+    pub l2_last_sync_block: U64,
+    pub l1_message_queue: VecDeque<MessageBeacon>,
+    pub l2_delivered_messages: Vec<H256>,
+    pub l2_message_queue: Vec<MessageBeacon>,
+    pub l1_delivered_messages: Vec<H256>,
+    pub l1_checkpoints: VecDeque<L1Checkpoint>,
+    /// Changes of a partially applied L1 range, undone by `SharedState::restore`
+    pub l1_changes: L1Changes,
+    pub messages: HashMap<H256, MessageRecord>,
+    pub dropped_messages: HashSet<H256>,
+    pub l1_expired_messages: Vec<MessageBeacon>,
+    pub l2_expired_messages: Vec<MessageBeacon>,
+}
+
+/// Embedded on-disk store for `PersistentState`.
+/// Each field is stored as JSON under its own key.
+/// sled locks the directory, clones of a `Store` share the database
+/// instead of opening the same path again.
+#[derive(Clone)]
+pub struct Store {
+    db: sled::Db,
+    /// The hashes of the stored fields, by key
+    stored: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl Store {
+    /// Opens or creates the database in directory `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let db = sled::open(path).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            db,
+            stored: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Returns `None` if nothing was stored yet.
+    pub fn load(&self) -> Result<Option<PersistentState>, String> {
+        let mut fields = serde_json::Map::new();
+        let mut stored = self.stored.lock().unwrap();
+        for item in self.db.iter() {
+            let (key, value) = item.map_err(|e| e.to_string())?;
+            let key = String::from_utf8(key.to_vec()).map_err(|e| e.to_string())?;
+            stored.insert(key.clone(), hash(&value));
+            let value = serde_json::from_slice(&value).map_err(|e| e.to_string())?;
+            fields.insert(key, value);
+        }
+
+        if fields.is_empty() {
+            return Ok(None);
+        }
+
+        let state =
+            serde_json::from_value(serde_json::Value::Object(fields)).map_err(|e| e.to_string())?;
+
+        Ok(Some(state))
+    }
+
+    /// Writes the fields of `state` that changed since the last `save` or `load`
+    /// in one atomic batch and flushes them to disk.
+    pub async fn save(&self, state: &PersistentState) -> Result<(), String> {
+        let fields = match serde_json::to_value(state).map_err(|e| e.to_string())? {
+            serde_json::Value::Object(fields) => fields,
+            _ => unreachable!("PersistentState is a struct"),
+        };
+
+        let mut batch = sled::Batch::default();
+        let mut changed = Vec::new();
+        {
+            let stored = self.stored.lock().unwrap();
+            for (key, value) in fields {
+                let value = serde_json::to_vec(&value).map_err(|e| e.to_string())?;
+                let value_hash = hash(&value);
+                if stored.get(&key) != Some(&value_hash) {
+                    batch.insert(key.as_bytes(), value);
+                    changed.push((key, value_hash));
+                }
+            }
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        self.db.apply_batch(batch).map_err(|e| e.to_string())?;
+        self.db.flush_async().await.map_err(|e| e.to_string())?;
+        self.stored.lock().unwrap().extend(changed);
+
+        Ok(())
+    }
+}
+
+fn hash(value: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
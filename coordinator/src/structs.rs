@@ -2,8 +2,9 @@ use ethers_core::abi::encode;
 use ethers_core::abi::Tokenizable;
 use ethers_core::types::{Address, Bytes, H256, U256, U64};
 use ethers_core::utils::keccak256;
+use std::collections::HashMap;
 
-#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
 pub struct ForkchoiceStateV1 {
     #[serde(rename = "headBlockHash")]
     pub head_block_hash: H256,
@@ -13,7 +14,56 @@ pub struct ForkchoiceStateV1 {
     pub finalized_block_hash: H256,
 }
 
-#[derive(Clone, Debug)]
+/// Submission and proof state of a L2 block, see `SharedState::block_status`.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct BlockStatus {
+    pub number: U64,
+    pub submitted: bool,
+    pub finalized: bool,
+    /// `None` if no proof was requested ahead of finalization
+    pub proof_ready: Option<bool>,
+}
+
+/// The L1 derived state after syncing up to L1 block `number`.
+/// Used to roll back to a common ancestor on L1 reorgs.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct L1Checkpoint {
+    pub number: U64,
+    pub hash: H256,
+    /// Bridge events were applied since the previous checkpoint
+    pub events: bool,
+    pub safe_block_hash: H256,
+    pub finalized_block_hash: H256,
+    /// The changes of the bridge events since the previous checkpoint
+    pub changes: L1Changes,
+}
+
+/// The changes of the L1 bridge events to the message state, undone on a rollback.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct L1Changes {
+    /// Messages added to `l1_message_queue`
+    pub l1_queued: Vec<H256>,
+    /// Messages added to `l1_delivered_messages`
+    pub l1_delivered: Vec<H256>,
+    /// Messages added to `l2_message_queue`
+    pub l2_queued: Vec<H256>,
+    /// The message records before their first change, `None` if they were created
+    pub records: HashMap<H256, Option<MessageRecord>>,
+}
+
+impl L1Changes {
+    /// Appends the changes of `next`, which followed these.
+    pub fn extend(&mut self, next: L1Changes) {
+        self.l1_queued.extend(next.l1_queued);
+        self.l1_delivered.extend(next.l1_delivered);
+        self.l2_queued.extend(next.l2_queued);
+        for (id, record) in next.records {
+            self.records.entry(id).or_insert(record);
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct MessageBeacon {
     pub id: H256,
     pub from: Address,
@@ -55,6 +105,135 @@ impl MessageBeacon {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageDirection {
+    L1ToL2,
+    L2ToL1,
+}
+
+/// The lifecycle of a bridge message, in the order a message goes through.
+/// - L1 > L2: dispatched > included (delivered by a L2 block) > delivered (that block is finalized)
+/// - L2 > L1: included (dispatched in a L2 block) > finalized > delivered (on L1)
+///
+/// `dropped` and `expired` messages can still become `delivered` if someone else delivers them
+/// or `refunded` once the coordinator called `dropMessage` for them.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageState {
+    Dispatched,
+    Included,
+    Finalized,
+    /// Given up by the coordinator, the message reverts or was dropped via the admin RPC
+    Dropped,
+    /// The deadline of the message passed
+    Expired,
+    Delivered,
+    /// Dropped via `dropMessage` on the origin chain, the sender got the value back
+    Refunded,
+}
+
+/// The state of a bridge message, see `SharedState::message_status`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MessageRecord {
+    pub id: H256,
+    pub direction: MessageDirection,
+    pub state: MessageState,
+    /// The transaction calling `dispatchMessage`
+    pub dispatch_tx: Option<H256>,
+    /// The L2 block of the delivery (L1 > L2) or the dispatch (L2 > L1)
+    pub l2_block: Option<H256>,
+    /// The L1 transaction finalizing `l2_block`
+    pub finalize_tx: Option<H256>,
+    /// The transaction delivering the message
+    pub deliver_tx: Option<H256>,
+    /// The `dropMessage` transaction
+    pub refund_tx: Option<H256>,
+    /// Unix timestamp in milliseconds of the latest state change
+    pub updated_at: u64,
+}
+
+impl MessageRecord {
+    pub fn new(id: H256, direction: MessageDirection, now: u64) -> Self {
+        Self {
+            id,
+            direction,
+            state: MessageState::Dispatched,
+            dispatch_tx: None,
+            l2_block: None,
+            finalize_tx: None,
+            deliver_tx: None,
+            refund_tx: None,
+            updated_at: now,
+        }
+    }
+
+    /// Moves to `state` unless the message is already past it.
+    /// Returns `false` if the state didn't change.
+    pub fn advance(&mut self, state: MessageState, now: u64) -> bool {
+        if state <= self.state {
+            return false;
+        }
+
+        self.state = state;
+        self.updated_at = now;
+
+        true
+    }
+}
+
+/// The storage proof and the gas estimate of a L1 > L2 message delivery.
+#[derive(Clone, Debug)]
+pub struct DeliveryEstimate {
+    /// The L1 block of the storage proof
+    pub l1_block: H256,
+    pub storage_proof: Bytes,
+    pub gas: U256,
+}
+
+/// EIP-1559 fee parameters of a L1 transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct L1Fees {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl L1Fees {
+    /// Returns the fees raised by `percent` but at least by 1 wei each,
+    /// as required to replace a pending transaction.
+    pub fn bump(&self, percent: u64) -> Self {
+        let bump = |fee: U256| std::cmp::max(fee * (100 + percent) / 100, fee + 1);
+
+        Self {
+            max_fee_per_gas: bump(self.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(self.max_priority_fee_per_gas),
+        }
+    }
+
+    /// Returns the fees with `max_fee_per_gas` limited to `cap`.
+    pub fn capped(&self, cap: U256) -> Self {
+        let max_fee_per_gas = std::cmp::min(self.max_fee_per_gas, cap);
+
+        Self {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: std::cmp::min(self.max_priority_fee_per_gas, max_fee_per_gas),
+        }
+    }
+
+    /// Returns the higher fees of both.
+    pub fn max(&self, other: &Self) -> Self {
+        Self {
+            max_fee_per_gas: std::cmp::max(self.max_fee_per_gas, other.max_fee_per_gas),
+            max_priority_fee_per_gas: std::cmp::max(
+                self.max_priority_fee_per_gas,
+                other.max_priority_fee_per_gas,
+            ),
+        }
+    }
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct SealBlockRequest<'a> {
     pub parent: &'a H256,
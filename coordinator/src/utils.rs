@@ -1,5 +1,5 @@
+use crate::signer::{LocalSigner, TxSigner};
 use crate::structs::*;
-use crate::timeout;
 use ethers_core::types::transaction::eip2930::AccessListWithGasUsed;
 use ethers_core::types::Transaction;
 use ethers_core::types::{
@@ -8,61 +8,69 @@ use ethers_core::types::{
 };
 use ethers_core::utils::keccak256;
 use ethers_core::utils::rlp::RlpStream;
-use ethers_signers::{LocalWallet, Signer};
+use ethers_signers::LocalWallet;
 use hyper::client::HttpConnector;
+use hyper::HeaderMap;
 use hyper::Uri;
+use std::net::IpAddr;
 use zkevm_common::json_rpc::jsonrpc_request_client;
+use zkevm_common::json_rpc::JsonRpcError;
 
 pub const RPC_REQUEST_TIMEOUT: u64 = 30000;
 
 /// may override any pending transactions
-pub async fn send_transaction_to_l1(
+pub async fn sign_transaction_l1(
     client: &hyper::Client<HttpConnector>,
     node_uri: &Uri,
     wallet: &LocalWallet,
     to: Option<Address>,
     value: U256,
     calldata: Vec<u8>,
-) -> Result<TransactionReceipt, String> {
-    let nonce: U256 = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_getTransactionCount",
-        (wallet.address(), "latest"),
-    )
-    .await
-    .expect("nonce");
+    nonce: U256,
+) -> Result<Bytes, String> {
+    let fees = get_l1_fees(client, node_uri).await?;
+    let signer = LocalSigner(wallet.clone());
+    let tx =
+        prepare_transaction_l1(client, node_uri, &signer, to, value, calldata, nonce, fees).await?;
 
-    let raw_tx = sign_transaction_l1(client, node_uri, wallet, to, value, calldata, nonce).await?;
-    // wait up to 120 seconds
-    timeout!(120_000, wait_for_tx(client, node_uri, &raw_tx).await)
+    sign_eip1559(&signer, &tx).await
 }
 
-/// may override any pending transactions
-pub async fn sign_transaction_l1(
+/// Returns the initial fees for L1 transactions.
+pub async fn get_l1_fees(
     client: &hyper::Client<HttpConnector>,
     node_uri: &Uri,
-    wallet: &LocalWallet,
+) -> Result<L1Fees, String> {
+    let gas_price: U256 =
+        jsonrpc_request_client(RPC_REQUEST_TIMEOUT, client, node_uri, "eth_gasPrice", ()).await?;
+
+    Ok(L1Fees {
+        max_fee_per_gas: gas_price * 2u64,
+        max_priority_fee_per_gas: U256::one(),
+    })
+}
+
+/// Returns the transaction including access list and gas limit, ready for signing.
+#[allow(clippy::too_many_arguments)]
+pub async fn prepare_transaction_l1(
+    client: &hyper::Client<HttpConnector>,
+    node_uri: &Uri,
+    signer: &dyn TxSigner,
     to: Option<Address>,
     value: U256,
     calldata: Vec<u8>,
     nonce: U256,
-) -> Result<Bytes, String> {
-    let wallet_addr: Address = wallet.address();
-
-    let gas_price: U256 =
-        jsonrpc_request_client(RPC_REQUEST_TIMEOUT, client, node_uri, "eth_gasPrice", ())
-            .await
-            .expect("gasPrice");
+    fees: L1Fees,
+) -> Result<Eip1559TransactionRequest, String> {
+    let wallet_addr: Address = signer.address();
 
     let mut tx: Eip1559TransactionRequest = Eip1559TransactionRequest::new()
-        .chain_id(wallet.chain_id())
+        .chain_id(signer.chain_id())
         .from(wallet_addr)
         .nonce(nonce)
         .value(value)
-        .max_priority_fee_per_gas(1u64)
-        .max_fee_per_gas(gas_price * 2u64)
+        .max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
+        .max_fee_per_gas(fees.max_fee_per_gas)
         .data(calldata);
 
     if to.is_some() {
@@ -76,8 +84,7 @@ pub async fn sign_transaction_l1(
         "eth_createAccessList",
         [&tx],
     )
-    .await
-    .expect("eth_createAccessList");
+    .await?;
     let tx = tx.access_list(access_list.access_list);
     let estimate: U256 = jsonrpc_request_client(
         RPC_REQUEST_TIMEOUT,
@@ -87,54 +94,49 @@ pub async fn sign_transaction_l1(
         [&tx],
     )
     .await?;
-    let tx = tx.gas(estimate).into();
 
-    log::debug!("sending l1 tx: {:?}", tx);
+    Ok(tx.gas(estimate))
+}
+
+/// Returns the raw signed transaction.
+pub async fn sign_eip1559(
+    signer: &dyn TxSigner,
+    tx: &Eip1559TransactionRequest,
+) -> Result<Bytes, String> {
+    let tx = tx.clone().into();
 
-    let sig = wallet
-        .sign_transaction(&tx)
-        .await
-        .expect("sign_transaction");
+    log::debug!("sending l1 tx: {:?}", tx);
 
-    Ok(tx.rlp_signed(&sig))
+    signer.sign_transaction(&tx).await
 }
 
-/// may override any pending transactions
-pub async fn send_transaction_to_l2(
+/// Returns the raw signed L2 transaction.
+/// The gas is estimated against `block_tag` unless `gas_limit` is given.
+#[allow(clippy::too_many_arguments)]
+pub async fn sign_transaction_l2(
     client: &hyper::Client<HttpConnector>,
     node_uri: &Uri,
-    wallet: &LocalWallet,
+    signer: &dyn TxSigner,
     to: Option<Address>,
     value: U256,
+    nonce: U256,
     calldata: Vec<u8>,
     gas_limit: Option<U256>,
-) -> Result<H256, String> {
-    let wallet_addr: Address = wallet.address();
-    let nonce: U256 = jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_getTransactionCount",
-        (wallet_addr, "latest"),
-    )
-    .await
-    .expect("nonce");
-
+    block_tag: &str,
+) -> Result<Bytes, String> {
     let gas_price: U256 =
-        jsonrpc_request_client(RPC_REQUEST_TIMEOUT, client, node_uri, "eth_gasPrice", ())
-            .await
-            .expect("gasPrice");
+        jsonrpc_request_client(RPC_REQUEST_TIMEOUT, client, node_uri, "eth_gasPrice", ()).await?;
 
     let mut tx = TransactionRequest::new()
-        .chain_id(wallet.chain_id())
-        .from(wallet_addr)
+        .chain_id(signer.chain_id())
+        .from(signer.address())
         .nonce(nonce)
         .value(value)
         .gas_price(gas_price * 2u64)
         .data(calldata);
 
-    if to.is_some() {
-        tx = tx.to(to.unwrap())
+    if let Some(to) = to {
+        tx = tx.to(to);
     }
 
     let estimate: U256 = match gas_limit {
@@ -145,25 +147,14 @@ pub async fn send_transaction_to_l2(
                 client,
                 node_uri,
                 "eth_estimateGas",
-                [&tx],
+                (&tx, block_tag),
             )
             .await?
         }
     };
     let tx = tx.gas(estimate).into();
 
-    let sig = wallet.sign_transaction(&tx).await.unwrap();
-    let raw_tx = tx.rlp_signed(&sig);
-
-    // TODO: will be obsolete once execution api is used
-    jsonrpc_request_client(
-        RPC_REQUEST_TIMEOUT,
-        client,
-        node_uri,
-        "eth_sendRawTransaction",
-        [raw_tx],
-    )
-    .await
+    signer.sign_transaction(&tx).await
 }
 
 /// Can loop forever, thus should be wrapped inside timeout handler
@@ -223,8 +214,11 @@ pub fn format_block<T>(block: &Block<T>) -> String {
     )
 }
 
-pub async fn get_chain_head(client: &hyper::Client<HttpConnector>, uri: &Uri) -> BlockHeader {
-    let header: BlockHeader = jsonrpc_request_client(
+pub async fn get_chain_head(
+    client: &hyper::Client<HttpConnector>,
+    uri: &Uri,
+) -> Result<BlockHeader, String> {
+    jsonrpc_request_client(
         RPC_REQUEST_TIMEOUT,
         client,
         uri,
@@ -232,9 +226,6 @@ pub async fn get_chain_head(client: &hyper::Client<HttpConnector>, uri: &Uri) ->
         ["latest"],
     )
     .await
-    .unwrap();
-
-    header
 }
 
 pub async fn get_blocks_between(
@@ -242,7 +233,7 @@ pub async fn get_blocks_between(
     uri: &Uri,
     from: &H256,
     to: &H256,
-) -> Vec<Block<H256>> {
+) -> Result<Vec<Block<H256>>, String> {
     let mut ret: Vec<Block<H256>> = Vec::new();
     let mut hash = *to;
     loop {
@@ -253,8 +244,7 @@ pub async fn get_blocks_between(
             "eth_getBlockByHash",
             (hash, false),
         )
-        .await
-        .expect("eth_getBlockByHash");
+        .await?;
         hash = block.parent_hash;
 
         if block.hash.unwrap() != *from {
@@ -265,7 +255,19 @@ pub async fn get_blocks_between(
         }
     }
 
-    ret
+    Ok(ret)
+}
+
+/// Returns `calls` as calldata for `Multicall.multicall`,
+/// each call prefixed with its length as 4 byte big-endian.
+pub fn encode_multicall(calls: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = keccak256("multicall()")[0..4].to_vec();
+    for call in calls {
+        bytes.extend((call.len() as u32).to_be_bytes());
+        bytes.extend(call);
+    }
+
+    bytes
 }
 
 /// encodes the proof from `eth_getCode` suitable for the Patricia{Account,Storage}Validator contract.
@@ -378,3 +380,59 @@ pub fn encode_verifier_witness(
 
     Ok(witness)
 }
+
+/// Tells if `err` of `eth_call` or `eth_estimateGas` is a failed execution,
+/// unlike errors of the node itself.
+pub fn is_execution_revert(err: &JsonRpcError) -> bool {
+    // geth uses code 3 for reverts with data
+    err.code == 3
+        || err.message.starts_with("execution reverted")
+        || err.message.starts_with("gas required exceeds allowance")
+        || err.message.starts_with("invalid opcode")
+        || err.message.starts_with("out of gas")
+}
+
+/// The revert reasons of the bridges for a `dropMessage` call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropMessageRevert {
+    /// `DMTS`: the proven state is not past the deadline yet
+    NotExpired,
+    /// `DMH` or `DMVAL`: the message was already dropped or delivered
+    Settled,
+}
+
+/// Classifies the revert reason in the error `err` of a `dropMessage` call,
+/// `None` for any other error.
+pub fn drop_message_revert(err: &str) -> Option<DropMessageRevert> {
+    const PREFIX: &str = "execution reverted: ";
+    let start = err.find(PREFIX)? + PREFIX.len();
+    let reason: String = err[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+
+    match reason.as_str() {
+        "DMTS" => Some(DropMessageRevert::NotExpired),
+        "DMH" | "DMVAL" => Some(DropMessageRevert::Settled),
+        _ => None,
+    }
+}
+
+/// Returns the address of the client behind the peer `peer`.
+/// `X-Real-IP` or else the last `X-Forwarded-For` entry is used if `peer` is a trusted proxy.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> IpAddr {
+    // the listener may be dual-stack
+    let peer = match peer {
+        IpAddr::V6(ip) => ip.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(peer),
+        ip => ip,
+    };
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    header("x-real-ip")
+        .or_else(|| header("x-forwarded-for").and_then(|value| value.rsplit(',').next()))
+        .and_then(|ip| ip.trim().parse().ok())
+        .unwrap_or(peer)
+}
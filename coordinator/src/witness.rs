@@ -0,0 +1,261 @@
+use ethers_core::types::{Address, Block, Bytes, Signature, Transaction, H256, U256};
+use ethers_core::utils::keccak256;
+use ethers_core::utils::rlp::{Rlp, RlpStream};
+
+/// Version byte of the compact block witness.
+///
+/// Layout: `version (0x02) | flags | body`, the body is zero-byte run-length encoded
+/// if `FLAG_COMPRESSED` is set. The body consists of the RLP block header followed by
+/// each transaction as `rlp([nonce, gasPrice, gas, to, value, data]) | r | s`,
+/// with the signature recovery bit stored in the highest bit of `s`.
+///
+/// Compared to `encode_verifier_witness` (v1), the history hashes are omitted because
+/// they are derivable from the parent hashes, the EIP-155 fields are implied by the chain id
+/// and the sender is recovered from the signature.
+/// Scope: only this encoder and the reference decoder `decode_witness_v2` exist.
+/// Neither the L1 bridge nor the prover decode v2 witnesses, thus the coordinator
+/// doesn't emit them and `submit_blocks` keeps sending v1 witnesses.
+pub const WITNESS_V2: u8 = 2;
+/// The body is zero-byte run-length encoded
+pub const FLAG_COMPRESSED: u8 = 1;
+
+/// A transaction of a decoded witness.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessTransaction {
+    pub nonce: U256,
+    pub gas_price: U256,
+    pub gas: U256,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub input: Bytes,
+    /// Recovered from the signature
+    pub from: Address,
+    pub r: U256,
+    pub s: U256,
+    /// EIP-155 `v`
+    pub v: u64,
+}
+
+/// A decoded v2 witness.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedWitness {
+    /// The RLP encoded block header
+    pub header: Bytes,
+    pub transactions: Vec<WitnessTransaction>,
+}
+
+impl DecodedWitness {
+    pub fn block_hash(&self) -> H256 {
+        H256::from(keccak256(&self.header))
+    }
+}
+
+/// Encodes the v2 witness of `block`, run-length encoded if `compress` is set.
+/// Only legacy EIP-155 transactions for `chain_id` are supported.
+pub fn encode_witness_v2(
+    block: &Block<Transaction>,
+    chain_id: u64,
+    compress: bool,
+) -> Result<Vec<u8>, String> {
+    let mut body: Vec<u8> = Vec::new();
+    {
+        let mut rlp = RlpStream::new_list(15);
+        rlp.append(&block.parent_hash);
+        rlp.append(&block.uncles_hash);
+        rlp.append(&block.author.ok_or("block.author")?);
+        rlp.append(&block.state_root);
+        rlp.append(&block.transactions_root);
+        rlp.append(&block.receipts_root);
+        rlp.append(&block.logs_bloom.ok_or("block.logs_bloom")?);
+        rlp.append(&block.difficulty);
+        rlp.append(&block.number.ok_or("block.number")?);
+        rlp.append(&block.gas_limit);
+        rlp.append(&block.gas_used);
+        rlp.append(&block.timestamp);
+        rlp.append(&block.extra_data.as_ref());
+        rlp.append(&block.mix_hash.ok_or("block.mix_hash")?);
+        rlp.append(&block.nonce.ok_or("block.nonce")?);
+        body.extend(rlp.out());
+    }
+
+    for tx in block.transactions.iter() {
+        if !tx.transaction_type.unwrap_or_default().is_zero() {
+            return Err(format!(
+                "unsupported type {:?} of tx {:?}",
+                tx.transaction_type, tx.hash
+            ));
+        }
+        // the decoder implies `chain_id * 2 + 35/36`
+        let parity = match tx.v.as_u64().checked_sub(chain_id * 2 + 35) {
+            Some(parity) if parity < 2 => parity,
+            _ => return Err(format!("non EIP-155 v {} in tx {:?}", tx.v, tx.hash)),
+        };
+
+        let mut rlp = RlpStream::new_list(6);
+        rlp.append(&tx.nonce);
+        rlp.append(&tx.gas_price.ok_or("tx.gas_price")?);
+        rlp.append(&tx.gas);
+        match tx.to {
+            Some(to) => rlp.append(&to.as_ref()),
+            None => rlp.append_empty_data(),
+        };
+        rlp.append(&tx.value);
+        rlp.append(&tx.input.as_ref());
+        body.extend(rlp.out());
+
+        // low-s signatures (EIP-2) leave the highest bit of `s` unused
+        if tx.s.bit(255) {
+            return Err(format!("non-canonical signature in tx {:?}", tx.hash));
+        }
+        let mut s = tx.s;
+        if parity == 1 {
+            s |= U256::one() << 255;
+        }
+        let mut tmp = [0u8; 32];
+        tx.r.to_big_endian(&mut tmp);
+        body.extend(tmp);
+        s.to_big_endian(&mut tmp);
+        body.extend(tmp);
+    }
+
+    let mut witness = vec![WITNESS_V2, 0];
+    if compress {
+        witness[1] |= FLAG_COMPRESSED;
+        witness.extend(rle_compress(&body));
+    } else {
+        witness.extend(body);
+    }
+
+    Ok(witness)
+}
+
+/// Decodes a v2 witness of a block with EIP-155 transactions for `chain_id`.
+pub fn decode_witness_v2(witness: &[u8], chain_id: u64) -> Result<DecodedWitness, String> {
+    let (version, flags) = match witness {
+        [version, flags, ..] => (*version, *flags),
+        _ => return Err("witness too short".to_string()),
+    };
+    if version != WITNESS_V2 {
+        return Err(format!("unsupported witness version {version}"));
+    }
+    let body = if flags & FLAG_COMPRESSED != 0 {
+        rle_decompress(&witness[2..])?
+    } else {
+        witness[2..].to_vec()
+    };
+
+    let header_len = rlp_item_len(&body)?;
+    let header = Bytes::from(body[0..header_len].to_vec());
+
+    let mut transactions = Vec::new();
+    let mut ptr = header_len;
+    while ptr < body.len() {
+        let len = rlp_item_len(&body[ptr..])?;
+        let rlp = Rlp::new(&body[ptr..ptr + len]);
+        ptr += len;
+        let signature = body
+            .get(ptr..ptr + 64)
+            .ok_or("tx signature out of bounds")?;
+        ptr += 64;
+
+        let to = rlp.at(3).map_err(|e| e.to_string())?;
+        let to = if to.is_empty() {
+            None
+        } else {
+            Some(to.as_val::<Address>().map_err(|e| e.to_string())?)
+        };
+        let r = U256::from_big_endian(&signature[0..32]);
+        let s = U256::from_big_endian(&signature[32..64]);
+        let parity = s.bit(255) as u64;
+        let s = s & !(U256::one() << 255);
+        let v = chain_id * 2 + 35 + parity;
+
+        // EIP-155 signing payload
+        let mut payload = RlpStream::new_list(9);
+        for i in 0..6 {
+            payload.append_raw(rlp.at(i).map_err(|e| e.to_string())?.as_raw(), 1);
+        }
+        payload.append(&chain_id);
+        payload.append_empty_data();
+        payload.append_empty_data();
+        let sighash = H256::from(keccak256(payload.out()));
+        let from = Signature { r, s, v }
+            .recover(sighash)
+            .map_err(|e| e.to_string())?;
+
+        transactions.push(WitnessTransaction {
+            nonce: rlp.val_at(0).map_err(|e| e.to_string())?,
+            gas_price: rlp.val_at(1).map_err(|e| e.to_string())?,
+            gas: rlp.val_at(2).map_err(|e| e.to_string())?,
+            to,
+            value: rlp.val_at(4).map_err(|e| e.to_string())?,
+            input: Bytes::from(rlp.val_at::<Vec<u8>>(5).map_err(|e| e.to_string())?),
+            from,
+            r,
+            s,
+            v,
+        });
+    }
+
+    Ok(DecodedWitness {
+        header,
+        transactions,
+    })
+}
+
+/// Returns the length of the RLP item at the start of `data`.
+fn rlp_item_len(data: &[u8]) -> Result<usize, String> {
+    let info = Rlp::new(data).payload_info().map_err(|e| e.to_string())?;
+    let len = info.header_len + info.value_len;
+    if len > data.len() {
+        return Err("rlp item out of bounds".to_string());
+    }
+
+    Ok(len)
+}
+
+/// Replaces each run of zero bytes with `0x00 | run length (1..=255)`.
+pub fn rle_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] != 0 {
+            out.push(data[i]);
+            i += 1;
+            continue;
+        }
+
+        let run = data[i..]
+            .iter()
+            .take(255)
+            .take_while(|byte| **byte == 0)
+            .count();
+        out.push(0);
+        out.push(run as u8);
+        i += run;
+    }
+
+    out
+}
+
+/// Reverses `rle_compress`.
+pub fn rle_decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len() * 2);
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] != 0 {
+            out.push(data[i]);
+            i += 1;
+            continue;
+        }
+
+        let run = *data.get(i + 1).ok_or("truncated zero run")?;
+        if run == 0 {
+            return Err("empty zero run".to_string());
+        }
+        out.resize(out.len() + run as usize, 0);
+        i += 2;
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use futures_util::SinkExt;
+use futures_util::StreamExt;
+use hyper::client::HttpConnector;
+use hyper::header::HeaderValue;
+use hyper::header::{CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::{json, Map, Value};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::proxy::{local_response, proxy, ProxyRequest};
+use crate::shared_state::SharedState;
+
+/// Maximum number of subscriptions per connection
+const MAX_SUBSCRIPTIONS: usize = 64;
+/// Maximum number of open websocket connections
+const MAX_CONNECTIONS: usize = 1024;
+
+/// A block set by `SharedState::set_chain_head`, with all of its logs.
+#[derive(Debug, Clone, Default)]
+pub struct NewHead {
+    pub header: Value,
+    pub logs: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Subscription {
+    NewHeads,
+    /// The `address` and `topics` of the filter
+    Logs(Map<String, Value>),
+}
+
+/// The `eth_subscribe` subscriptions of a websocket connection, by subscription id.
+#[derive(Debug, Default)]
+pub struct Subscriptions(pub HashMap<String, Subscription>);
+
+impl Subscriptions {
+    /// Handles `eth_subscribe` and `eth_unsubscribe`.
+    /// Returns `None` for any other method.
+    pub fn call(&mut self, method: &str, params: &[Value]) -> Option<Result<Value, String>> {
+        match method {
+            "eth_subscribe" => Some(self.subscribe(params)),
+            "eth_unsubscribe" => Some(match params.get(0).and_then(Value::as_str) {
+                Some(id) => Ok(self.0.remove(id).is_some().into()),
+                None => Err("expected a subscription id".to_string()),
+            }),
+            _ => None,
+        }
+    }
+
+    fn subscribe(&mut self, params: &[Value]) -> Result<Value, String> {
+        if self.0.len() >= MAX_SUBSCRIPTIONS {
+            return Err("too many subscriptions".to_string());
+        }
+
+        let subscription = match params.get(0).and_then(Value::as_str) {
+            Some("newHeads") => Subscription::NewHeads,
+            Some("logs") => {
+                let filter = match params.get(1) {
+                    None => Map::new(),
+                    Some(Value::Object(filter)) => filter
+                        .iter()
+                        .filter(|(key, _)| *key == "address" || *key == "topics")
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect(),
+                    Some(_) => return Err("invalid logs filter".to_string()),
+                };
+                Subscription::Logs(filter)
+            }
+            _ => return Err("only newHeads and logs are supported".to_string()),
+        };
+
+        let id = format!("0x{:032x}", rand::random::<u128>());
+        self.0.insert(id.clone(), subscription);
+
+        Ok(id.into())
+    }
+
+    /// Returns the `eth_subscription` notifications for `head`.
+    /// The logs of the block are matched against the filter of each subscription.
+    pub fn notifications(&self, head: &NewHead) -> Vec<Value> {
+        let mut notifications = Vec::new();
+
+        for (id, subscription) in self.0.iter() {
+            match subscription {
+                Subscription::NewHeads => notifications.push(notification(id, head.header.clone())),
+                Subscription::Logs(filter) => notifications.extend(
+                    head.logs
+                        .iter()
+                        .filter(|log| log_matches(filter, log))
+                        .map(|log| notification(id, log.clone())),
+                ),
+            }
+        }
+
+        notifications
+    }
+}
+
+/// Returns `true` if `log` matches the `address` and `topics` of `filter`,
+/// like the `eth_getLogs` filter of the node.
+pub fn log_matches(filter: &Map<String, Value>, log: &Value) -> bool {
+    // a value or a list of values, null matches anything
+    let matches = |expected: &Value, value: Option<&Value>| {
+        let eq = |expected: &Value| match (expected.as_str(), value.and_then(Value::as_str)) {
+            (Some(expected), Some(value)) => expected.eq_ignore_ascii_case(value),
+            _ => false,
+        };
+        match expected {
+            Value::Null => true,
+            Value::Array(expected) => expected.is_empty() || expected.iter().any(eq),
+            expected => eq(expected),
+        }
+    };
+
+    if let Some(address) = filter.get("address") {
+        if !matches(address, log.get("address")) {
+            return false;
+        }
+    }
+
+    if let Some(Value::Array(topics)) = filter.get("topics") {
+        let log_topics = log["topics"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if log_topics.len() < topics.len() {
+            return false;
+        }
+        if !topics
+            .iter()
+            .zip(log_topics)
+            .all(|(topic, value)| matches(topic, Some(value)))
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn notification(id: &str, result: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "eth_subscription",
+        "params": { "subscription": id, "result": result },
+    })
+}
+
+/// Returns `true` if `req` asks for a websocket upgrade.
+pub fn is_upgrade(req: &Request<Body>) -> bool {
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    };
+
+    header(CONNECTION).split(',').any(|e| e.trim() == "upgrade") && header(UPGRADE) == "websocket"
+}
+
+/// Accepts the websocket upgrade of `req` and serves the connection in the background.
+/// The json-rpc requests are proxied to the rpc nodes like on the http endpoint,
+/// `eth_subscribe` supports `newHeads` and `logs` for the blocks of `SharedState::new_heads`.
+/// Responds with `503` if there are already `MAX_CONNECTIONS` open connections.
+pub fn upgrade(
+    shared_state: SharedState,
+    client: hyper::Client<HttpConnector>,
+    remote_addr: SocketAddr,
+    mut req: Request<Body>,
+) -> Response<Body> {
+    let accept = match req.headers().get(SEC_WEBSOCKET_KEY) {
+        Some(key) => derive_accept_key(key.as_bytes()),
+        None => {
+            let mut resp = Response::new(Body::from("missing sec-websocket-key"));
+            *resp.status_mut() = StatusCode::BAD_REQUEST;
+            return resp;
+        }
+    };
+
+    let connections = shared_state.ws_connections.clone();
+    if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+        connections.fetch_sub(1, Ordering::SeqCst);
+        let mut resp = Response::new(Body::from("too many connections"));
+        *resp.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+        return resp;
+    }
+    let connection = Connection(connections);
+
+    tokio::spawn(async move {
+        let _connection = connection;
+        match hyper::upgrade::on(&mut req).await {
+            Ok(upgraded) => {
+                let ws = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+                serve(shared_state, client, remote_addr, ws).await;
+            }
+            Err(err) => log::warn!("ws: upgrade for {}: {}", remote_addr, err),
+        }
+    });
+
+    let mut resp = Response::default();
+    *resp.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = resp.headers_mut();
+    headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+    headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(
+        SEC_WEBSOCKET_ACCEPT,
+        HeaderValue::from_str(&accept).unwrap(),
+    );
+
+    resp
+}
+
+/// Counts as an open connection until dropped.
+struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+async fn serve<S>(
+    shared_state: SharedState,
+    client: hyper::Client<HttpConnector>,
+    remote_addr: SocketAddr,
+    mut ws: WebSocketStream<S>,
+) where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let mut new_heads = shared_state.new_heads.subscribe();
+    let mut subscriptions = Subscriptions::default();
+
+    loop {
+        let messages = tokio::select! {
+            msg = ws.next() => {
+                let body = match msg {
+                    Some(Ok(Message::Text(text))) => text.into_bytes(),
+                    Some(Ok(Message::Binary(data))) => data,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    // pings are answered by tungstenite
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => {
+                        log::debug!("ws: {}: {}", remote_addr, err);
+                        break;
+                    }
+                };
+
+                vec![handle_message(&shared_state, &client, remote_addr, &mut subscriptions, &body).await]
+            }
+            head = new_heads.recv() => match head {
+                Ok(head) => subscriptions.notifications(&head),
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("ws: {} skipped {} heads", remote_addr, skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        for msg in messages {
+            if let Err(err) = ws.send(Message::Text(msg.to_string())).await {
+                log::debug!("ws: {}: {}", remote_addr, err);
+                return;
+            }
+        }
+    }
+}
+
+async fn handle_message(
+    shared_state: &SharedState,
+    client: &hyper::Client<HttpConnector>,
+    remote_addr: SocketAddr,
+    subscriptions: &mut Subscriptions,
+    body: &[u8],
+) -> Value {
+    // subscriptions are handled by the coordinator itself, only as single requests
+    if let Ok(Value::Object(req)) = serde_json::from_slice::<Value>(body) {
+        let method = req
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = match req.get("params") {
+            Some(Value::Array(params)) => params.as_slice(),
+            _ => &[],
+        };
+
+        if let Some(res) = subscriptions.call(method, params) {
+            let id = req.get("id").cloned().unwrap_or_default();
+            return match res {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(err) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32602, "message": err },
+                }),
+            };
+        }
+    }
+
+    if let Some(resp) = local_response(shared_state, body).await {
+        return resp;
+    }
+
+    match ProxyRequest::parse(body) {
+        Err(err) => serde_json::to_value(err).unwrap(),
+        Ok(req) if req.forward.is_empty() => req.response(Ok(Value::Null)),
+        Ok(req) => {
+            let upstream = proxy(shared_state, client, remote_addr.ip(), &req).await;
+            req.response(upstream)
+        }
+    }
+}
@@ -31,7 +31,7 @@ async fn zero_eth_transfer() {
         )
         .await
         .expect("tx_hash");
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     wait_for_tx!(tx_hash, &shared_state.config.lock().await.l2_rpc_url);
 
     finalize_chain!(shared_state);
@@ -49,7 +49,7 @@ async fn keccak() {
         .transaction_to_l2(None, U256::zero(), bytecode, None)
         .await
         .expect("tx_hash");
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     shared_state.config.lock().await.dummy_prover = true;
     finalize_chain!(shared_state);
 
@@ -59,7 +59,7 @@ async fn keccak() {
         .transaction_to_l2(contract_addr, U256::zero(), vec![], None)
         .await
         .expect("tx_hash");
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     wait_for_tx!(tx_hash, &shared_state.config.lock().await.l2_rpc_url);
     shared_state.config.lock().await.dummy_prover = false;
     shared_state.config.lock().await.mock_prover = true;
@@ -150,7 +150,7 @@ async fn l1_l2_sync_test() {
 async fn finalize_chain() {
     let shared_state = await_state!();
     sync!(shared_state);
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     finalize_chain!(shared_state);
 }
 
@@ -160,7 +160,7 @@ async fn finalize_chain() {
 async fn test_pi_commitment() {
     let shared_state = await_state!();
     sync!(shared_state);
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
 
     let mut tx_nonce: U256 = jsonrpc_request(
         &shared_state.config.lock().await.l2_rpc_url,
@@ -198,7 +198,7 @@ async fn test_pi_commitment() {
 
     loop {
         let proofs: Option<Proofs> = shared_state
-            .request_proof(&block_num)
+            .request_proof(&block_num, &block.hash.unwrap())
             .await
             .expect("proofs");
         match proofs {
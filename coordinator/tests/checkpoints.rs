@@ -0,0 +1,148 @@
+use coordinator::shared_state::RwState;
+use coordinator::structs::{DeliveryEstimate, L1Changes, MessageBeacon, MessageDirection};
+use ethers_core::types::{Address, Bytes, H256, U256, U64};
+
+fn beacon(id: H256) -> MessageBeacon {
+    MessageBeacon {
+        id,
+        from: Address::zero(),
+        to: Address::zero(),
+        value: U256::zero(),
+        fee: U256::zero(),
+        deadline: U256::zero(),
+        nonce: U256::zero(),
+        calldata: Vec::new(),
+    }
+}
+
+fn estimate() -> DeliveryEstimate {
+    DeliveryEstimate {
+        l1_block: H256::zero(),
+        storage_proof: Bytes::default(),
+        gas: U256::zero(),
+    }
+}
+
+#[test]
+fn l1_checkpoints() {
+    let mut rw = RwState::default();
+    let checkpoints = |rw: &RwState| -> Vec<u64> {
+        rw.l1_checkpoints
+            .iter()
+            .map(|checkpoint| checkpoint.number.as_u64())
+            .collect()
+    };
+
+    rw.push_l1_checkpoint(U64::from(0), H256::repeat_byte(0), true);
+    // ranges without events move the newest checkpoint forward
+    rw.push_l1_checkpoint(U64::from(10), H256::repeat_byte(10), false);
+    assert_eq!(checkpoints(&rw), vec![0, 10]);
+    rw.push_l1_checkpoint(U64::from(20), H256::repeat_byte(20), false);
+    assert_eq!(checkpoints(&rw), vec![0, 20]);
+    assert_eq!(rw.l1_last_sync_block, U64::from(20));
+
+    rw.push_l1_checkpoint(U64::from(30), H256::repeat_byte(30), true);
+    assert_eq!(checkpoints(&rw), vec![0, 30]);
+    rw.push_l1_checkpoint(U64::from(40), H256::repeat_byte(40), false);
+    rw.push_l1_checkpoint(U64::from(50), H256::repeat_byte(50), true);
+    rw.push_l1_checkpoint(U64::from(60), H256::repeat_byte(60), false);
+    assert_eq!(checkpoints(&rw), vec![0, 30, 50, 60]);
+    assert_eq!(
+        rw.l1_checkpoints.back().unwrap().hash,
+        H256::repeat_byte(60)
+    );
+
+    // the only checkpoint is kept
+    let mut rw = RwState::default();
+    rw.push_l1_checkpoint(U64::from(1), H256::repeat_byte(1), false);
+    rw.push_l1_checkpoint(U64::from(2), H256::repeat_byte(2), false);
+    assert_eq!(checkpoints(&rw), vec![1, 2]);
+}
+
+#[test]
+fn l1_checkpoints_prune_delivered() {
+    let (queued, expired, relayed, early) = (
+        H256::repeat_byte(1),
+        H256::repeat_byte(2),
+        H256::repeat_byte(3),
+        H256::repeat_byte(4),
+    );
+    let mut rw = RwState::default();
+    rw.l2_message_queue.push(beacon(queued));
+    rw.l2_expired_messages.push(beacon(expired));
+    rw.message_record(relayed, MessageDirection::L2ToL1, 0)
+        .dispatch_tx = Some(H256::repeat_byte(9));
+    // delivered before the dispatch was synced
+    rw.message_record(early, MessageDirection::L2ToL1, 0);
+    rw.l1_delivered_messages = vec![queued, expired, relayed, early];
+
+    rw.l1_message_queue.push_back(beacon(queued));
+    rw.l2_delivered_messages = vec![queued, relayed];
+
+    rw.push_l1_checkpoint(U64::from(1), H256::repeat_byte(1), true);
+    assert_eq!(rw.l1_delivered_messages, vec![queued, expired, early]);
+    assert_eq!(rw.l2_delivered_messages, vec![queued]);
+}
+
+#[test]
+fn l1_rollback() {
+    let (old, queued, delivered, created) = (
+        H256::repeat_byte(1),
+        H256::repeat_byte(2),
+        H256::repeat_byte(3),
+        H256::repeat_byte(4),
+    );
+    let mut rw = RwState::default();
+    rw.l1_message_queue.push_back(beacon(old));
+    rw.message_record(old, MessageDirection::L1ToL2, 0);
+    rw.message_record(delivered, MessageDirection::L2ToL1, 0)
+        .dispatch_tx = Some(H256::repeat_byte(9));
+    rw.l2_message_queue.push(beacon(delivered));
+    rw.push_l1_checkpoint(U64::from(10), H256::repeat_byte(10), true);
+    let record = rw.messages.get(&delivered).cloned();
+
+    // an applied range
+    rw.l1_message_queue.push_back(beacon(queued));
+    rw.l1_changes.l1_queued.push(queued);
+    rw.l1_message_record(queued, MessageDirection::L1ToL2, 1);
+    rw.l1_message_record(delivered, MessageDirection::L2ToL1, 1)
+        .deliver_tx = Some(H256::repeat_byte(8));
+    rw.l1_delivered_messages.push(delivered);
+    rw.l1_changes.l1_delivered.push(delivered);
+    rw.push_l1_checkpoint(U64::from(20), H256::repeat_byte(20), true);
+
+    // a partially applied range
+    rw.l1_message_record(created, MessageDirection::L1ToL2, 2);
+    rw.dropped_messages.insert(created);
+    rw.delivery_estimates.insert(queued, estimate());
+    rw.delivery_estimates.insert(old, estimate());
+
+    rw.rollback_l1(0);
+    assert_eq!(rw.l1_last_sync_block, U64::from(10));
+    assert_eq!(rw.l1_checkpoints.len(), 1);
+    assert_eq!(rw.l1_changes, L1Changes::default());
+    let ids: Vec<H256> = rw.l1_message_queue.iter().map(|msg| msg.id).collect();
+    assert_eq!(ids, vec![old]);
+    assert!(rw.l1_delivered_messages.is_empty());
+    assert_eq!(rw.messages.get(&delivered).cloned(), record);
+    assert!(rw.messages.contains_key(&old));
+    assert!(!rw.messages.contains_key(&queued));
+    assert!(!rw.messages.contains_key(&created));
+    assert!(!rw.dropped_messages.contains(&created));
+    assert!(rw.delivery_estimates.contains_key(&old));
+    assert!(!rw.delivery_estimates.contains_key(&queued));
+}
+
+#[test]
+fn awaits_sync() {
+    let mut rw = RwState::default();
+    assert!(!rw.awaits_sync(None));
+
+    rw.push_l1_checkpoint(U64::from(10), H256::repeat_byte(10), false);
+    assert!(rw.awaits_sync(Some(U64::from(11))));
+    assert!(!rw.awaits_sync(Some(U64::from(10))));
+
+    // rolled back by a reorg
+    rw.l1_last_sync_block = U64::from(5);
+    assert!(rw.awaits_sync(Some(U64::from(10))));
+}
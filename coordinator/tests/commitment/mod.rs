@@ -7,7 +7,6 @@ use ethers_core::abi::Tokenizable;
 use ethers_core::types::Bytes;
 use ethers_core::types::U256;
 use ethers_core::types::U64;
-use ethers_signers::Signer;
 use std::fs::File;
 use std::io::BufReader;
 use zkevm_common::prover::CircuitConfig;
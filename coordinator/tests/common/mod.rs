@@ -125,6 +125,8 @@ pub async fn get_shared_state() -> &'static Mutex<SharedState> {
                 .is_test(true)
                 .try_init();
         let shared_state = SharedState::from_env().await;
+        // reproducible proofs
+        shared_state.config.lock().await.fixed_rng = true;
         shared_state.init().await;
 
         Mutex::new(shared_state)
@@ -143,10 +145,10 @@ macro_rules! await_state {
 macro_rules! sync {
     ($shared_state:expr) => {
         // sync bridge and process events
-        $shared_state.sync().await;
+        $shared_state.sync().await.expect("sync");
         while $shared_state.rw.lock().await.l1_message_queue.len() > 0 {
-            $shared_state.mine().await;
-            $shared_state.sync().await;
+            $shared_state.mine().await.expect("mine");
+            $shared_state.sync().await.expect("sync");
             sleep!(300);
         }
     };
@@ -203,15 +205,15 @@ macro_rules! finalize_chain {
             drop(rw);
 
             sync!($shared_state);
-            $shared_state.submit_blocks().await;
+            $shared_state.submit_blocks().await.expect("submit_blocks");
             let dummy_prover = $shared_state.config.lock().await.dummy_prover;
             if $use_dummy {
                 $shared_state.config.lock().await.dummy_prover = true;
             }
             let result = $shared_state.finalize_blocks().await;
             if result.is_err() {
-                let msg = result.err().unwrap();
-                if msg.find("deadline has elapsed").is_some() {
+                let msg = result.err().unwrap().to_string();
+                if msg.contains("deadline has elapsed") || msg.contains("timed out") {
                     deadline_count += 1;
                     if deadline_count > MAX_DEADLINE_ERRORS {
                         panic!("finalize_chain: exceeded MAX_DEADLINE_ERRORS in finalize_blocks");
@@ -225,7 +227,7 @@ macro_rules! finalize_chain {
             }
             sync!($shared_state);
             while $shared_state.rw.lock().await.l2_message_queue.len() != 0 {
-                $shared_state.relay_to_l1().await;
+                $shared_state.relay_to_l1().await.expect("relay_to_l1");
                 sync!($shared_state);
             }
         }
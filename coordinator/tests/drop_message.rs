@@ -38,8 +38,8 @@ async fn trigger_l1_block_import(shared_state: &SharedState) {
         .transaction_to_l1(Some(l1_bridge_addr), U256::zero(), calldata)
         .await
         .expect("dispatchMessage");
-    shared_state.sync().await;
-    shared_state.mine().await;
+    shared_state.sync().await.expect("sync");
+    shared_state.mine().await.expect("mine");
     finalize_chain!(shared_state, true);
 }
 
@@ -62,8 +62,8 @@ async fn trigger_l2_block_import(shared_state: &SharedState) {
         .transaction_to_l2(Some(l2_bridge_addr), U256::zero(), calldata, None)
         .await
         .expect("dispatchMessage");
-    shared_state.sync().await;
-    shared_state.mine().await;
+    shared_state.sync().await.expect("sync");
+    shared_state.mine().await.expect("mine");
     finalize_chain!(shared_state, true);
 }
 
@@ -360,12 +360,12 @@ async fn l2_drop_message() {
 
     // dispatch
     {
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         dispatch_msg_l2(&shared_state, &msg)
             .await
             .expect("dispatch_message success");
 
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         assert_eq!(
             dispatch_msg_l2(&shared_state, &msg).await.unwrap_err(),
             "execution reverted: DMH"
@@ -376,12 +376,12 @@ async fn l2_drop_message() {
 
     // drop
     {
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         drop_message_l2(&shared_state, &msg, None)
             .await
             .expect("drop_message success");
 
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         assert_eq!(
             drop_message_l2(&shared_state, &msg, None)
                 .await
@@ -390,7 +390,7 @@ async fn l2_drop_message() {
         );
     }
 
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     finalize_chain!(shared_state);
 }
 
@@ -401,12 +401,12 @@ async fn l2_drop_message_zero_length_proof() {
 
     // dispatch
     {
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         dispatch_msg_l2(&shared_state, &msg)
             .await
             .expect("dispatch_message success");
 
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         assert_eq!(
             dispatch_msg_l2(&shared_state, &msg).await.unwrap_err(),
             "execution reverted: DMH"
@@ -417,7 +417,7 @@ async fn l2_drop_message_zero_length_proof() {
 
     // drop
     {
-        shared_state.mine().await;
+        shared_state.mine().await.expect("mine");
         assert_eq!(
             drop_message_l2(&shared_state, &msg, Some(Bytes::from([])))
                 .await
@@ -426,7 +426,7 @@ async fn l2_drop_message_zero_length_proof() {
         );
     }
 
-    shared_state.mine().await;
+    shared_state.mine().await.expect("mine");
     finalize_chain!(shared_state);
 }
 
@@ -0,0 +1,140 @@
+use coordinator::event_sync::EventSync;
+use coordinator::event_sync::MAX_LOG_RANGE;
+use ethers_core::types::{Filter, Log, U64};
+use std::sync::Mutex;
+
+fn range_of(filter: &Filter) -> (U64, U64) {
+    let filter = serde_json::to_value(filter).expect("serialize filter");
+    let block = |key: &str| serde_json::from_value(filter[key].clone()).expect(key);
+
+    (block("fromBlock"), block("toBlock"))
+}
+
+#[tokio::test]
+async fn event_sync_adapts_range() {
+    let filter = Filter::new();
+    let latest = U64::from(1_000_000);
+    let mut events = EventSync::default();
+
+    // empty responses grow the range up to the limit
+    let mut from = U64::from(1);
+    let mut prev_range = 0;
+    while events.range() < MAX_LOG_RANGE {
+        assert!(events.range() > prev_range);
+        prev_range = events.range();
+        let (to, logs) = events
+            .next_logs(&filter, from, latest, |filter| async move {
+                let (from, to) = range_of(&filter);
+                assert_eq!((to - from).as_u64() + 1, prev_range);
+                Ok::<_, String>(vec![])
+            })
+            .await
+            .expect("next_logs");
+        assert!(logs.is_empty());
+        from = to + 1u64;
+    }
+
+    // errors shrink the range until the request succeeds
+    let (to, _) = events
+        .next_logs(&filter, from, latest, |filter| async move {
+            let (from, to) = range_of(&filter);
+            if (to - from).as_u64() + 1 > 100 {
+                return Err("query returned more than 10000 results".to_string());
+            }
+            Ok(vec![])
+        })
+        .await
+        .expect("next_logs");
+    assert_eq!((to - from).as_u64() + 1, 64);
+
+    // large responses shrink the range, removed logs are dropped
+    let (_, logs) = events
+        .next_logs(&filter, to + 1u64, latest, |_| async move {
+            let mut logs = vec![Log::default(); 2000];
+            logs[0].removed = Some(true);
+            Ok::<_, String>(logs)
+        })
+        .await
+        .expect("next_logs");
+    assert_eq!(logs.len(), 1999);
+    assert_eq!(events.range(), 64);
+
+    // the range never exceeds `latest`
+    let (to, _) = events
+        .next_logs(&filter, latest - 3u64, latest, |_| async move {
+            Ok::<_, String>(vec![])
+        })
+        .await
+        .expect("next_logs");
+    assert_eq!(to, latest);
+
+    // a single block that fails is an error
+    let mut events = EventSync::default();
+    let res = events
+        .next_logs(&filter, from, latest, |_| async move {
+            Err("unavailable".to_string())
+        })
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn event_sync_advances_by_block() {
+    let filter = Filter::new();
+    let cursor = Mutex::new(U64::zero());
+    let mut events = EventSync::default();
+
+    // ranges without logs advance the cursor
+    events
+        .sync(
+            &filter,
+            U64::from(1),
+            U64::from(100),
+            |_| async move { Ok::<_, String>(vec![]) },
+            |from, to, _| {
+                let cursor = &cursor;
+                async move {
+                    let mut cursor = cursor.lock().unwrap();
+                    assert_eq!(*cursor + 1u64, from);
+                    *cursor = to;
+                    Ok(true)
+                }
+            },
+        )
+        .await
+        .expect("sync");
+    assert_eq!(*cursor.lock().unwrap(), U64::from(100));
+
+    // `apply` stops the sync
+    let calls = Mutex::new(0);
+    events
+        .sync(
+            &filter,
+            U64::from(101),
+            U64::from(1000),
+            |_| async move { Ok::<_, String>(vec![Log::default()]) },
+            |_, _, logs| {
+                let calls = &calls;
+                async move {
+                    assert_eq!(logs.len(), 1);
+                    *calls.lock().unwrap() += 1;
+                    Ok(false)
+                }
+            },
+        )
+        .await
+        .expect("sync");
+    assert_eq!(*calls.lock().unwrap(), 1);
+
+    // errors of `apply` are returned
+    let res = events
+        .sync(
+            &filter,
+            U64::from(101),
+            U64::from(1000),
+            |_| async move { Ok(vec![]) },
+            |_, _, _| async move { Err("apply".to_string()) },
+        )
+        .await;
+    assert_eq!(res, Err("apply".to_string()));
+}
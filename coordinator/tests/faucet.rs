@@ -0,0 +1,60 @@
+use coordinator::faucet::Faucet;
+use coordinator::utils::client_ip;
+use ethers_core::types::Address;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+
+#[tokio::test]
+async fn faucet_request() {
+    let faucet = Faucet::default();
+    let (a, b, c) = (
+        Address::repeat_byte(1),
+        Address::repeat_byte(2),
+        Address::repeat_byte(3),
+    );
+    let ip_a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let ip_b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let cooldown = 1000;
+
+    assert_eq!(faucet.request(a, Some(ip_a), cooldown, 0).await, Ok(0));
+    // already queued, also from another client
+    assert_eq!(faucet.request(a, Some(ip_b), cooldown, 10).await, Ok(0));
+    // same client
+    assert_eq!(faucet.request(b, Some(ip_a), cooldown, 100).await, Err(900));
+    assert_eq!(faucet.request(b, Some(ip_b), cooldown, 100).await, Ok(1));
+    assert_eq!(faucet.request(c, None, cooldown, 100).await, Ok(2));
+    assert_eq!(faucet.queue.lock().await.len(), 3);
+
+    // paid out
+    faucet.queue.lock().await.clear();
+    assert_eq!(faucet.request(a, None, cooldown, 500).await, Err(500));
+    assert_eq!(faucet.request(a, None, cooldown, 1000).await, Ok(0));
+    assert_eq!(
+        faucet.request(b, Some(ip_a), cooldown, 1000).await,
+        Err(100)
+    );
+    assert_eq!(faucet.request(b, Some(ip_a), cooldown, 1100).await, Ok(1));
+}
+
+#[test]
+fn faucet_client_ip() {
+    let proxy: IpAddr = Ipv4Addr::new(172, 18, 0, 2).into();
+    let client: IpAddr = Ipv4Addr::new(203, 0, 113, 7).into();
+    let mut headers = hyper::HeaderMap::new();
+    headers.insert("x-forwarded-for", "10.0.0.1, 203.0.113.7".parse().unwrap());
+
+    // the last entry was added by the trusted proxy
+    assert_eq!(client_ip(proxy, &headers, &[proxy]), client);
+    // anyone else could fake the header
+    assert_eq!(client_ip(proxy, &headers, &[]), proxy);
+    assert_eq!(client_ip(client, &headers, &[proxy]), client);
+
+    headers.insert("x-real-ip", "198.51.100.1".parse().unwrap());
+    assert_eq!(
+        client_ip(proxy, &headers, &[proxy]),
+        IpAddr::from(Ipv4Addr::new(198, 51, 100, 1))
+    );
+    // dual-stack listeners see IPv4-mapped addresses
+    let mapped: IpAddr = "::ffff:172.18.0.2".parse().unwrap();
+    assert_eq!(client_ip(mapped, &hyper::HeaderMap::new(), &[proxy]), proxy);
+}
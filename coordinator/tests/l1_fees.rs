@@ -0,0 +1,41 @@
+use coordinator::structs::L1Fees;
+use ethers_core::types::U256;
+
+#[test]
+fn l1_fees_bump() {
+    let fees = L1Fees {
+        max_fee_per_gas: U256::from(1000),
+        max_priority_fee_per_gas: U256::one(),
+    };
+
+    let bumped = fees.bump(20);
+    assert_eq!(bumped.max_fee_per_gas, U256::from(1200));
+    // at least 1 wei more, otherwise the replacement is rejected
+    assert_eq!(bumped.max_priority_fee_per_gas, U256::from(2));
+
+    let network = L1Fees {
+        max_fee_per_gas: U256::from(5000),
+        max_priority_fee_per_gas: U256::zero(),
+    };
+    let fees = network.max(&bumped);
+    assert_eq!(fees.max_fee_per_gas, U256::from(5000));
+    assert_eq!(fees.max_priority_fee_per_gas, U256::from(2));
+}
+
+#[test]
+fn l1_fees_capped() {
+    let fees = L1Fees {
+        max_fee_per_gas: U256::from(1000),
+        max_priority_fee_per_gas: U256::from(10),
+    };
+
+    assert_eq!(fees.capped(U256::from(2000)), fees);
+    let capped = fees.bump(20).capped(U256::from(1100));
+    assert_eq!(capped.max_fee_per_gas, U256::from(1100));
+    assert_eq!(capped.max_priority_fee_per_gas, U256::from(12));
+    // the priority fee can't exceed the max fee
+    assert_eq!(
+        fees.capped(U256::from(5)).max_priority_fee_per_gas,
+        U256::from(5)
+    );
+}
@@ -0,0 +1,75 @@
+use coordinator::shared_state::RwState;
+use coordinator::structs::{MessageDirection, MessageRecord, MessageState};
+use ethers_core::types::H256;
+
+#[test]
+fn message_lifecycle() {
+    let id = H256::repeat_byte(1);
+    let mut record = MessageRecord::new(id, MessageDirection::L2ToL1, 1);
+    assert_eq!(record.state, MessageState::Dispatched);
+
+    assert!(record.advance(MessageState::Included, 2));
+    assert!(record.advance(MessageState::Finalized, 3));
+    // no going back
+    assert!(!record.advance(MessageState::Included, 4));
+    assert!(record.advance(MessageState::Expired, 5));
+    // someone else delivered it anyway
+    assert!(record.advance(MessageState::Delivered, 6));
+    assert!(!record.advance(MessageState::Dropped, 7));
+    assert_eq!(record.state, MessageState::Delivered);
+    assert_eq!(record.updated_at, 6);
+
+    // refunded via `dropMessage`
+    let mut expired = MessageRecord::new(id, MessageDirection::L1ToL2, 1);
+    assert!(expired.advance(MessageState::Expired, 2));
+    assert!(expired.advance(MessageState::Refunded, 3));
+    assert!(!expired.advance(MessageState::Delivered, 4));
+
+    let json = serde_json::to_value(&record).unwrap();
+    assert_eq!(json["direction"], "l2_to_l1");
+    assert_eq!(json["state"], "delivered");
+}
+
+#[test]
+fn message_records() {
+    const DAY: u64 = 24 * 3600 * 1000;
+    let (a, b, c) = (
+        H256::repeat_byte(1),
+        H256::repeat_byte(2),
+        H256::repeat_byte(3),
+    );
+    let mut rw = RwState::default();
+
+    rw.message_record(a, MessageDirection::L1ToL2, 0)
+        .dispatch_tx = Some(H256::repeat_byte(9));
+    rw.message_record(a, MessageDirection::L1ToL2, 1)
+        .advance(MessageState::Delivered, 1);
+    rw.message_record(b, MessageDirection::L2ToL1, 0);
+    assert_eq!(rw.messages[&a].dispatch_tx, Some(H256::repeat_byte(9)));
+    assert_eq!(rw.messages[&a].updated_at, 1);
+
+    // finished messages are forgotten after a while, pending ones are kept
+    rw.message_record(c, MessageDirection::L1ToL2, 30 * DAY);
+    assert!(!rw.messages.contains_key(&a));
+    assert!(rw.messages.contains_key(&b));
+    assert!(rw.messages.contains_key(&c));
+}
+
+#[test]
+fn message_tombstones() {
+    let id = H256::repeat_byte(1);
+    let mut rw = RwState::default();
+
+    rw.tombstone_message(id, MessageDirection::L1ToL2, 1);
+    assert_eq!(rw.messages[&id].state, MessageState::Dropped);
+    assert!(rw.dropped_messages.contains(&id));
+
+    // the tombstone outlives the record
+    rw.message_record(
+        H256::repeat_byte(2),
+        MessageDirection::L1ToL2,
+        30 * 24 * 3600 * 1000,
+    );
+    assert!(!rw.messages.contains_key(&id));
+    assert!(rw.dropped_messages.contains(&id));
+}
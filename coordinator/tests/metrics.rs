@@ -0,0 +1,72 @@
+use coordinator::metrics::*;
+use ethers_core::types::H256;
+
+#[test]
+fn metrics_render() {
+    let metrics = Metrics::default();
+    assert_eq!(metrics.render(), "");
+
+    metrics.inc(&L1_GAS_USED, &[("role", "submitter")], 21000.0);
+    metrics.inc(&L1_GAS_USED, &[("role", "submitter")], 1000.0);
+    metrics.inc(&L1_GAS_USED, &[("role", "finalizer")], 5.0);
+    metrics.set(&L2_BLOCK, &[("kind", "head")], 7.0);
+    metrics.set(&L2_BLOCK, &[("kind", "head")], 8.0);
+    metrics.inc(
+        &PROXY_REQUESTS,
+        &[("method", "eth_call"), ("node", "http://\"l2\"")],
+        1.0,
+    );
+
+    let expected = r#"# HELP coordinator_l1_gas_used_total Gas used by the L1 transactions of the coordinator.
+# TYPE coordinator_l1_gas_used_total counter
+coordinator_l1_gas_used_total{role="finalizer"} 5
+coordinator_l1_gas_used_total{role="submitter"} 22000
+# HELP coordinator_l2_block Number of the head, safe and finalized L2 block.
+# TYPE coordinator_l2_block gauge
+coordinator_l2_block{kind="head"} 8
+# HELP coordinator_proxy_requests_total Number of proxied json-rpc requests.
+# TYPE coordinator_proxy_requests_total counter
+coordinator_proxy_requests_total{method="eth_call",node="http://\"l2\""} 1
+"#;
+    assert_eq!(metrics.render(), expected);
+}
+
+#[test]
+fn metrics_latency() {
+    let metrics = Metrics::default();
+    let (a, b, c) = (
+        H256::repeat_byte(1),
+        H256::repeat_byte(2),
+        H256::repeat_byte(3),
+    );
+
+    metrics.message_queued(a, 1000);
+    // the first time counts
+    metrics.message_queued(a, 2000);
+    metrics.message_queued(b, 1000);
+    metrics.message_queued(c, 1000);
+    metrics.message_delivered("l1_to_l2", a, 3500);
+    // not queued anymore
+    metrics.message_delivered("l1_to_l2", a, 9000);
+    metrics.retain_messages(|id| *id == c);
+    metrics.message_delivered("l1_to_l2", b, 9000);
+    metrics.message_delivered("l2_to_l1", c, 2000);
+
+    metrics.proof_requested(5, 0);
+    metrics.proof_ready("super", 5, 60_000);
+    metrics.proof_ready("super", 6, 60_000);
+
+    let rendered = metrics.render();
+    assert!(rendered.contains("# TYPE coordinator_message_delivery_seconds summary\n"));
+    assert!(
+        rendered.contains("coordinator_message_delivery_seconds_sum{direction=\"l1_to_l2\"} 2.5\n")
+    );
+    assert!(
+        rendered.contains("coordinator_message_delivery_seconds_count{direction=\"l1_to_l2\"} 1\n")
+    );
+    assert!(
+        rendered.contains("coordinator_message_delivery_seconds_sum{direction=\"l2_to_l1\"} 1\n")
+    );
+    assert!(rendered.contains("coordinator_proof_seconds_sum{circuit=\"super\"} 60\n"));
+    assert!(rendered.contains("coordinator_proof_seconds_count{circuit=\"super\"} 1\n"));
+}
@@ -0,0 +1,15 @@
+use coordinator::utils::encode_multicall;
+use ethers_core::utils::keccak256;
+
+#[test]
+fn multicall_encoding() {
+    let calls = vec![vec![1u8, 2, 3, 4], vec![], vec![0xff; 300]];
+    let calldata = encode_multicall(&calls);
+    assert_eq!(calldata[0..4], keccak256("multicall()")[0..4]);
+    // selector, length prefixes and calls
+    assert_eq!(calldata.len(), 4 + 3 * 4 + 4 + 300);
+    assert_eq!(calldata[4..8], [0, 0, 0, 4]);
+    assert_eq!(calldata[8..12], [1, 2, 3, 4]);
+    assert_eq!(calldata[12..16], [0, 0, 0, 0]);
+    assert_eq!(calldata[16..20], [0, 0, 1, 44]);
+}
@@ -9,7 +9,6 @@ use ethers_core::types::Bytes;
 use ethers_core::types::H256;
 use ethers_core::types::U256;
 use ethers_core::utils::keccak256;
-use ethers_signers::Signer;
 use zkevm_common::json_rpc::jsonrpc_request;
 
 #[tokio::test]
@@ -26,8 +25,8 @@ async fn native_withdraw() {
     .await
     .expect("eth_getBalance");
 
-    shared_state.sync().await;
-    shared_state.mine().await;
+    shared_state.sync().await.expect("sync");
+    shared_state.mine().await.expect("mine");
 
     {
         let mut tx_nonce: U256 = jsonrpc_request(
@@ -94,7 +93,7 @@ async fn native_withdraw() {
 
     // verify that all messages are picked up
     {
-        shared_state.sync().await;
+        shared_state.sync().await.expect("sync");
         for id in messages {
             let found = shared_state
                 .rw
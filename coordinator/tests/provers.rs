@@ -0,0 +1,60 @@
+use coordinator::provers::ProverPool;
+use hyper::Uri;
+
+fn urls() -> Vec<Uri> {
+    vec![
+        "http://prover-a:8545".parse().unwrap(),
+        "http://prover-b:8545".parse().unwrap(),
+        "http://prover-c:8545".parse().unwrap(),
+    ]
+}
+
+#[test]
+fn prover_pool_dispatch() {
+    let urls = urls();
+    let mut pool = ProverPool::new(&urls);
+
+    assert_eq!(pool.select(1), Some(urls[0].clone()));
+    // busy provers are avoided
+    pool.record_check(&urls[1], Ok(true), 1);
+    assert_eq!(pool.select(2), Some(urls[2].clone()));
+    // round-robin among equally busy provers
+    assert_eq!(pool.select(3), Some(urls[0].clone()));
+    pool.release(1);
+    pool.release(3);
+    assert_eq!(pool.select(4), Some(urls[0].clone()));
+
+    // sticky assignments
+    assert_eq!(pool.select(2), Some(urls[2].clone()));
+    assert_eq!(pool.backends()[2].assigned, 1);
+}
+
+#[test]
+fn prover_pool_failover() {
+    let urls = urls();
+    let mut pool = ProverPool::new(&urls[0..2]);
+
+    assert_eq!(pool.select(1), Some(urls[0].clone()));
+    pool.fail(&urls[0], "connection refused".to_string());
+    assert_eq!(pool.select(1), Some(urls[1].clone()));
+    assert_eq!(pool.backends()[0].assigned, 0);
+    assert_eq!(
+        pool.backends()[0].last_error.as_deref(),
+        Some("connection refused")
+    );
+
+    pool.record_check(&urls[1], Err("timeout".to_string()), 2);
+    assert_eq!(pool.select(2), None);
+
+    // recovered provers are used again
+    pool.record_check(&urls[0], Ok(false), 3);
+    assert_eq!(pool.select(1), Some(urls[0].clone()));
+
+    // updating the list keeps the state of existing provers
+    pool.set_urls(&urls[0..3]);
+    assert_eq!(pool.backends().len(), 3);
+    assert!(!pool.backends()[1].healthy);
+    assert_eq!(pool.select(1), Some(urls[0].clone()));
+    pool.set_urls(&urls[1..3]);
+    assert_eq!(pool.select(1), Some(urls[2].clone()));
+}
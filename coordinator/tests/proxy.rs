@@ -0,0 +1,121 @@
+use coordinator::proxy::*;
+use hyper::Uri;
+use serde_json::json;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+
+#[test]
+fn proxy_request() {
+    let req = ProxyRequest::parse(br#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#).unwrap();
+    assert!(!req.batch);
+    assert_eq!(req.methods, vec!["eth_chainId"]);
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&req.upstream_body()).unwrap(),
+        json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"})
+    );
+    let upstream = json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"});
+    assert_eq!(req.response(Ok(upstream.clone())), upstream);
+    assert_eq!(
+        req.response(Err("no rpc node available".to_string())),
+        json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32603, "message": "no rpc node available"}})
+    );
+
+    let req = ProxyRequest::parse(br#"{"jsonrpc":"2.0","id":2,"method":"admin_peers"}"#).unwrap();
+    assert!(req.forward.is_empty());
+    assert_eq!(
+        req.response(Ok(serde_json::Value::Null)),
+        json!({"jsonrpc": "2.0", "id": 2, "error": {"code": -32601, "message": "this method is not available"}})
+    );
+
+    assert_eq!(ProxyRequest::parse(b"{").unwrap_err().error.code, -32700);
+    assert_eq!(ProxyRequest::parse(b"[]").unwrap_err().error.code, -32600);
+}
+
+#[test]
+fn proxy_batch() {
+    let req = ProxyRequest::parse(
+        br#"[
+            {"jsonrpc":"2.0","id":1,"method":"eth_blockNumber"},
+            {"jsonrpc":"2.0","id":2,"method":"admin_peers"},
+            {"jsonrpc":"2.0","id":3},
+            {"jsonrpc":"2.0","id":4,"method":"eth_getBalance","params":["0x0","latest"]}
+        ]"#,
+    )
+    .unwrap();
+    assert!(req.batch);
+    assert_eq!(req.methods, vec!["eth_blockNumber", "eth_getBalance"]);
+    assert_eq!(req.rejected.len(), 2);
+
+    let upstream: serde_json::Value = serde_json::from_slice(&req.upstream_body()).expect("batch");
+    assert_eq!(upstream.as_array().unwrap().len(), 2);
+
+    let upstream = json!([
+        {"jsonrpc": "2.0", "id": 1, "result": "0x10"},
+        {"jsonrpc": "2.0", "id": 4, "result": "0x0"},
+    ]);
+    let resp = req.response(Ok(upstream));
+    let ids: Vec<u64> = resp
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![1, 4, 2, 3]);
+    assert_eq!(resp[3]["error"]["code"], -32600);
+
+    let resp = req.response(Err("no rpc node available".to_string()));
+    assert_eq!(resp.as_array().unwrap().len(), 4);
+    assert_eq!(resp[1]["id"], 4);
+    assert_eq!(resp[1]["error"]["code"], -32603);
+}
+
+#[test]
+fn proxy_node_order() {
+    let nodes: Vec<Uri> = (1..=5)
+        .map(|i| format!("http://10.0.0.{i}:8545").parse().unwrap())
+        .collect();
+    let client = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+
+    let order = node_order(&client, &nodes);
+    assert_eq!(order.len(), nodes.len());
+    assert_eq!(order, node_order(&client, &nodes));
+
+    // sticks to the same node if other nodes come and go
+    let mut reversed = nodes.clone();
+    reversed.reverse();
+    assert_eq!(node_order(&client, &reversed), order);
+    let others: Vec<Uri> = nodes
+        .iter()
+        .filter(|node| **node == order[0] || **node == order[4])
+        .cloned()
+        .collect();
+    assert_eq!(node_order(&client, &others)[0], order[0]);
+    // the next node if it is gone
+    let remaining: Vec<Uri> = nodes[..]
+        .iter()
+        .filter(|node| **node != order[0])
+        .cloned()
+        .collect();
+    assert_eq!(node_order(&client, &remaining), order[1..]);
+
+    // clients are spread over the nodes
+    let first: std::collections::HashSet<Uri> = (0..64)
+        .map(|i| node_order(&IpAddr::V4(Ipv4Addr::new(192, 168, 1, i)), &nodes)[0].clone())
+        .collect();
+    assert!(first.len() > 1);
+}
+
+#[test]
+fn proxy_request_idempotent() {
+    let req = ProxyRequest::parse(
+        br#"[{"jsonrpc":"2.0","id":1,"method":"eth_chainId"},{"jsonrpc":"2.0","id":2,"method":"eth_blockNumber"}]"#,
+    )
+    .unwrap();
+    assert!(req.idempotent());
+
+    let req = ProxyRequest::parse(
+        br#"[{"jsonrpc":"2.0","id":1,"method":"eth_chainId"},{"jsonrpc":"2.0","id":2,"method":"eth_sendRawTransaction","params":["0x"]}]"#,
+    )
+    .unwrap();
+    assert!(!req.idempotent());
+}
@@ -0,0 +1,55 @@
+use coordinator::utils::{drop_message_revert, is_execution_revert, DropMessageRevert};
+use zkevm_common::json_rpc::JsonRpcError;
+
+fn rpc_error(code: i32, message: &str) -> JsonRpcError {
+    JsonRpcError {
+        code,
+        message: message.to_string(),
+        data: None,
+    }
+}
+
+#[test]
+fn execution_reverts() {
+    assert!(is_execution_revert(&rpc_error(
+        3,
+        "execution reverted: DMH"
+    )));
+    assert!(is_execution_revert(&rpc_error(
+        -32000,
+        "execution reverted"
+    )));
+    assert!(is_execution_revert(&rpc_error(
+        -32000,
+        "gas required exceeds allowance (30000000)"
+    )));
+
+    // the node failed, not the message
+    assert!(!is_execution_revert(&rpc_error(-32000, "header not found")));
+    assert!(!is_execution_revert(&rpc_error(
+        -32601,
+        "the method does not exist"
+    )));
+}
+
+#[test]
+fn drop_message_reverts() {
+    assert_eq!(
+        drop_message_revert("execution reverted: DMTS"),
+        Some(DropMessageRevert::NotExpired)
+    );
+    assert_eq!(
+        drop_message_revert("L1:dropMessage: execution reverted: DMH"),
+        Some(DropMessageRevert::Settled)
+    );
+    assert_eq!(
+        drop_message_revert("execution reverted: DMVAL"),
+        Some(DropMessageRevert::Settled)
+    );
+
+    // only the revert reason counts
+    assert_eq!(drop_message_revert("execution reverted: DMHX"), None);
+    assert_eq!(drop_message_revert("execution reverted: EOA"), None);
+    assert_eq!(drop_message_revert("DMTS"), None);
+    assert_eq!(drop_message_revert("transaction reverted"), None);
+}
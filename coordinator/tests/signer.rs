@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use coordinator::signer::{load_signer, LocalSigner, TxSigner};
+use coordinator::utils::sign_eip1559;
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{Address, Bytes, Eip1559TransactionRequest};
+use ethers_core::utils::rlp::Rlp;
+use ethers_signers::{LocalWallet, Signer};
+use std::sync::Mutex;
+
+const KEY: &str = "2a871d0798f97d79848a013d4936a73bf4cc922c825d33c1cf7073dff6d409c6";
+
+/// Records the transactions instead of signing them.
+#[derive(Default)]
+struct StubSigner {
+    signed: Mutex<Vec<TypedTransaction>>,
+}
+
+#[async_trait]
+impl TxSigner for StubSigner {
+    fn address(&self) -> Address {
+        Address::repeat_byte(0xaa)
+    }
+
+    fn chain_id(&self) -> u64 {
+        99
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Bytes, String> {
+        self.signed.lock().unwrap().push(tx.clone());
+
+        Ok(Bytes::from(vec![0xaa]))
+    }
+}
+
+#[tokio::test]
+async fn signer_stub() {
+    let stub = StubSigner::default();
+    let tx = Eip1559TransactionRequest::new().nonce(7);
+
+    let raw = sign_eip1559(&stub, &tx).await.expect("sign");
+    assert_eq!(raw.as_ref(), [0xaa]);
+    assert_eq!(stub.signed.lock().unwrap()[0].nonce(), Some(&7.into()));
+}
+
+#[tokio::test]
+async fn signer_local() {
+    let client = hyper::Client::new();
+    let signer = load_signer(&client, KEY, None, 99).await.expect("load");
+    let wallet = KEY.parse::<LocalWallet>().unwrap();
+    assert_eq!(signer.address(), wallet.address());
+    assert_eq!(signer.chain_id(), 99);
+
+    let tx = Eip1559TransactionRequest::new().nonce(1).chain_id(99u64);
+    let raw = sign_eip1559(&*signer, &tx).await.expect("sign");
+    // EIP-2718 envelope
+    assert_eq!(raw[0], 2);
+    assert!(Rlp::new(&raw[1..]).is_list());
+
+    assert!(load_signer(&client, "not a key", None, 99).await.is_err());
+}
+
+#[tokio::test]
+async fn signer_keystore() {
+    let dir = std::env::temp_dir().join(format!("coordinator-keystore-{}", rand::random::<u64>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (wallet, _) =
+        LocalWallet::new_keystore(&dir, &mut rand::thread_rng(), "secret", Some("key")).unwrap();
+    let spec = format!("keystore:{}", dir.join("key").display());
+    let client = hyper::Client::new();
+
+    let signer = load_signer(&client, &spec, Some("secret"), 99)
+        .await
+        .expect("load");
+    assert_eq!(signer.address(), wallet.address());
+    assert_eq!(signer.chain_id(), 99);
+
+    assert!(load_signer(&client, &spec, Some("wrong"), 99)
+        .await
+        .is_err());
+    assert!(load_signer(&client, &spec, None, 99).await.is_err());
+
+    let local = LocalSigner(wallet.with_chain_id(99u64));
+    assert_eq!(local.address(), signer.address());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
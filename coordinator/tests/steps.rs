@@ -0,0 +1,37 @@
+use coordinator::error::CoordinatorError;
+use coordinator::steps::StepStatus;
+
+#[test]
+fn step_status_backoff() {
+    let mut status = StepStatus::default();
+    assert!(status.ready(0));
+
+    let err = Err(CoordinatorError::l1("eth_getLogs", "connection refused"));
+    status.record(&err, 10_000);
+    assert_eq!(status.errors, 1);
+    assert_eq!(status.retry_at, 11_000);
+    assert!(!status.ready(10_999));
+    assert!(status.ready(11_000));
+
+    status.record(&err, 11_000);
+    status.record(&err, 13_000);
+    assert_eq!(status.consecutive_errors, 3);
+    assert_eq!(status.retry_at, 17_000);
+
+    // the delay is capped
+    for _ in 0..32 {
+        status.record(&err, 0);
+    }
+    assert_eq!(status.backoff(), 300_000);
+
+    // a success resets the schedule but keeps the counts
+    status.record(&Ok(()), 20_000);
+    assert!(status.ready(20_000));
+    assert_eq!(status.consecutive_errors, 0);
+    assert_eq!(status.errors, 35);
+    assert_eq!(status.runs, 36);
+    assert_eq!(
+        status.last_error.map(|err| err.to_string()),
+        Some("l1: eth_getLogs: connection refused".to_string())
+    );
+}
@@ -0,0 +1,59 @@
+use coordinator::store::PersistentState;
+use coordinator::store::Store;
+use coordinator::structs::{MessageBeacon, MessageDirection, MessageRecord};
+use ethers_core::types::{Address, H256, U256, U64};
+
+#[tokio::test]
+async fn store_roundtrip() {
+    let path = std::env::temp_dir().join(format!("coordinator-store-{}", rand::random::<u64>()));
+    let store = Store::open(&path).expect("open");
+    assert!(store.load().expect("load").is_none());
+
+    let mut state = PersistentState {
+        genesis_hash: H256::repeat_byte(10),
+        l1_last_sync_block: U64::from(123),
+        l2_last_sync_block: U64::from(45),
+        l2_delivered_messages: vec![H256::repeat_byte(1)],
+        ..Default::default()
+    };
+    state.chain_state.safe_block_hash = H256::repeat_byte(2);
+    state.l1_message_queue.push_back(MessageBeacon {
+        id: H256::repeat_byte(3),
+        from: Address::repeat_byte(4),
+        to: Address::repeat_byte(5),
+        value: U256::from(6),
+        fee: U256::from(7),
+        deadline: U256::from(8),
+        nonce: U256::from(9),
+        calldata: vec![0xaa, 0xbb],
+    });
+    let record = MessageRecord::new(H256::repeat_byte(3), MessageDirection::L1ToL2, 10);
+    state.messages.insert(record.id, record);
+    state.dropped_messages.insert(H256::repeat_byte(11));
+    store.save(&state).await.expect("save");
+    let restored = store.load().expect("load").expect("some state");
+    assert_eq!(restored.genesis_hash, state.genesis_hash);
+    assert_eq!(restored.l1_last_sync_block, state.l1_last_sync_block);
+    assert_eq!(restored.l2_last_sync_block, state.l2_last_sync_block);
+    assert_eq!(
+        restored.chain_state.safe_block_hash,
+        state.chain_state.safe_block_hash
+    );
+    assert_eq!(restored.l2_delivered_messages, state.l2_delivered_messages);
+    assert_eq!(restored.l1_message_queue.len(), 1);
+    assert_eq!(restored.l1_message_queue[0].id, H256::repeat_byte(3));
+    assert_eq!(restored.l1_message_queue[0].calldata, vec![0xaa, 0xbb]);
+    assert!(restored.l2_message_queue.is_empty());
+    assert_eq!(restored.messages, state.messages);
+    assert_eq!(restored.dropped_messages, state.dropped_messages);
+
+    // a later save writes the changed fields
+    state.l1_last_sync_block = U64::from(124);
+    store.save(&state).await.expect("save");
+    let restored = store.load().expect("load").expect("some state");
+    assert_eq!(restored.l1_last_sync_block, U64::from(124));
+    assert_eq!(restored.messages, state.messages);
+
+    drop(store);
+    let _ = std::fs::remove_dir_all(&path);
+}
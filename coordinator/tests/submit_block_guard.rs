@@ -0,0 +1,116 @@
+mod common;
+
+use crate::common::get_shared_state;
+use crate::common::zkevm_abi;
+use ethers_core::abi::encode;
+use ethers_core::abi::Tokenizable;
+use ethers_core::types::Bytes;
+use ethers_core::types::H256;
+use ethers_core::types::U256;
+use ethers_core::types::U64;
+use ethers_core::utils::keccak256;
+use ethers_signers::Signer;
+
+#[tokio::test]
+async fn relayed_submit_block_reverts() {
+    let abi = zkevm_abi();
+    let shared_state = await_state!();
+    finalize_chain!(shared_state, true);
+
+    // a L2 > L1 message that calls `submitBlock` on the L1 bridge with a valid witness
+    let block_num: U64 = shared_state
+        .request_l2("eth_blockNumber", ())
+        .await
+        .expect("eth_blockNumber");
+    let witness = shared_state
+        .request_witness(&block_num)
+        .await
+        .expect("witness");
+    let from = shared_state.ro.l2_wallet.address();
+    let to = shared_state.config.lock().await.l1_bridge;
+    let value = U256::zero();
+    let fee = U256::zero();
+    let deadline = U256::from(0xffffffffffffffffu64);
+    let nonce: U256 = rand::random::<usize>().into();
+    let data = Bytes::from(
+        shared_state
+            .ro
+            .bridge_abi
+            .function("submitBlock")
+            .unwrap()
+            .encode_input(&[witness.input.into_token()])
+            .expect("calldata"),
+    );
+    let id: H256 = keccak256(encode(&[
+        from.into_token(),
+        to.into_token(),
+        value.into_token(),
+        fee.into_token(),
+        deadline.into_token(),
+        nonce.into_token(),
+        data.clone().into_token(),
+    ]))
+    .into();
+    let calldata = abi
+        .function("dispatchMessage")
+        .unwrap()
+        .encode_input(&[
+            to.into_token(),
+            fee.into_token(),
+            deadline.into_token(),
+            nonce.into_token(),
+            data.into_token(),
+        ])
+        .expect("calldata");
+    shared_state
+        .transaction_to_l2(
+            Some(shared_state.ro.l2_message_dispatcher_addr),
+            value,
+            calldata,
+            None,
+        )
+        .await
+        .expect("dispatchMessage");
+    shared_state.mine().await.expect("mine");
+
+    // finalize the block of the message without relaying it, see `finalize_chain`
+    let dummy_prover = shared_state.config.lock().await.dummy_prover;
+    shared_state.config.lock().await.dummy_prover = true;
+    loop {
+        let rw = shared_state.rw.lock().await;
+        if rw.chain_state.head_block_hash == rw.chain_state.finalized_block_hash {
+            break;
+        }
+        drop(rw);
+
+        sync!(shared_state);
+        shared_state.submit_blocks().await.expect("submit_blocks");
+        shared_state
+            .finalize_blocks()
+            .await
+            .expect("finalize_blocks");
+        sync!(shared_state);
+    }
+    shared_state.config.lock().await.dummy_prover = dummy_prover;
+    assert!(
+        shared_state
+            .rw
+            .lock()
+            .await
+            .l2_message_queue
+            .iter()
+            .any(|msg| msg.id == id),
+        "message should be queued"
+    );
+
+    // `deliverMessageWithProof` calls `submitBlock` from the bridge itself
+    let err = shared_state
+        .relay_to_l1()
+        .await
+        .expect_err("relayed submitBlock should revert");
+    assert!(err.to_string().contains("EOA"), "{}", err);
+
+    let mut rw = shared_state.rw.lock().await;
+    assert!(!rw.l1_delivered_messages.contains(&id));
+    rw.l2_message_queue.retain(|msg| msg.id != id);
+}
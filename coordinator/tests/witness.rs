@@ -0,0 +1,187 @@
+use coordinator::utils::encode_verifier_witness;
+use coordinator::witness::*;
+use ethers_core::types::transaction::eip2718::TypedTransaction;
+use ethers_core::types::{
+    Address, Block, Bloom, Bytes, Transaction, TransactionRequest, H256, H64, U256, U64,
+};
+use ethers_core::utils::keccak256;
+use ethers_signers::{LocalWallet, Signer};
+
+const CHAIN_ID: u64 = 99;
+
+fn signed_tx(wallet: &LocalWallet, nonce: u64, to: Option<Address>, input: Vec<u8>) -> Transaction {
+    let mut req = TransactionRequest::new()
+        .nonce(nonce)
+        .gas_price(1_000_000_000u64)
+        .gas(100_000u64)
+        .value(nonce * 7)
+        .data(input.clone())
+        .chain_id(CHAIN_ID);
+    if let Some(to) = to {
+        req = req.to(to);
+    }
+    let typed = TypedTransaction::Legacy(req);
+    let sig = wallet.sign_transaction_sync(&typed);
+
+    Transaction {
+        hash: H256::from(keccak256(typed.rlp_signed(&sig))),
+        nonce: nonce.into(),
+        from: wallet.address(),
+        to,
+        value: U256::from(nonce * 7),
+        gas_price: Some(1_000_000_000u64.into()),
+        gas: 100_000u64.into(),
+        input: Bytes::from(input),
+        v: sig.v.into(),
+        r: sig.r,
+        s: sig.s,
+        ..Default::default()
+    }
+}
+
+fn block() -> Block<Transaction> {
+    let alice: LocalWallet = "2a871d0798f97d79848a013d4936a73bf4cc922c825d33c1cf7073dff6d409c6"
+        .parse::<LocalWallet>()
+        .unwrap()
+        .with_chain_id(CHAIN_ID);
+    let bob = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(CHAIN_ID);
+
+    Block {
+        parent_hash: H256::repeat_byte(1),
+        author: Some(Address::repeat_byte(2)),
+        state_root: H256::repeat_byte(3),
+        logs_bloom: Some(Bloom::zero()),
+        number: Some(U64::from(42)),
+        gas_limit: 300_000u64.into(),
+        gas_used: 63_000u64.into(),
+        timestamp: 1_666_000_000u64.into(),
+        mix_hash: Some(H256::zero()),
+        nonce: Some(H64::zero()),
+        transactions: vec![
+            signed_tx(&alice, 0, Some(bob.address()), vec![]),
+            signed_tx(&bob, 1, None, vec![0x60, 0x00, 0x60, 0x00, 0xf3]),
+            signed_tx(&alice, 2, Some(Address::repeat_byte(3)), vec![0; 100]),
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn witness_v2_round_trip() {
+    let block = block();
+
+    for compress in [false, true] {
+        let witness = encode_witness_v2(&block, CHAIN_ID, compress).expect("encode");
+        assert_eq!(witness[0], WITNESS_V2);
+        assert_eq!(witness[1] & FLAG_COMPRESSED != 0, compress);
+
+        let decoded = decode_witness_v2(&witness, CHAIN_ID).expect("decode");
+        assert_eq!(decoded.transactions.len(), block.transactions.len());
+        for (tx, expected) in decoded.transactions.iter().zip(block.transactions.iter()) {
+            assert_eq!(tx.nonce, expected.nonce);
+            assert_eq!(Some(tx.gas_price), expected.gas_price);
+            assert_eq!(tx.gas, expected.gas);
+            assert_eq!(tx.to, expected.to);
+            assert_eq!(tx.value, expected.value);
+            assert_eq!(tx.input, expected.input);
+            assert_eq!(tx.from, expected.from);
+            assert_eq!((tx.r, tx.s), (expected.r, expected.s));
+            assert_eq!(tx.v, expected.v.as_u64());
+        }
+
+        // the header is the same as in v1
+        let v1 = encode_verifier_witness(&block, &[H256::zero(); 256], &CHAIN_ID).expect("v1");
+        assert_eq!(v1[0..decoded.header.len()], decoded.header[..]);
+        assert!(witness.len() < v1.len());
+    }
+}
+
+#[test]
+fn witness_v2_block_hash() {
+    // the Ethereum mainnet genesis block
+    let genesis: Block<Transaction> = Block {
+        uncles_hash: "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
+            .parse()
+            .unwrap(),
+        author: Some(Address::zero()),
+        state_root: "0xd7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544"
+            .parse()
+            .unwrap(),
+        transactions_root: "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            .parse()
+            .unwrap(),
+        receipts_root: "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            .parse()
+            .unwrap(),
+        logs_bloom: Some(Bloom::zero()),
+        difficulty: 17_179_869_184u64.into(),
+        number: Some(U64::zero()),
+        gas_limit: 5000u64.into(),
+        extra_data: "0x11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa"
+            .parse::<H256>()
+            .unwrap()
+            .as_bytes()
+            .to_vec()
+            .into(),
+        mix_hash: Some(H256::zero()),
+        nonce: Some(H64::from_low_u64_be(0x42)),
+        ..Default::default()
+    };
+    let expected: H256 = "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+        .parse()
+        .unwrap();
+
+    for compress in [false, true] {
+        let witness = encode_witness_v2(&genesis, CHAIN_ID, compress).expect("encode");
+        let decoded = decode_witness_v2(&witness, CHAIN_ID).expect("decode");
+        assert!(decoded.transactions.is_empty());
+        assert_eq!(decoded.block_hash(), expected);
+    }
+}
+
+#[test]
+fn witness_v2_compression() {
+    let block = block();
+    let plain = encode_witness_v2(&block, CHAIN_ID, false).unwrap();
+    let compressed = encode_witness_v2(&block, CHAIN_ID, true).unwrap();
+    assert!(compressed.len() < plain.len());
+
+    let data = [vec![0u8; 300], vec![1, 0, 2], vec![0; 255], vec![3]].concat();
+    let rle = rle_compress(&data);
+    assert_eq!(rle, vec![0, 255, 0, 45, 1, 0, 1, 2, 0, 255, 3]);
+    assert_eq!(rle_decompress(&rle).unwrap(), data);
+}
+
+#[test]
+fn witness_v2_invalid() {
+    let witness = encode_witness_v2(&block(), CHAIN_ID, true).unwrap();
+
+    assert!(decode_witness_v2(&[], CHAIN_ID).is_err());
+    assert!(decode_witness_v2(&[1, 0], CHAIN_ID).is_err());
+    // truncated signature of the last transaction
+    assert!(decode_witness_v2(&witness[0..witness.len() - 1], CHAIN_ID).is_err());
+    assert!(rle_decompress(&[1, 0]).is_err());
+    assert!(rle_decompress(&[0, 0]).is_err());
+
+    // a different chain id recovers a different sender
+    let decoded = decode_witness_v2(&witness, CHAIN_ID + 1).unwrap();
+    assert_ne!(decoded.transactions[0].from, block().transactions[0].from);
+}
+
+#[test]
+fn witness_v2_unsupported_transactions() {
+    let block = block();
+    assert!(encode_witness_v2(&block, CHAIN_ID + 1, false).is_err());
+
+    // typed transactions
+    let mut typed = block.clone();
+    typed.transactions[1].transaction_type = Some(U64::from(2));
+    assert!(encode_witness_v2(&typed, CHAIN_ID, false).is_err());
+    typed.transactions[1].transaction_type = Some(U64::zero());
+    assert!(encode_witness_v2(&typed, CHAIN_ID, false).is_ok());
+
+    // pre EIP-155 signatures
+    let mut legacy = block;
+    legacy.transactions[0].v = U64::from(27);
+    assert!(encode_witness_v2(&legacy, CHAIN_ID, false).is_err());
+}
@@ -0,0 +1,152 @@
+use coordinator::ws::*;
+use serde_json::json;
+
+#[test]
+fn ws_subscriptions() {
+    let mut subscriptions = Subscriptions::default();
+    assert_eq!(subscriptions.call("eth_chainId", &[]), None);
+
+    let heads = subscriptions
+        .call("eth_subscribe", &[json!("newHeads")])
+        .unwrap()
+        .unwrap();
+    let logs = subscriptions
+        .call(
+            "eth_subscribe",
+            &[
+                json!("logs"),
+                json!({
+                    "address": "0x0000000000000000000000000000000000010000",
+                    "topics": [null],
+                    "fromBlock": "0x0",
+                }),
+            ],
+        )
+        .unwrap()
+        .unwrap();
+    assert_ne!(heads, logs);
+    assert_eq!(
+        subscriptions.0[heads.as_str().unwrap()],
+        Subscription::NewHeads
+    );
+    // only the address and topics are used, the blocks are set by the coordinator
+    assert_eq!(
+        subscriptions.0[logs.as_str().unwrap()],
+        Subscription::Logs(
+            json!({
+                "address": "0x0000000000000000000000000000000000010000",
+                "topics": [null],
+            })
+            .as_object()
+            .unwrap()
+            .clone()
+        )
+    );
+
+    assert!(subscriptions
+        .call("eth_subscribe", &[json!("newPendingTransactions")])
+        .unwrap()
+        .is_err());
+    assert!(subscriptions
+        .call("eth_subscribe", &[json!("logs"), json!("0x0")])
+        .unwrap()
+        .is_err());
+
+    assert_eq!(
+        subscriptions.call("eth_unsubscribe", std::slice::from_ref(&heads)),
+        Some(Ok(json!(true)))
+    );
+    assert_eq!(
+        subscriptions.call("eth_unsubscribe", &[heads]),
+        Some(Ok(json!(false)))
+    );
+    assert!(subscriptions.call("eth_unsubscribe", &[]).unwrap().is_err());
+    assert_eq!(subscriptions.0.len(), 1);
+
+    while subscriptions.0.len() < 64 {
+        subscriptions
+            .call("eth_subscribe", &[json!("newHeads")])
+            .unwrap()
+            .unwrap();
+    }
+    assert!(subscriptions
+        .call("eth_subscribe", &[json!("newHeads")])
+        .unwrap()
+        .is_err());
+}
+
+#[test]
+fn ws_notifications() {
+    let mut subscriptions = Subscriptions::default();
+    let heads = subscriptions
+        .call("eth_subscribe", &[json!("newHeads")])
+        .unwrap()
+        .unwrap();
+    let logs = subscriptions
+        .call(
+            "eth_subscribe",
+            &[
+                json!("logs"),
+                json!({
+                    "address": ["0x0000000000000000000000000000000000010000"],
+                    "topics": [null, "0x01"],
+                }),
+            ],
+        )
+        .unwrap()
+        .unwrap();
+
+    let log = |address: &str, topics: &[&str]| json!({ "address": address, "topics": topics });
+    let head = NewHead {
+        header: json!({ "number": "0x1" }),
+        logs: vec![
+            log(
+                "0x0000000000000000000000000000000000010000",
+                &["0x00", "0x01"],
+            ),
+            log(
+                "0x0000000000000000000000000000000000010000",
+                &["0x00", "0x02"],
+            ),
+            log("0x0000000000000000000000000000000000010000", &["0x00"]),
+            log(
+                "0x0000000000000000000000000000000000010001",
+                &["0x00", "0x01"],
+            ),
+        ],
+    };
+    let notifications = subscriptions.notifications(&head);
+    assert_eq!(notifications.len(), 2);
+    for notification in notifications {
+        let params = &notification["params"];
+        if params["subscription"] == heads {
+            assert_eq!(params["result"], head.header);
+        } else {
+            assert_eq!(params["subscription"], logs);
+            assert_eq!(params["result"], head.logs[0]);
+        }
+    }
+
+    let filter = |filter: serde_json::Value| filter.as_object().unwrap().clone();
+    let log = log("0x000000000000000000000000000000000001000A", &["0xAB"]);
+    assert!(log_matches(&filter(json!({})), &log));
+    assert!(log_matches(
+        &filter(json!({ "address": "0x000000000000000000000000000000000001000a" })),
+        &log
+    ));
+    assert!(log_matches(
+        &filter(json!({ "topics": [["0xab", "0xcd"]] })),
+        &log
+    ));
+    assert!(log_matches(&filter(json!({ "topics": [[]] })), &log));
+    assert!(!log_matches(&filter(json!({ "topics": ["0xcd"] })), &log));
+    // like geth, the log needs at least as many topics as the filter
+    assert!(!log_matches(
+        &filter(json!({ "topics": [null, null] })),
+        &log
+    ));
+    assert!(!log_matches(
+        &filter(json!({ "topics": [null, "0xab"] })),
+        &log
+    ));
+}
@@ -8,6 +8,8 @@ use zkevm_common::prover::*;
 /// - PROVERD_BLOCK_NUM - the block number to generate the proof for
 /// - PROVERD_RPC_URL - a geth http rpc that supports the debug namespace
 /// - PROVERD_PARAMS_PATH - a path to a file generated with the gen_params tool
+/// Optional environment variables:
+/// - PROVERD_FIXED_RNG - use a fixed seed for the blinding factors if `true`
 #[tokio::main]
 async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
@@ -25,7 +27,12 @@ async fn main() {
         .parse()
         .expect("Cannot parse PROVERD_PARAMS_PATH env var");
 
-    let state = SharedState::new(String::new(), None);
+    let randomness = match var("PROVERD_FIXED_RNG").as_deref() {
+        Ok("true") | Ok("1") => ProofRandomness::Fixed,
+        _ => ProofRandomness::Os,
+    };
+
+    let state = SharedState::new(String::new(), None).with_randomness(randomness);
     let request = ProofRequestOptions {
         circuit: "super".to_string(),
         block: block_num,
@@ -4,6 +4,7 @@ use env_logger::Env;
 use prover::server::serve;
 use prover::shared_state::SharedState;
 use prover::VERSION;
+use zkevm_common::prover::ProofRandomness;
 
 #[derive(Parser, Debug)]
 #[clap(version = VERSION, about)]
@@ -16,6 +17,15 @@ pub(crate) struct ProverdConfig {
     #[clap(long, env = "PROVERD_LOOKUP")]
     /// A `HOSTNAME:PORT` conformant string that will be used for DNS service discovery of other nodes.
     lookup: Option<String>,
+    #[clap(long, env = "PROVERD_FIXED_RNG", default_value_t = false)]
+    /// Use a fixed seed instead of OS randomness for the blinding factors of proofs
+    /// if the request doesn't specify it. Proofs are reproducible but not zero-knowledge,
+    /// only meant for testing.
+    fixed_rng: bool,
+    #[clap(long, env = "PROVERD_TRACE_DIR")]
+    /// The directory for requests with the `trace_dir` witness source.
+    /// Contains one folder per block number with the output of `scripts/get_block_fixtures.sh`.
+    trace_dir: Option<String>,
 }
 
 #[tokio::main]
@@ -23,7 +33,13 @@ async fn main() {
     let config = ProverdConfig::parse();
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
-    let shared_state = SharedState::new(SharedState::random_worker_id(), config.lookup);
+    let randomness = match config.fixed_rng {
+        true => ProofRandomness::Fixed,
+        false => ProofRandomness::Os,
+    };
+    let shared_state = SharedState::new(SharedState::random_worker_id(), config.lookup)
+        .with_randomness(randomness)
+        .with_trace_dir(config.trace_dir);
     {
         // start the http server
         let h1 = serve(&shared_state, &config.bind);
@@ -1,19 +1,22 @@
+use crate::witness_provider::GethDebugProvider;
+use crate::witness_provider::WitnessProvider;
 use crate::Fr;
-use bus_mapping::circuit_input_builder::BuilderClient;
 use bus_mapping::circuit_input_builder::CircuitsParams;
 use bus_mapping::mock::BlockData;
-use bus_mapping::rpc::GethClient;
 use eth_types::geth_types;
 use eth_types::geth_types::GethData;
 use eth_types::Address;
 use eth_types::ToBigEndian;
 use eth_types::Word;
 use eth_types::H256;
-use ethers_providers::Http;
-use std::str::FromStr;
 use zkevm_circuits::evm_circuit;
 use zkevm_circuits::pi_circuit::PublicData;
 use zkevm_common::prover::CircuitConfig;
+use zkevm_common::prover::ProverError;
+
+/// The fixed witness randomness used in the PublicInput contract and SuperCircuit.
+/// Unlike the blinding factors of the proof, this must match the on-chain verifier.
+pub const WITNESS_RANDOMNESS: u64 = 0x100;
 
 /// Wrapper struct for circuit witness data.
 pub struct CircuitWitness {
@@ -65,15 +68,26 @@ impl CircuitWitness {
     }
 
     /// Gathers debug trace(s) from `rpc_url` for block `block_num`.
+    /// If `block_hash` is given, fails if the canonical block `block_num` has a different hash.
     /// Expects a go-ethereum node with debug & archive capabilities on `rpc_url`.
     pub async fn from_rpc(
         block_num: &u64,
+        block_hash: Option<&H256>,
         rpc_url: &str,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let url = Http::from_str(rpc_url)?;
-        let geth_client = GethClient::new(url);
-        // TODO: add support for `eth_getHeaderByNumber`
-        let block = geth_client.get_block_by_number((*block_num).into()).await?;
+        Self::from_provider(&GethDebugProvider::new(rpc_url), block_num, block_hash).await
+    }
+
+    /// Gathers the block data and traces for block `block_num` from `provider`,
+    /// by `block_hash` if given. Fails if the block has a different hash,
+    /// not all providers can fetch by hash.
+    pub async fn from_provider(
+        provider: &dyn WitnessProvider,
+        block_num: &u64,
+        block_hash: Option<&H256>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let block = provider.get_block(*block_num, block_hash).await?;
+        check_block_hash(&block, block_hash)?;
         let circuit_config =
             crate::match_circuit_params!(block.gas_used.as_usize(), CIRCUIT_CONFIG, {
                 return Err(format!(
@@ -92,8 +106,11 @@ impl CircuitWitness {
             max_evm_rows: circuit_config.pad_to,
             max_keccak_rows: circuit_config.keccak_padding,
         };
-        let builder = BuilderClient::new(geth_client, circuit_params).await?;
-        let (builder, eth_block) = builder.gen_inputs(*block_num).await?;
+        let (builder, eth_block) = provider
+            .gen_inputs(*block_num, block_hash, circuit_params)
+            .await?;
+        // the block may have been reorged in the meantime
+        check_block_hash(&eth_block, block_hash)?;
 
         Ok(Self {
             circuit_config,
@@ -103,14 +120,29 @@ impl CircuitWitness {
         })
     }
 
-    pub fn evm_witness(&self) -> zkevm_circuits::witness::Block<Fr> {
+    pub fn evm_witness(&self) -> Result<zkevm_circuits::witness::Block<Fr>, ProverError> {
         let mut block =
-            evm_circuit::witness::block_convert(&self.block, &self.code_db).expect("block_convert");
+            evm_circuit::witness::block_convert(&self.block, &self.code_db).map_err(|e| {
+                ProverError::Witness {
+                    cause: format!("block_convert: {e:?}"),
+                }
+            })?;
         block.exp_circuit_pad_to = self.circuit_config.pad_to;
-        // fixed randomness used in PublicInput contract and SuperCircuit
-        block.randomness = Fr::from(0x100);
+        block.randomness = Fr::from(WITNESS_RANDOMNESS);
+
+        Ok(block)
+    }
 
-        block
+    pub fn block_hash(&self) -> H256 {
+        self.eth_block.hash.unwrap_or_default()
+    }
+
+    pub fn prev_state_root(&self) -> H256 {
+        H256::from(self.block.prev_state_root.to_be_bytes())
+    }
+
+    pub fn state_root(&self) -> H256 {
+        self.eth_block.state_root
     }
 
     pub fn gas_used(&self) -> u64 {
@@ -140,7 +172,7 @@ impl CircuitWitness {
             gas_limit: eth_block.gas_limit,
             base_fee: eth_block.base_fee_per_gas.unwrap_or_default(),
         };
-        let prev_state_root = H256::from(self.block.prev_state_root.to_be_bytes());
+        let prev_state_root = self.prev_state_root();
 
         PublicData {
             chain_id,
@@ -153,3 +185,17 @@ impl CircuitWitness {
         }
     }
 }
+
+/// Returns an error if `expected` is given and `block` has a different hash.
+fn check_block_hash(
+    block: &eth_types::Block<eth_types::Transaction>,
+    expected: Option<&H256>,
+) -> Result<(), String> {
+    match expected {
+        Some(expected) if block.hash.as_ref() != Some(expected) => Err(format!(
+            "block {:?} has hash {:?} but expected {:?}, reorged?",
+            block.number, block.hash, expected
+        )),
+        _ => Ok(()),
+    }
+}
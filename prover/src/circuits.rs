@@ -11,6 +11,7 @@ use zkevm_circuits::state_circuit::StateCircuit;
 use zkevm_circuits::super_circuit::SuperCircuit;
 use zkevm_circuits::tx_circuit::TxCircuit;
 use zkevm_circuits::util::SubCircuit;
+use zkevm_common::prover::ProverError;
 
 /// Returns a instance of the `SuperCircuit`.
 pub fn gen_super_circuit<
@@ -22,8 +23,8 @@ pub fn gen_super_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<SuperCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<SuperCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     let circuit = SuperCircuit::new_from_block(&block);
     Ok(circuit)
 }
@@ -38,8 +39,8 @@ pub fn gen_pi_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<PiCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<PiCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     let circuit = PiCircuit::new_from_block(&block);
 
     Ok(circuit)
@@ -55,8 +56,8 @@ pub fn gen_evm_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<EvmCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<EvmCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     Ok(EvmCircuit::new_from_block(&block))
 }
 
@@ -70,8 +71,8 @@ pub fn gen_state_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<StateCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<StateCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     Ok(StateCircuit::new_from_block(&block))
 }
 
@@ -85,8 +86,8 @@ pub fn gen_tx_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<TxCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<TxCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     Ok(TxCircuit::new_from_block(&block))
 }
 
@@ -100,8 +101,8 @@ pub fn gen_bytecode_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<BytecodeCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<BytecodeCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     Ok(BytecodeCircuit::new_from_block(&block))
 }
 
@@ -115,8 +116,8 @@ pub fn gen_copy_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<CopyCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<CopyCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     Ok(CopyCircuit::new_from_block(&block))
 }
 
@@ -130,8 +131,8 @@ pub fn gen_exp_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<ExpCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<ExpCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     Ok(ExpCircuit::new_from_block(&block))
 }
 
@@ -145,7 +146,7 @@ pub fn gen_keccak_circuit<
 >(
     witness: &CircuitWitness,
     mut _rng: RNG,
-) -> Result<KeccakCircuit<Fr>, String> {
-    let block = witness.evm_witness();
+) -> Result<KeccakCircuit<Fr>, ProverError> {
+    let block = witness.evm_witness()?;
     Ok(KeccakCircuit::new_from_block(&block))
 }
@@ -18,3 +18,4 @@ pub mod circuits;
 pub mod server;
 pub mod shared_state;
 pub mod utils;
+pub mod witness_provider;
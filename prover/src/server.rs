@@ -1,5 +1,6 @@
 use crate::circuit_witness::CircuitWitness;
 use crate::shared_state::SharedState;
+use crate::witness_provider::witness_provider;
 use hyper::body::Buf;
 use hyper::body::HttpBody;
 use hyper::header::HeaderValue;
@@ -111,6 +112,7 @@ async fn handle_request(
                         // parser error
                         code: -32700,
                         message: err.to_string(),
+                        data: None,
                     },
                 })
                 .unwrap();
@@ -120,20 +122,14 @@ async fn handle_request(
             }
 
             let json_req = json_req.unwrap();
-            let result: Result<serde_json::Value, String> =
+            let result: Result<serde_json::Value, JsonRpcError> =
                 handle_method(json_req.method.as_str(), &json_req.params, &shared_state).await;
             let payload = match result {
-                Err(err) => {
-                    serde_json::to_vec(&JsonRpcResponseError {
-                        jsonrpc: "2.0".to_string(),
-                        id: json_req.id,
-                        error: JsonRpcError {
-                            // internal server error
-                            code: -32000,
-                            message: err,
-                        },
-                    })
-                }
+                Err(err) => serde_json::to_vec(&JsonRpcResponseError {
+                    jsonrpc: "2.0".to_string(),
+                    id: json_req.id,
+                    error: err,
+                }),
                 Ok(val) => serde_json::to_vec(&JsonRpcResponse {
                     jsonrpc: "2.0".to_string(),
                     id: json_req.id,
@@ -165,7 +161,7 @@ async fn handle_method(
     method: &str,
     params: &[serde_json::Value],
     shared_state: &SharedState,
-) -> Result<serde_json::Value, String> {
+) -> Result<serde_json::Value, JsonRpcError> {
     match method {
         // enqueues a task for computating proof for any given block
         "proof" => {
@@ -173,11 +169,12 @@ async fn handle_method(
             let options: ProofRequestOptions =
                 serde_json::from_value(options.to_owned()).map_err(|e| e.to_string())?;
 
-            shared_state
-                .get_or_enqueue(&options)
-                .await
-                .map(|result| serde_json::to_value(result?).map_err(|e| e.to_string()))
-                .unwrap_or_else(|| Ok(serde_json::Value::Null))
+            match shared_state.get_or_enqueue(&options).await {
+                Some(Ok(proofs)) => Ok(serde_json::to_value(proofs).unwrap()),
+                // failed tasks carry the tagged `ProverError` in `error.data`
+                Some(Err(err)) => Err(err.into()),
+                None => Ok(serde_json::Value::Null),
+            }
         }
 
         "circuit_config" => {
@@ -185,16 +182,26 @@ async fn handle_method(
             let options: ProofRequestOptions =
                 serde_json::from_value(options.to_owned()).map_err(|e| e.to_string())?;
 
-            let witness = CircuitWitness::from_rpc(&options.block, &options.rpc)
-                .await
-                .map_err(|e| e.to_string())?;
+            let provider = witness_provider(
+                &options.witness_source,
+                &options.rpc,
+                shared_state.ro.trace_dir.as_ref(),
+            )?;
+            let witness = CircuitWitness::from_provider(
+                provider.as_ref(),
+                &options.block,
+                options.block_hash.as_ref(),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
 
             let circuit_config =
                 crate::match_circuit_params!(witness.gas_used(), CIRCUIT_CONFIG, {
                     return Err(format!(
                         "No circuit parameters found for block with gas={}",
                         witness.gas_used()
-                    ));
+                    )
+                    .into());
                 });
 
             Ok(serde_json::to_value(circuit_config).unwrap())
@@ -278,6 +285,6 @@ async fn handle_method(
                 .retain(|e| e.result.is_none());
             Ok(serde_json::Value::Bool(true))
         }
-        _ => Err("this method is not available".to_string()),
+        _ => Err("this method is not available".into()),
     }
 }
@@ -4,6 +4,10 @@ use crate::utils::collect_instance;
 use crate::utils::fixed_rng;
 use crate::utils::gen_num_instance;
 use crate::utils::gen_proof;
+use crate::utils::mock_diagnostics;
+use crate::utils::proof_rng;
+use crate::utils::GenProofFailure;
+use crate::witness_provider::witness_provider;
 use crate::Fr;
 use crate::G1Affine;
 use crate::ProverKey;
@@ -43,40 +47,52 @@ fn get_param_path(path: &String, k: usize) -> PathBuf {
     }
 }
 
-fn get_or_gen_param(task_options: &ProofRequestOptions, k: usize) -> (Arc<ProverParams>, String) {
+fn get_or_gen_param(
+    task_options: &ProofRequestOptions,
+    k: usize,
+) -> Result<(Arc<ProverParams>, String), ProverError> {
     match &task_options.param {
         Some(v) => {
             let path = get_param_path(v, k);
-            let file = File::open(&path).expect("couldn't open params");
+            let file = File::open(&path).map_err(|e| ProverError::Params {
+                cause: format!("couldn't open {}: {e}", path.display()),
+            })?;
             let params = Arc::new(
-                ProverParams::read(&mut std::io::BufReader::new(file))
-                    .expect("Failed to read params"),
+                ProverParams::read(&mut std::io::BufReader::new(file)).map_err(|e| {
+                    ProverError::Params {
+                        cause: format!("failed to read {}: {e}", path.display()),
+                    }
+                })?,
             );
 
-            (params, path.to_str().unwrap().into())
+            Ok((params, path.display().to_string()))
         }
         None => {
             let param = ProverParams::setup(k as u32, fixed_rng());
             if std::env::var("PROVERD_DUMP").is_ok() {
-                param
-                    .write_custom(
-                        &mut File::create(format!("params-{k}")).unwrap(),
-                        SerdeFormat::RawBytesUnchecked,
-                    )
-                    .unwrap();
+                dump_file(&format!("params-{k}"), |file| {
+                    param.write_custom(file, SerdeFormat::RawBytesUnchecked)
+                });
             }
             let param = Arc::new(param);
-            (param, format!("{k}"))
+            Ok((param, format!("{k}")))
         }
     }
 }
 
+/// Writes a debug artifact. Failures are only logged.
+fn dump_file<F: FnOnce(&mut File) -> std::io::Result<()>>(path: &str, write: F) {
+    if let Err(err) = File::create(path).and_then(|mut file| write(&mut file)) {
+        log::error!("dump_file: {} {}", path, err);
+    }
+}
+
 async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
     shared_state: &SharedState,
     task_options: &ProofRequestOptions,
     circuit_config: CircuitConfig,
     circuit: C,
-) -> Result<(CircuitConfig, ProofResult, ProofResult), String> {
+) -> Result<(CircuitConfig, ProofResult, ProofResult), ProverError> {
     log::info!("Using circuit parameters: {:#?}", circuit_config);
 
     let mut circuit_proof = ProofResult {
@@ -93,6 +109,9 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
         ),
         ..Default::default()
     };
+    let randomness = task_options
+        .randomness
+        .unwrap_or(shared_state.ro.randomness);
 
     if task_options.mock {
         // only run the mock prover
@@ -100,12 +119,32 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
         circuit_proof.k = circuit_config.min_k as u8;
         circuit_proof.instance = collect_instance(&circuit.instance());
         let prover = MockProver::run(circuit_config.min_k as u32, &circuit, circuit.instance())
-            .expect("MockProver::run");
-        prover.verify_par().expect("MockProver::verify_par");
+            .map_err(|e| ProverError::Proving {
+                cause: format!("MockProver::run: {e:?}"),
+                diagnostics: None,
+            })?;
+        if let Err(failures) = prover.verify_par() {
+            return Err(prover_error(
+                task_options,
+                GenProofFailure {
+                    error: ProverError::Proving {
+                        cause: "MockProver::verify_par".to_string(),
+                        diagnostics: Some(mock_diagnostics(&failures)),
+                    },
+                    report: Some(format!("{failures:#?}")),
+                },
+            ));
+        }
         circuit_proof.aux.mock = Instant::now().duration_since(time_started).as_millis() as u32;
     } else {
-        let (param, param_path) = get_or_gen_param(task_options, circuit_config.min_k);
+        let (param, param_path) = get_or_gen_param(task_options, circuit_config.min_k)?;
         circuit_proof.k = param.k() as u8;
+        circuit_proof.rng = Some(randomness);
+        let (circuit_rng, circuit_seed) = proof_rng(randomness);
+        circuit_proof.randomness = circuit_seed
+            .map(|seed| seed.to_vec())
+            .unwrap_or_default()
+            .into();
         // generate and cache the prover key
         let pk = {
             let cache_key = format!(
@@ -114,8 +153,7 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
             );
             shared_state
                 .gen_pk(&cache_key, &param, &circuit, &mut circuit_proof.aux)
-                .await
-                .map_err(|e| e.to_string())?
+                .await?
         };
 
         let circuit_instance = circuit.instance();
@@ -127,21 +165,19 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
                 &pk,
                 circuit,
                 circuit_instance.clone(),
-                fixed_rng(),
+                circuit_rng,
                 task_options.mock_feedback,
                 task_options.verify_proof,
                 &mut circuit_proof.aux,
-            );
+            )
+            .map_err(|e| prover_error(task_options, e))?;
             circuit_proof.proof = proof.clone().into();
 
             if std::env::var("PROVERD_DUMP").is_ok() {
-                File::create(format!(
-                    "proof-{}-{:?}",
-                    task_options.circuit, &circuit_config
-                ))
-                .unwrap()
-                .write_all(&proof)
-                .unwrap();
+                dump_file(
+                    &format!("proof-{}-{:?}", task_options.circuit, &circuit_config),
+                    |file| file.write_all(&proof),
+                );
             }
 
             // aggregate the circuit proof
@@ -158,8 +194,14 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
             };
 
             let (agg_params, agg_param_path) =
-                get_or_gen_param(task_options, circuit_config.min_k_aggregation);
+                get_or_gen_param(task_options, circuit_config.min_k_aggregation)?;
             aggregation_proof.k = agg_params.k() as u8;
+            aggregation_proof.rng = Some(randomness);
+            let (aggregation_rng, aggregation_seed) = proof_rng(randomness);
+            aggregation_proof.randomness = aggregation_seed
+                .map(|seed| seed.to_vec())
+                .unwrap_or_default()
+                .into();
 
             let agg_circuit = {
                 let time_started = Instant::now();
@@ -169,7 +211,9 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
                     Value::known(&circuit_instance),
                     Value::known(&proof),
                 )
-                .expect("RootCircuit::new");
+                .map_err(|e| ProverError::Aggregation {
+                    cause: format!("RootCircuit::new: {e:?}"),
+                })?;
                 aggregation_proof.aux.circuit =
                     Instant::now().duration_since(time_started).as_millis() as u32;
                 v
@@ -187,8 +231,7 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
                         &agg_circuit,
                         &mut aggregation_proof.aux,
                     )
-                    .await
-                    .map_err(|e| e.to_string())?
+                    .await?
             };
             let agg_instance = agg_circuit.instance().to_vec();
             aggregation_proof.instance = collect_instance(&agg_instance);
@@ -203,19 +246,17 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
                 &agg_pk,
                 agg_circuit,
                 agg_instance,
-                fixed_rng(),
+                aggregation_rng,
                 task_options.mock_feedback,
                 task_options.verify_proof,
                 &mut aggregation_proof.aux,
-            );
+            )
+            .map_err(|e| prover_error(task_options, e))?;
             if std::env::var("PROVERD_DUMP").is_ok() {
-                File::create(format!(
-                    "proof-{}-agg--{:?}",
-                    task_options.circuit, &circuit_config
-                ))
-                .unwrap()
-                .write_all(&proof)
-                .unwrap();
+                dump_file(
+                    &format!("proof-{}-agg--{:?}", task_options.circuit, &circuit_config),
+                    |file| file.write_all(&proof),
+                );
             }
             aggregation_proof.proof = proof.into();
         } else {
@@ -230,11 +271,12 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
                 &pk,
                 circuit,
                 circuit_instance.clone(),
-                fixed_rng(),
+                circuit_rng,
                 task_options.mock_feedback,
                 task_options.verify_proof,
                 &mut circuit_proof.aux,
-            );
+            )
+            .map_err(|e| prover_error(task_options, e))?;
             circuit_proof.proof = proof.into();
         }
     }
@@ -242,6 +284,51 @@ async fn compute_proof<C: Circuit<Fr> + Clone + SubCircuit<Fr>>(
     Ok((circuit_config, circuit_proof, aggregation_proof))
 }
 
+/// Labels the `MockProver` diagnostics of `failure`, if any,
+/// and writes the full report into the directory `PROVERD_DUMP`, if set.
+fn prover_error(task_options: &ProofRequestOptions, failure: GenProofFailure) -> ProverError {
+    let mut error = failure.error;
+    if let ProverError::Proving {
+        diagnostics: Some(diagnostics),
+        ..
+    }
+    | ProverError::Verification {
+        diagnostics: Some(diagnostics),
+        ..
+    } = &mut error
+    {
+        diagnostics.label = task_options.circuit.clone();
+        diagnostics.report = failure
+            .report
+            .and_then(|report| write_mock_report(task_options, &report));
+    }
+
+    error
+}
+
+/// Writes the full `MockProver` report into the directory `PROVERD_DUMP`, if set.
+/// Returns the path of the report.
+fn write_mock_report(task_options: &ProofRequestOptions, report: &str) -> Option<String> {
+    let dir = std::env::var("PROVERD_DUMP")
+        .ok()
+        .filter(|dir| !dir.is_empty())?;
+
+    let path = Path::new(&dir).join(format!(
+        "mock-report-{}-{}.txt",
+        task_options.circuit, task_options.block
+    ));
+    match std::fs::create_dir_all(&dir)
+        .and_then(|_| File::create(&path))
+        .and_then(|mut file| file.write_all(report.as_bytes()))
+    {
+        Ok(_) => Some(path.display().to_string()),
+        Err(err) => {
+            log::error!("write_mock_report: {} {}", path.display(), err);
+            None
+        }
+    }
+}
+
 macro_rules! compute_proof_wrapper {
     ($shared_state:expr, $task_options:expr, $witness:expr, $CIRCUIT:ident) => {{
         let timing = Instant::now();
@@ -267,6 +354,10 @@ pub struct RoState {
     // a `HOSTNAME:PORT` conformant string that will be used for DNS service discovery of other
     // nodes
     pub node_lookup: Option<String>,
+    // the source of the blinding factors if the request doesn't specify one
+    pub randomness: ProofRandomness,
+    // the directory for `WitnessSource::TraceDir`
+    pub trace_dir: Option<String>,
 }
 
 pub struct RwState {
@@ -290,6 +381,8 @@ impl SharedState {
             ro: RoState {
                 node_id,
                 node_lookup,
+                randomness: ProofRandomness::Os,
+                trace_dir: None,
             },
             rw: Arc::new(Mutex::new(RwState {
                 tasks: Vec::new(),
@@ -300,6 +393,18 @@ impl SharedState {
         }
     }
 
+    /// Sets the default `ProofRandomness` for requests that don't specify one.
+    pub fn with_randomness(mut self, randomness: ProofRandomness) -> Self {
+        self.ro.randomness = randomness;
+        self
+    }
+
+    /// Sets the directory for requests with `WitnessSource::TraceDir`.
+    pub fn with_trace_dir(mut self, trace_dir: Option<String>) -> Self {
+        self.ro.trace_dir = trace_dir;
+        self
+    }
+
     /// Will return the result or error of the task if it's completed.
     /// Otherwise enqueues the task and returns `None`.
     /// `retry_if_error` enqueues the task again if it returned with an error
@@ -307,7 +412,7 @@ impl SharedState {
     pub async fn get_or_enqueue(
         &self,
         options: &ProofRequestOptions,
-    ) -> Option<Result<Proofs, String>> {
+    ) -> Option<Result<Proofs, ProverError>> {
         let mut rw = self.rw.lock().await;
 
         // task already pending or completed?
@@ -414,15 +519,26 @@ impl SharedState {
         // instead.
 
         // spawn a task to catch panics
-        let task_result: Result<Result<Proofs, String>, tokio::task::JoinError> = {
+        let task_result: Result<Result<Proofs, ProverError>, tokio::task::JoinError> = {
             let task_options_copy = task_options.clone();
             let self_copy = self.clone();
 
             tokio::spawn(async move {
-                let witness =
-                    CircuitWitness::from_rpc(&task_options_copy.block, &task_options_copy.rpc)
-                        .await
-                        .map_err(|e| e.to_string())?;
+                let provider = witness_provider(
+                    &task_options_copy.witness_source,
+                    &task_options_copy.rpc,
+                    self_copy.ro.trace_dir.as_ref(),
+                )
+                .map_err(|cause| ProverError::Witness { cause })?;
+                let witness = CircuitWitness::from_provider(
+                    provider.as_ref(),
+                    &task_options_copy.block,
+                    task_options_copy.block_hash.as_ref(),
+                )
+                .await
+                .map_err(|e| ProverError::Witness {
+                    cause: e.to_string(),
+                })?;
 
                 let (config, circuit_proof, aggregation_proof) = crate::match_circuit_params!(
                     witness.gas_used(),
@@ -494,14 +610,20 @@ impl SharedState {
                                 &witness,
                                 gen_keccak_circuit
                             ),
-                            _ => panic!("unknown circuit"),
+                            name => {
+                                return Err(ProverError::Circuit {
+                                    cause: format!("unknown circuit: {name}"),
+                                });
+                            }
                         }
                     },
                     {
-                        return Err(format!(
-                            "No circuit parameters found for block with gas used={}",
-                            witness.gas_used()
-                        ));
+                        return Err(ProverError::Circuit {
+                            cause: format!(
+                                "No circuit parameters found for block with gas used={}",
+                                witness.gas_used()
+                            ),
+                        });
                     }
                 );
 
@@ -510,6 +632,9 @@ impl SharedState {
                     circuit: circuit_proof,
                     aggregation: aggregation_proof,
                     gas: witness.gas_used(),
+                    block_hash: witness.block_hash(),
+                    prev_state_root: witness.prev_state_root(),
+                    state_root: witness.state_root(),
                 };
 
                 Ok(res)
@@ -517,22 +642,25 @@ impl SharedState {
             .await
         };
 
-        // convert the JoinError to string - if applicable
-        let task_result: Result<Proofs, String> = match task_result {
-            Err(err) => match err.is_panic() {
-                true => {
-                    let panic = err.into_panic();
-
-                    if let Some(msg) = panic.downcast_ref::<&str>() {
-                        Err(msg.to_string())
-                    } else if let Some(msg) = panic.downcast_ref::<String>() {
-                        Err(msg.to_string())
-                    } else {
-                        Err("unknown panic".to_string())
+        // convert the JoinError - if applicable
+        let task_result: Result<Proofs, ProverError> = match task_result {
+            Err(err) => {
+                let cause = match err.is_panic() {
+                    true => {
+                        let panic = err.into_panic();
+
+                        if let Some(msg) = panic.downcast_ref::<&str>() {
+                            msg.to_string()
+                        } else if let Some(msg) = panic.downcast_ref::<String>() {
+                            msg.to_string()
+                        } else {
+                            "unknown panic".to_string()
+                        }
                     }
-                }
-                false => Err(err.to_string()),
-            },
+                    false => err.to_string(),
+                };
+                Err(ProverError::Internal { cause })
+            }
             Ok(val) => val,
         };
 
@@ -621,7 +749,7 @@ impl SharedState {
         param: &Arc<ProverParams>,
         circuit: &C,
         aux: &mut ProofResultInstrumentation,
-    ) -> Result<Arc<ProverKey>, Box<dyn std::error::Error>> {
+    ) -> Result<Arc<ProverKey>, ProverError> {
         let mut rw = self.rw.lock().await;
         if !rw.pk_cache.contains_key(cache_key) {
             // drop, potentially long running
@@ -629,22 +757,25 @@ impl SharedState {
 
             let vk = {
                 let time_started = Instant::now();
-                let vk = keygen_vk(param.as_ref(), circuit)?;
+                let vk = keygen_vk(param.as_ref(), circuit).map_err(|e| ProverError::Keygen {
+                    cause: format!("keygen_vk: {e:?}"),
+                })?;
                 aux.vk = Instant::now().duration_since(time_started).as_millis() as u32;
                 vk
             };
             let pk = {
                 let time_started = Instant::now();
-                let pk = keygen_pk(param.as_ref(), vk, circuit)?;
+                let pk =
+                    keygen_pk(param.as_ref(), vk, circuit).map_err(|e| ProverError::Keygen {
+                        cause: format!("keygen_pk: {e:?}"),
+                    })?;
                 aux.pk = Instant::now().duration_since(time_started).as_millis() as u32;
                 pk
             };
             if std::env::var("PROVERD_DUMP").is_ok() {
-                pk.write(
-                    &mut File::create(cache_key).unwrap(),
-                    SerdeFormat::RawBytesUnchecked,
-                )
-                .unwrap();
+                dump_file(cache_key, |file| {
+                    pk.write(file, SerdeFormat::RawBytesUnchecked)
+                });
             }
 
             let pk = Arc::new(pk);
@@ -4,7 +4,9 @@ use crate::ProverCommitmentScheme;
 use crate::ProverKey;
 use crate::ProverParams;
 use eth_types::U256;
+use halo2_proofs::dev::FailureLocation;
 use halo2_proofs::dev::MockProver;
+use halo2_proofs::dev::VerifyFailure;
 use halo2_proofs::halo2curves::ff::PrimeField;
 use halo2_proofs::plonk::create_proof;
 use halo2_proofs::plonk::verify_proof;
@@ -17,13 +19,39 @@ use halo2_proofs::poly::kzg::strategy::SingleStrategy;
 use halo2_proofs::transcript::EncodedChallenge;
 use halo2_proofs::transcript::TranscriptReadBuffer;
 use halo2_proofs::transcript::TranscriptWriterBuffer;
+use rand::rngs::OsRng;
 use rand::rngs::StdRng;
 use rand::Rng;
+use rand::RngCore;
 use rand::SeedableRng;
 use std::clone::Clone;
 use std::io::Cursor;
 use std::time::Instant;
+use zkevm_common::prover::MockProverDiagnostics;
+use zkevm_common::prover::MockProverFailure;
+use zkevm_common::prover::ProofRandomness;
 use zkevm_common::prover::ProofResultInstrumentation;
+use zkevm_common::prover::ProverError;
+
+/// Upper limit of failures that are kept in `MockProverDiagnostics`.
+const MAX_DIAGNOSTIC_FAILURES: usize = 32;
+
+/// The error of `gen_proof`.
+#[derive(Debug)]
+pub struct GenProofFailure {
+    pub error: ProverError,
+    /// the full `MockProver` report if `mock_feedback` is enabled
+    pub report: Option<String>,
+}
+
+impl From<ProverError> for GenProofFailure {
+    fn from(error: ProverError) -> Self {
+        Self {
+            error,
+            report: None,
+        }
+    }
+}
 
 /// Returns [<len>, ...] of `instance`
 pub fn gen_num_instance(instance: &[Vec<Fr>]) -> Vec<usize> {
@@ -31,7 +59,8 @@ pub fn gen_num_instance(instance: &[Vec<Fr>]) -> Vec<usize> {
 }
 
 /// Returns the finalized transcript.
-/// Runs the MockProver on `create_proof` error and panics afterwards.
+/// If `mock_feedback` is enabled, runs the MockProver on `create_proof` or
+/// `verify_proof` errors and attaches its diagnostics to the error.
 #[allow(clippy::too_many_arguments)]
 pub fn gen_proof<
     C: Circuit<Fr> + Clone,
@@ -48,7 +77,7 @@ pub fn gen_proof<
     mock_feedback: bool,
     verify: bool,
     aux: &mut ProofResultInstrumentation,
-) -> Vec<u8> {
+) -> Result<Vec<u8>, GenProofFailure> {
     let mut transcript = TW::init(Vec::new());
     let inputs: Vec<&[Fr]> = instance.iter().map(|v| v.as_slice()).collect();
     let res = {
@@ -64,21 +93,24 @@ pub fn gen_proof<
         aux.proof = Instant::now().duration_since(time_started).as_millis() as u32;
         v
     };
-    // run the `MockProver` and return (hopefully) useful errors
     if let Err(proof_err) = res {
-        if mock_feedback {
-            let res = {
-                let time_started = Instant::now();
-                let v = MockProver::run(params.k(), &circuit, instance)
-                    .expect("MockProver::run")
-                    .verify_par();
-                aux.mock = Instant::now().duration_since(time_started).as_millis() as u32;
-                v
-            };
-            panic!("gen_proof: {proof_err:#?}\nMockProver: {res:#?}");
-        } else {
-            panic!("gen_proof: {proof_err:#?}");
+        let cause = format!("create_proof: {proof_err:?}");
+        if !mock_feedback {
+            return Err(ProverError::Proving {
+                cause,
+                diagnostics: None,
+            }
+            .into());
         }
+        // run the `MockProver` and return (hopefully) useful errors
+        let (diagnostics, report) = mock_feedback_run(params.k(), &circuit, instance, aux)?;
+        return Err(GenProofFailure {
+            error: ProverError::Proving {
+                cause,
+                diagnostics: Some(diagnostics),
+            },
+            report: Some(report),
+        });
     }
 
     let proof = transcript.finalize();
@@ -98,23 +130,140 @@ pub fn gen_proof<
         };
 
         if let Err(verify_err) = res {
-            if mock_feedback {
-                let res = {
-                    let time_started = Instant::now();
-                    let v = MockProver::run(params.k(), &circuit, instance)
-                        .expect("MockProver::run")
-                        .verify_par();
-                    aux.mock = Instant::now().duration_since(time_started).as_millis() as u32;
-                    v
-                };
-                panic!("verify_proof: {verify_err:#?}\nMockProver: {res:#?}");
-            } else {
-                panic!("verify_proof: {verify_err:#?}");
+            let cause = format!("verify_proof: {verify_err:?}");
+            if !mock_feedback {
+                return Err(ProverError::Verification {
+                    cause,
+                    diagnostics: None,
+                }
+                .into());
             }
+            let (diagnostics, report) = mock_feedback_run(params.k(), &circuit, instance, aux)?;
+            return Err(GenProofFailure {
+                error: ProverError::Verification {
+                    cause,
+                    diagnostics: Some(diagnostics),
+                },
+                report: Some(report),
+            });
+        }
+    }
+
+    Ok(proof)
+}
+
+/// Runs the `MockProver` and returns the diagnostics and the full report.
+fn mock_feedback_run<C: Circuit<Fr>>(
+    k: u32,
+    circuit: &C,
+    instance: Vec<Vec<Fr>>,
+    aux: &mut ProofResultInstrumentation,
+) -> Result<(MockProverDiagnostics, String), GenProofFailure> {
+    let res = {
+        let time_started = Instant::now();
+        let v = MockProver::run(k, circuit, instance)
+            .map_err(|e| ProverError::Proving {
+                cause: format!("MockProver::run: {e:?}"),
+                diagnostics: None,
+            })?
+            .verify_par();
+        aux.mock = Instant::now().duration_since(time_started).as_millis() as u32;
+        v
+    };
+    let failures = res.err().unwrap_or_default();
+
+    Ok((mock_diagnostics(&failures), format!("{failures:#?}")))
+}
+
+/// Summarizes the failures of `MockProver::verify_par`.
+/// Only the first `MAX_DIAGNOSTIC_FAILURES` failures are kept.
+pub fn mock_diagnostics(failures: &[VerifyFailure]) -> MockProverDiagnostics {
+    MockProverDiagnostics {
+        num_failures: failures.len(),
+        failures: failures
+            .iter()
+            .take(MAX_DIAGNOSTIC_FAILURES)
+            .map(mock_failure)
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn mock_failure(failure: &VerifyFailure) -> MockProverFailure {
+    let mut ret = MockProverFailure {
+        kind: "other".into(),
+        name: failure.to_string(),
+        ..Default::default()
+    };
+    match failure {
+        VerifyFailure::CellNotAssigned {
+            gate,
+            region,
+            offset,
+            ..
+        } => {
+            ret.kind = "cell_not_assigned".into();
+            ret.name = gate.to_string();
+            ret.region = Some(region.to_string());
+            ret.offset = Some(*offset as i64);
+        }
+        VerifyFailure::ConstraintNotSatisfied {
+            constraint,
+            location,
+            ..
+        } => {
+            set_location(&mut ret, location);
+            ret.kind = "constraint".into();
+            ret.name = constraint.to_string();
         }
+        VerifyFailure::ConstraintPoisoned { constraint, .. } => {
+            ret.kind = "constraint_poisoned".into();
+            ret.name = constraint.to_string();
+        }
+        VerifyFailure::Lookup { location, .. } => {
+            set_location(&mut ret, location);
+            ret.kind = "lookup".into();
+        }
+        VerifyFailure::Permutation { location, .. } => {
+            set_location(&mut ret, location);
+            ret.kind = "permutation".into();
+        }
+        #[allow(unreachable_patterns)]
+        _ => {}
     }
 
-    proof
+    ret.sub_circuit = sub_circuit_hint(&ret);
+    ret
+}
+
+fn set_location(failure: &mut MockProverFailure, location: &FailureLocation) {
+    match location {
+        FailureLocation::InRegion { region, offset } => {
+            failure.region = Some(region.to_string());
+            failure.offset = Some(*offset as i64);
+        }
+        FailureLocation::OutsideRegion { row } => {
+            failure.row = Some(*row);
+        }
+    }
+}
+
+/// Guesses the sub-circuit from region or gate names.
+fn sub_circuit_hint(failure: &MockProverFailure) -> Option<String> {
+    const SUB_CIRCUITS: [&str; 8] = [
+        "evm", "state", "tx", "bytecode", "copy", "exp", "keccak", "pi",
+    ];
+    let haystack = format!(
+        "{} {}",
+        failure.region.as_deref().unwrap_or_default(),
+        failure.name
+    )
+    .to_lowercase();
+
+    haystack
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .find(|word| SUB_CIRCUITS.contains(word))
+        .map(|word| word.to_string())
 }
 
 /// Fixed rng for testing purposes
@@ -122,6 +271,21 @@ pub fn fixed_rng() -> StdRng {
     StdRng::seed_from_u64(9)
 }
 
+/// Returns the rng for the blinding factors of `create_proof`
+/// and its seed if the proof is meant to be reproducible.
+/// The seed of `ProofRandomness::Fixed` is drawn from `fixed_rng`,
+/// the seed of `ProofRandomness::Os` never leaves this function.
+pub fn proof_rng(randomness: ProofRandomness) -> (StdRng, Option<[u8; 32]>) {
+    match randomness {
+        ProofRandomness::Fixed => {
+            let mut seed = [0u8; 32];
+            fixed_rng().fill_bytes(&mut seed);
+            (StdRng::from_seed(seed), Some(seed))
+        }
+        ProofRandomness::Os => (StdRng::from_rng(OsRng).expect("OsRng"), None),
+    }
+}
+
 /// Collect circuit instance as flat vector
 pub fn collect_instance(instance: &[Vec<Fr>]) -> Vec<U256> {
     instance
@@ -0,0 +1,384 @@
+use async_trait::async_trait;
+use bus_mapping::circuit_input_builder::BuilderClient;
+use bus_mapping::circuit_input_builder::CircuitInputBuilder;
+use bus_mapping::circuit_input_builder::CircuitsParams;
+use bus_mapping::mock::BlockData;
+use bus_mapping::rpc::GethClient;
+use eth_types::geth_types;
+use eth_types::geth_types::Account;
+use eth_types::geth_types::GethData;
+use eth_types::Address;
+use eth_types::Bytes;
+use eth_types::Word;
+use eth_types::H256;
+use ethers_providers::Http;
+use ethers_providers::JsonRpcClient;
+use external_tracer::LoggerConfig;
+use external_tracer::TraceConfig;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::str::FromStr;
+use zkevm_common::prover::WitnessSource;
+
+pub type EthBlock = eth_types::Block<eth_types::Transaction>;
+
+/// Number of concurrent block requests of `PrestateProvider::history`
+const HISTORY_CONCURRENCY: usize = 32;
+
+/// Gathers the block data and traces for `CircuitWitness`.
+#[async_trait]
+pub trait WitnessProvider: Send + Sync {
+    /// Returns block `block_num` including transactions, fetched by `block_hash` if given.
+    async fn get_block(
+        &self,
+        block_num: u64,
+        block_hash: Option<&H256>,
+    ) -> Result<EthBlock, Box<dyn std::error::Error>>;
+
+    /// Returns the circuit inputs of block `block_num`, fetched by `block_hash` if given.
+    /// Providers that can only fetch by number ignore `block_hash`,
+    /// `CircuitWitness::from_provider` checks the hash of the returned block.
+    async fn gen_inputs(
+        &self,
+        block_num: u64,
+        block_hash: Option<&H256>,
+        circuit_params: CircuitsParams,
+    ) -> Result<(CircuitInputBuilder, EthBlock), Box<dyn std::error::Error>>;
+}
+
+/// Returns the provider for `source`.
+/// `trace_dir` is the directory of the prover used for `WitnessSource::TraceDir`.
+pub fn witness_provider(
+    source: &WitnessSource,
+    rpc_url: &str,
+    trace_dir: Option<&String>,
+) -> Result<Box<dyn WitnessProvider>, String> {
+    match source {
+        WitnessSource::GethDebug => Ok(Box::new(GethDebugProvider::new(rpc_url))),
+        WitnessSource::Prestate => Ok(Box::new(PrestateProvider::new(rpc_url))),
+        WitnessSource::TraceDir => match trace_dir {
+            Some(dir) => Ok(Box::new(TraceDirProvider::new(dir))),
+            None => Err("no trace directory configured".to_string()),
+        },
+    }
+}
+
+/// Uses the struct logger output of `debug_traceBlockByNumber`.
+/// Expects a go-ethereum node with debug & archive capabilities on `rpc_url`.
+pub struct GethDebugProvider {
+    rpc_url: String,
+}
+
+impl GethDebugProvider {
+    pub fn new(rpc_url: &str) -> Self {
+        Self {
+            rpc_url: rpc_url.to_string(),
+        }
+    }
+
+    fn client(&self) -> Result<GethClient<Http>, Box<dyn std::error::Error>> {
+        Ok(GethClient::new(Http::from_str(&self.rpc_url)?))
+    }
+}
+
+#[async_trait]
+impl WitnessProvider for GethDebugProvider {
+    async fn get_block(
+        &self,
+        block_num: u64,
+        block_hash: Option<&H256>,
+    ) -> Result<EthBlock, Box<dyn std::error::Error>> {
+        // TODO: add support for `eth_getHeaderByNumber`
+        let client = self.client()?;
+        match block_hash {
+            Some(hash) => Ok(client.get_block_by_hash(*hash).await?),
+            None => Ok(client.get_block_by_number(block_num.into()).await?),
+        }
+    }
+
+    async fn gen_inputs(
+        &self,
+        block_num: u64,
+        _block_hash: Option<&H256>,
+        circuit_params: CircuitsParams,
+    ) -> Result<(CircuitInputBuilder, EthBlock), Box<dyn std::error::Error>> {
+        // `BuilderClient` only fetches by number
+        let builder = BuilderClient::new(self.client()?, circuit_params).await?;
+        Ok(builder.gen_inputs(block_num).await?)
+    }
+}
+
+/// Uses the `prestateTracer` output of `debug_traceBlockByHash` and re-executes the block.
+/// Works with nodes or proxies that only expose this tracer and recent state.
+///
+/// Limitations: the node still needs the `debug` namespace and the state of the parent block
+/// to trace it, a non-archive node only keeps the state of recent blocks.
+/// The history hashes cost up to 256 `eth_getBlockByNumber` requests per block,
+/// see `HISTORY_CONCURRENCY`.
+pub struct PrestateProvider {
+    rpc_url: String,
+}
+
+impl PrestateProvider {
+    pub fn new(rpc_url: &str) -> Self {
+        Self {
+            rpc_url: rpc_url.to_string(),
+        }
+    }
+
+    async fn request<T: serde::Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: T,
+    ) -> Result<R, Box<dyn std::error::Error>> {
+        let client = Http::from_str(&self.rpc_url)?;
+        Ok(client.request(method, params).await?)
+    }
+
+    /// Returns the hashes of the up to 256 blocks before `block_num`, oldest first,
+    /// and the state root of the parent block.
+    /// Fetches the blocks by number, `CircuitWitness::from_provider` only checks the hash of
+    /// block `block_num`.
+    pub async fn history(
+        &self,
+        block_num: u64,
+    ) -> Result<(Vec<Word>, H256), Box<dyn std::error::Error>> {
+        let nums: Vec<u64> = (block_num.saturating_sub(256)..block_num).collect();
+        let mut history_hashes = Vec::with_capacity(nums.len());
+        let mut prev_state_root = H256::zero();
+
+        for chunk in nums.chunks(HISTORY_CONCURRENCY) {
+            let tasks: Vec<_> = chunk
+                .iter()
+                .map(|num| {
+                    let provider = Self::new(&self.rpc_url);
+                    let num = format!("{num:#x}");
+                    tokio::spawn(async move {
+                        provider
+                            .request::<_, eth_types::Block<H256>>(
+                                "eth_getBlockByNumber",
+                                (num, false),
+                            )
+                            .await
+                            .map_err(|e| e.to_string())
+                    })
+                })
+                .collect();
+
+            for task in tasks {
+                let block = task.await??;
+                history_hashes.push(Word::from_big_endian(
+                    block.hash.unwrap_or_default().as_bytes(),
+                ));
+                prev_state_root = block.state_root;
+            }
+        }
+
+        Ok((history_hashes, prev_state_root))
+    }
+}
+
+#[async_trait]
+impl WitnessProvider for PrestateProvider {
+    async fn get_block(
+        &self,
+        block_num: u64,
+        block_hash: Option<&H256>,
+    ) -> Result<EthBlock, Box<dyn std::error::Error>> {
+        match block_hash {
+            Some(hash) => self.request("eth_getBlockByHash", (hash, true)).await,
+            None => {
+                self.request("eth_getBlockByNumber", (format!("{block_num:#x}"), true))
+                    .await
+            }
+        }
+    }
+
+    async fn gen_inputs(
+        &self,
+        block_num: u64,
+        block_hash: Option<&H256>,
+        circuit_params: CircuitsParams,
+    ) -> Result<(CircuitInputBuilder, EthBlock), Box<dyn std::error::Error>> {
+        let eth_block = self.get_block(block_num, block_hash).await?;
+        let chain_id: Word = self.request("eth_chainId", ()).await?;
+        let tracer = serde_json::json!({ "tracer": "prestateTracer" });
+        let prestate: Vec<PrestateTrace> = match block_hash {
+            Some(hash) => {
+                self.request("debug_traceBlockByHash", (hash, tracer))
+                    .await?
+            }
+            None => {
+                self.request(
+                    "debug_traceBlockByNumber",
+                    (format!("{block_num:#x}"), tracer),
+                )
+                .await?
+            }
+        };
+
+        let (history_hashes, prev_state_root) = self.history(block_num).await?;
+
+        gen_inputs_from_prestate(
+            chain_id,
+            history_hashes,
+            prev_state_root,
+            eth_block,
+            prestate,
+            circuit_params,
+        )
+    }
+}
+
+/// Reads `block.json`, `parent.json`, `chain_id.json`, `prestate.json` and
+/// `block_hashes.json` from `<dir>/<block number>/` and re-executes the block.
+/// See `scripts/get_block_fixtures.sh`.
+pub struct TraceDirProvider {
+    dir: PathBuf,
+}
+
+impl TraceDirProvider {
+    pub fn new(dir: &str) -> Self {
+        Self {
+            dir: PathBuf::from(dir),
+        }
+    }
+
+    fn read_json<T: DeserializeOwned>(
+        &self,
+        block_num: u64,
+        name: &str,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let path = self.dir.join(block_num.to_string()).join(name);
+        let file = File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+}
+
+#[async_trait]
+impl WitnessProvider for TraceDirProvider {
+    async fn get_block(
+        &self,
+        block_num: u64,
+        _block_hash: Option<&H256>,
+    ) -> Result<EthBlock, Box<dyn std::error::Error>> {
+        self.read_json(block_num, "block.json")
+    }
+
+    async fn gen_inputs(
+        &self,
+        block_num: u64,
+        block_hash: Option<&H256>,
+        circuit_params: CircuitsParams,
+    ) -> Result<(CircuitInputBuilder, EthBlock), Box<dyn std::error::Error>> {
+        let eth_block = self.get_block(block_num, block_hash).await?;
+        let parent: eth_types::Block<H256> = self.read_json(block_num, "parent.json")?;
+        let chain_id: Word = self.read_json(block_num, "chain_id.json")?;
+        let prestate: Vec<PrestateTrace> = self.read_json(block_num, "prestate.json")?;
+        let block_hashes: Vec<H256> = self.read_json(block_num, "block_hashes.json")?;
+        // the list is padded with zero hashes for blocks < 256
+        let skip = block_hashes
+            .len()
+            .saturating_sub(block_num.min(256) as usize);
+        let history_hashes = block_hashes[skip..]
+            .iter()
+            .map(|hash| Word::from_big_endian(hash.as_bytes()))
+            .collect();
+
+        gen_inputs_from_prestate(
+            chain_id,
+            history_hashes,
+            parent.state_root,
+            eth_block,
+            prestate,
+            circuit_params,
+        )
+    }
+}
+
+/// A transaction item of the `prestateTracer` output.
+#[derive(Deserialize)]
+struct PrestateTrace {
+    result: HashMap<Address, PrestateAccount>,
+}
+
+#[derive(Deserialize)]
+struct PrestateAccount {
+    #[serde(default)]
+    balance: Word,
+    #[serde(default)]
+    nonce: u64,
+    #[serde(default)]
+    code: Bytes,
+    #[serde(default)]
+    storage: HashMap<H256, H256>,
+}
+
+/// Re-executes the transactions of `eth_block` on top of `prestate`
+/// and builds the circuit inputs from the resulting traces.
+fn gen_inputs_from_prestate(
+    chain_id: Word,
+    history_hashes: Vec<Word>,
+    prev_state_root: H256,
+    eth_block: EthBlock,
+    prestate: Vec<PrestateTrace>,
+    circuit_params: CircuitsParams,
+) -> Result<(CircuitInputBuilder, EthBlock), Box<dyn std::error::Error>> {
+    // the first occurrence of an account or storage slot is the state before the block
+    let mut accounts: HashMap<Address, Account> = HashMap::new();
+    for trace in prestate {
+        for (address, state) in trace.result {
+            let account = accounts.entry(address).or_insert_with(|| Account {
+                address,
+                nonce: state.nonce.into(),
+                balance: state.balance,
+                code: state.code.clone(),
+                storage: HashMap::new(),
+            });
+            for (key, value) in state.storage {
+                account
+                    .storage
+                    .entry(Word::from_big_endian(key.as_bytes()))
+                    .or_insert_with(|| Word::from_big_endian(value.as_bytes()));
+            }
+        }
+    }
+
+    let block_constants = geth_types::BlockConstants {
+        coinbase: eth_block.author.unwrap_or_default(),
+        timestamp: eth_block.timestamp,
+        number: eth_block.number.unwrap_or_default(),
+        difficulty: eth_block.difficulty,
+        gas_limit: eth_block.gas_limit,
+        base_fee: eth_block.base_fee_per_gas.unwrap_or_default(),
+    };
+    let geth_traces = external_tracer::trace(&TraceConfig {
+        chain_id,
+        history_hashes: history_hashes.clone(),
+        block_constants,
+        accounts: accounts.clone(),
+        transactions: eth_block
+            .transactions
+            .iter()
+            .map(geth_types::Transaction::from)
+            .collect(),
+        logger_config: LoggerConfig::default(),
+    })?;
+
+    let geth_data = GethData {
+        chain_id,
+        history_hashes,
+        eth_block,
+        geth_traces,
+        accounts: accounts.into_values().collect(),
+    };
+    let mut builder = BlockData::new_from_geth_data_with_params(geth_data.clone(), circuit_params)
+        .new_circuit_input_builder();
+    builder.handle_block(&geth_data.eth_block, &geth_data.geth_traces)?;
+    builder.block.prev_state_root = Word::from_big_endian(prev_state_root.as_bytes());
+
+    Ok((builder, geth_data.eth_block))
+}
@@ -0,0 +1,105 @@
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Selector};
+use halo2_proofs::poly::Rotation;
+use prover::utils::mock_diagnostics;
+use prover::Fr;
+
+/// Constrains `a` to be boolean, assigns `2` to the first `rows` rows of region `region`
+/// and enables the gate for one more row without assigning it.
+#[derive(Clone, Default)]
+struct BoolCircuit {
+    region: &'static str,
+    rows: usize,
+}
+
+impl Circuit<Fr> for BoolCircuit {
+    type Config = (Selector, Column<Advice>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let q = meta.selector();
+        let a = meta.advice_column();
+        meta.create_gate("bool", |meta| {
+            let q = meta.query_selector(q);
+            let a = meta.query_advice(a, Rotation::cur());
+            vec![q * a.clone() * (Expression::Constant(Fr::from(1)) - a)]
+        });
+
+        (q, a)
+    }
+
+    fn synthesize(
+        &self,
+        (q, a): Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || self.region,
+            |mut region| {
+                for offset in 0..self.rows {
+                    q.enable(&mut region, offset)?;
+                    region.assign_advice(|| "a", a, offset, || Value::known(Fr::from(2)))?;
+                }
+                q.enable(&mut region, self.rows)
+            },
+        )
+    }
+}
+
+fn diagnostics(circuit: BoolCircuit) -> zkevm_common::prover::MockProverDiagnostics {
+    let failures = MockProver::run(7, &circuit, vec![])
+        .expect("MockProver::run")
+        .verify_par()
+        .expect_err("failures");
+
+    mock_diagnostics(&failures)
+}
+
+#[test]
+fn mock_diagnostics_failures() {
+    let diag = diagnostics(BoolCircuit {
+        region: "state: bool",
+        rows: 1,
+    });
+    assert_eq!(diag.num_failures, 2);
+
+    let constraint = diag
+        .failures
+        .iter()
+        .find(|failure| failure.kind == "constraint")
+        .expect("constraint");
+    assert!(constraint.name.contains("bool"), "{constraint}");
+    assert!(constraint.region.as_ref().unwrap().contains("state: bool"));
+    assert_eq!(constraint.offset, Some(0));
+    assert_eq!(constraint.row, None);
+    assert_eq!(constraint.sub_circuit.as_deref(), Some("state"));
+
+    let unassigned = diag
+        .failures
+        .iter()
+        .find(|failure| failure.kind == "cell_not_assigned")
+        .expect("cell_not_assigned");
+    assert!(unassigned.name.contains("bool"), "{unassigned}");
+    assert_eq!(unassigned.offset, Some(1));
+    assert_eq!(unassigned.sub_circuit.as_deref(), Some("state"));
+}
+
+#[test]
+fn mock_diagnostics_limits_failures() {
+    let diag = diagnostics(BoolCircuit {
+        region: "assignment",
+        rows: 40,
+    });
+    assert_eq!(diag.num_failures, 41);
+    assert_eq!(diag.failures.len(), 32);
+    // no sub-circuit in the region or gate name
+    assert!(diag
+        .failures
+        .iter()
+        .all(|failure| failure.sub_circuit.is_none()));
+}
@@ -0,0 +1,100 @@
+use eth_types::Word;
+use eth_types::H256;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use prover::witness_provider::*;
+use zkevm_common::prover::WitnessSource;
+
+/// Block `num` with hash `num + 1` and state root `num + 1000`.
+fn block(num: u64) -> serde_json::Value {
+    serde_json::json!({
+        "number": format!("{num:#x}"),
+        "hash": H256::from_low_u64_be(num + 1),
+        "parentHash": H256::from_low_u64_be(num),
+        "nonce": "0x0000000000000000",
+        "sha3Uncles": H256::zero(),
+        "logsBloom": format!("0x{}", "00".repeat(256)),
+        "transactionsRoot": H256::zero(),
+        "stateRoot": H256::from_low_u64_be(num + 1000),
+        "receiptsRoot": H256::zero(),
+        "miner": "0x0000000000000000000000000000000000000000",
+        "difficulty": "0x0",
+        "totalDifficulty": "0x0",
+        "extraData": "0x",
+        "size": "0x0",
+        "gasLimit": "0x0",
+        "gasUsed": "0x0",
+        "timestamp": "0x0",
+        "transactions": [],
+        "uncles": [],
+        "mixHash": H256::zero(),
+        "baseFeePerGas": "0x0",
+    })
+}
+
+/// Serves `eth_getBlockByNumber` with `block`, returns the url.
+async fn serve_blocks() -> String {
+    let service = make_service_fn(|_| async {
+        Ok::<_, hyper::Error>(service_fn(|req: Request<Body>| async move {
+            let body = hyper::body::to_bytes(req.into_body()).await?;
+            let req: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(req["method"], "eth_getBlockByNumber");
+            let num = req["params"][0].as_str().unwrap().trim_start_matches("0x");
+            let num = u64::from_str_radix(num, 16).unwrap();
+            let resp = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": req["id"],
+                "result": block(num),
+            });
+
+            Ok::<_, hyper::Error>(Response::new(Body::from(resp.to_string())))
+        }))
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(service);
+    let url = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+
+    url
+}
+
+#[tokio::test]
+async fn prestate_provider_history() {
+    let url = serve_blocks().await;
+    let provider = PrestateProvider::new(&url);
+
+    let (hashes, state_root) = provider.history(300).await.expect("history");
+    let expected: Vec<Word> = (44..300u64).map(|num| Word::from(num + 1)).collect();
+    assert_eq!(hashes, expected);
+    assert_eq!(state_root, H256::from_low_u64_be(299 + 1000));
+
+    // less than 256 blocks
+    let (hashes, state_root) = provider.history(3).await.expect("history");
+    assert_eq!(hashes, vec![Word::from(1), Word::from(2), Word::from(3)]);
+    assert_eq!(state_root, H256::from_low_u64_be(2 + 1000));
+
+    let block = provider.get_block(7, None).await.expect("get_block");
+    assert_eq!(block.hash, Some(H256::from_low_u64_be(8)));
+}
+
+#[tokio::test]
+async fn trace_dir_provider() {
+    let path = std::env::temp_dir().join(format!("trace-dir-{}", rand::random::<u64>()));
+    std::fs::create_dir_all(path.join("5")).unwrap();
+    std::fs::write(path.join("5").join("block.json"), block(5).to_string()).unwrap();
+    let dir = path.to_str().unwrap().to_string();
+
+    let provider = witness_provider(&WitnessSource::TraceDir, "", Some(&dir)).expect("provider");
+    let found = provider.get_block(5, None).await;
+    let missing = provider.get_block(6, None).await;
+    // before the asserts, to not leak the directory
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(
+        found.expect("get_block").hash,
+        Some(H256::from_low_u64_be(6))
+    );
+    assert!(missing.is_err());
+
+    // the prover needs a trace directory
+    assert!(witness_provider(&WitnessSource::TraceDir, "", None).is_err());
+}
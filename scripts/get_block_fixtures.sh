@@ -5,6 +5,16 @@ blockNumber=$2
 
 set -e
 
+if [ -z "$url" ] || [ -z "$blockNumber" ]; then
+  echo 'usage: get_block_fixtures.sh rpc_url block_number' >&2
+  exit 1
+fi
+# the genesis block has no parent and can't be traced
+if [ "$blockNumber" -lt 1 ]; then
+  echo 'block_number must be at least 1' >&2
+  exit 1
+fi
+
 rpc() {
   curl \
     --silent \
@@ -22,6 +32,12 @@ blockHex=$(hex $blockNumber)
 echo '### block.json'
 rpc 'eth_getBlockByNumber' '"'$blockHex'",true' | jq '.result' > block.json
 
+echo '### parent.json'
+rpc 'eth_getBlockByNumber' '"'$(hex $(($blockNumber - 1)))'",false' | jq '.result' > parent.json
+
+echo '### chain_id.json'
+rpc 'eth_chainId' '' | jq '.result' > chain_id.json
+
 echo '### prestate.json'
 rpc 'debug_traceBlockByNumber' '"'$blockHex'",{"tracer":"prestateTracer"}' | jq '.result' > prestate.json
 